@@ -0,0 +1,219 @@
+use anyhow::{Context, Result, bail};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+use crate::backend::{BackendFuture, LoadProgress, StoreBackend};
+use crate::store_path::StorePath;
+
+/// The contents of a `<hash>.narinfo` file
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct NarInfo {
+    pub store_path: String,
+    pub url: Option<String>,
+    pub compression: Option<String>,
+    pub file_size: Option<u64>,
+    pub nar_size: u64,
+    /// Full store paths, expanded from the base names in the file
+    pub references: Vec<String>,
+    pub deriver: Option<String>,
+    pub signatures: Vec<String>,
+    pub ca: Option<String>,
+}
+
+impl NarInfo {
+    pub fn parse(contents: &str) -> Result<Self> {
+        let mut info = NarInfo::default();
+        let mut references = Vec::new();
+        let mut deriver = None;
+        let mut nar_size = None;
+
+        for (line_no, line) in contents.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let Some((key, value)) = line.split_once(": ") else {
+                bail!("Malformed narinfo line {}: {}", line_no + 1, line);
+            };
+
+            let parse_size = |value: &str| {
+                value
+                    .parse::<u64>()
+                    .with_context(|| format!("Invalid {key} on line {}", line_no + 1))
+            };
+
+            match key {
+                "StorePath" => info.store_path = value.to_string(),
+                "URL" => info.url = Some(value.to_string()),
+                "Compression" => info.compression = Some(value.to_string()),
+                "FileSize" => info.file_size = Some(parse_size(value)?),
+                "NarSize" => nar_size = Some(parse_size(value)?),
+                "References" => references = value.split_whitespace().collect(),
+                "Deriver" if value != "unknown-deriver" => deriver = Some(value),
+                "Sig" => info.signatures.push(value.to_string()),
+                "CA" => info.ca = Some(value.to_string()),
+                _ => {}
+            }
+        }
+
+        if info.store_path.is_empty() {
+            bail!("narinfo has no StorePath");
+        }
+        info.nar_size = nar_size.context("narinfo has no NarSize")?;
+
+        // References and Deriver are base names relative to the store directory
+        let store_dir = Path::new(&info.store_path)
+            .parent()
+            .context("StorePath has no store directory")?;
+        info.references = references
+            .into_iter()
+            .map(|r| store_dir.join(r).to_string_lossy().to_string())
+            .collect();
+        info.deriver = deriver.map(|d| store_dir.join(d).to_string_lossy().to_string());
+
+        Ok(info)
+    }
+
+    pub fn into_store_path(self) -> Result<StorePath> {
+        let (hash, name) = StorePath::parse(&self.store_path)?;
+
+        Ok(StorePath {
+            path: self.store_path,
+            hash,
+            name,
+            nar_size: self.nar_size,
+            closure_size: None,
+            references: self.references,
+            signatures: self.signatures,
+            registration_time: None,
+            deriver: self.deriver,
+            ca: self.ca,
+            file_size: self.file_size,
+            compression: self.compression,
+            missing: false,
+        })
+    }
+}
+
+/// Reads a binary cache on the local file system, the layout produced by
+/// `nix copy --to file://...`, without going through the nix daemon
+#[derive(Debug, Clone)]
+pub struct BinaryCacheBackend {
+    cache_dir: PathBuf,
+}
+
+impl BinaryCacheBackend {
+    pub fn new(cache_dir: impl Into<PathBuf>) -> Self {
+        Self {
+            cache_dir: cache_dir.into(),
+        }
+    }
+
+    /// Accepts `file:///path/to/cache` store URLs, ignoring any parameters
+    pub fn from_url(url: &str) -> Option<Self> {
+        let path = url.strip_prefix("file://")?;
+        let path = path.split_once('?').map_or(path, |(path, _)| path);
+        Some(Self::new(path))
+    }
+
+    async fn read_narinfo(&self, hash: &str) -> Result<NarInfo> {
+        let file = self.cache_dir.join(format!("{hash}.narinfo"));
+        let contents = tokio::fs::read_to_string(&file)
+            .await
+            .with_context(|| format!("Failed to read {}", file.display()))?;
+        NarInfo::parse(&contents).with_context(|| format!("Failed to parse {}", file.display()))
+    }
+
+    async fn read_all(&self, progress: &LoadProgress) -> Result<Vec<NarInfo>> {
+        let mut entries = tokio::fs::read_dir(&self.cache_dir)
+            .await
+            .with_context(|| format!("Failed to read {}", self.cache_dir.display()))?;
+
+        let mut infos = Vec::new();
+        while let Some(entry) = entries.next_entry().await? {
+            let file_name = entry.file_name();
+            if let Some(hash) = file_name.to_string_lossy().strip_suffix(".narinfo") {
+                infos.push(self.read_narinfo(hash).await?);
+                progress.set_parsed(infos.len());
+            }
+        }
+        Ok(infos)
+    }
+}
+
+impl StoreBackend for BinaryCacheBackend {
+    /// Without installables, every path in the cache that no other cached
+    /// path refers to is treated as a root.
+    fn resolve_roots<'a>(
+        &'a self,
+        installables: &'a [String],
+        progress: &'a LoadProgress,
+    ) -> BackendFuture<'a, Vec<String>> {
+        Box::pin(async move {
+            if installables.is_empty() {
+                let infos = self.read_all(progress).await?;
+                let referenced: HashSet<&String> = infos
+                    .iter()
+                    .flat_map(|i| i.references.iter().filter(move |r| **r != i.store_path))
+                    .collect();
+                let mut roots: Vec<String> = infos
+                    .iter()
+                    .map(|i| &i.store_path)
+                    .filter(|p| !referenced.contains(p))
+                    .cloned()
+                    .collect();
+                roots.sort();
+                return Ok(roots);
+            }
+
+            let mut roots = Vec::new();
+            for installable in installables {
+                let mut path = installable.clone();
+                // Resolve symlinks for paths outside the Nix store
+                if !path.starts_with("/nix/store/")
+                    && let Ok(resolved) = tokio::fs::canonicalize(&path).await
+                {
+                    path = resolved.to_string_lossy().to_string();
+                }
+
+                let (hash, _) = StorePath::parse(&path)?;
+                let info = self
+                    .read_narinfo(&hash)
+                    .await
+                    .with_context(|| format!("{installable} is not in the binary cache"))?;
+                roots.push(info.store_path);
+            }
+            Ok(roots)
+        })
+    }
+
+    fn query_closure<'a>(
+        &'a self,
+        roots: &'a [String],
+        progress: &'a LoadProgress,
+    ) -> BackendFuture<'a, Vec<StorePath>> {
+        Box::pin(async move {
+            let mut seen = HashSet::new();
+            let mut to_visit: Vec<String> = roots.to_vec();
+            let mut paths = Vec::new();
+
+            while let Some(current) = to_visit.pop() {
+                if !seen.insert(current.clone()) {
+                    continue;
+                }
+
+                let (hash, _) = StorePath::parse(&current)?;
+                let info = self.read_narinfo(&hash).await?;
+                to_visit.extend(
+                    info.references
+                        .iter()
+                        .filter(|r| !seen.contains(*r))
+                        .cloned(),
+                );
+                paths.push(info.into_store_path()?);
+                progress.set_parsed(paths.len());
+            }
+
+            Ok(paths)
+        })
+    }
+}
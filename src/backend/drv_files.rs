@@ -0,0 +1,73 @@
+use anyhow::{Context, bail};
+use std::path::{Path, PathBuf};
+
+use crate::backend::{BackendFuture, LoadProgress, StoreBackend};
+use crate::derivation;
+use crate::store_path::StorePath;
+
+/// Reads the derivation graph of `.drv` files straight from disk, without
+/// nix. Used for `--derivation` when every installable is a `.drv` file,
+/// either in the store or in a directory of copies.
+///
+/// Paths are sized by their file length, which is close to but not quite
+/// their NAR size.
+#[derive(Debug)]
+pub struct DrvFilesBackend {
+    store_dir: PathBuf,
+}
+
+impl DrvFilesBackend {
+    /// The backend for `installables` if they are all `.drv` files in one
+    /// directory
+    pub fn from_installables(installables: &[String]) -> Option<Self> {
+        let mut store_dir = None;
+        for installable in installables {
+            let path = Path::new(installable);
+            if !installable.ends_with(".drv") || !path.is_file() {
+                return None;
+            }
+            let dir = path.parent()?.canonicalize().ok()?;
+            if store_dir.get_or_insert_with(|| dir.clone()) != &dir {
+                return None;
+            }
+        }
+        store_dir.map(|store_dir| Self { store_dir })
+    }
+}
+
+impl StoreBackend for DrvFilesBackend {
+    /// Each file named as the store path it is a copy of
+    fn resolve_roots<'a>(
+        &'a self,
+        installables: &'a [String],
+        _progress: &'a LoadProgress,
+    ) -> BackendFuture<'a, Vec<String>> {
+        Box::pin(async move {
+            installables
+                .iter()
+                .map(|installable| {
+                    let Some(name) = Path::new(installable).file_name() else {
+                        bail!("{installable} is not a .drv file");
+                    };
+                    let root = format!("/nix/store/{}", name.to_string_lossy());
+                    StorePath::parse(&root)
+                        .with_context(|| format!("{installable} is not named like a store path"))?;
+                    Ok(root)
+                })
+                .collect()
+        })
+    }
+
+    fn query_closure<'a>(
+        &'a self,
+        roots: &'a [String],
+        _progress: &'a LoadProgress,
+    ) -> BackendFuture<'a, Vec<StorePath>> {
+        Box::pin(async move {
+            let roots = roots.to_vec();
+            let store_dir = self.store_dir.clone();
+            tokio::task::spawn_blocking(move || derivation::load_closure(&roots, &store_dir))
+                .await?
+        })
+    }
+}
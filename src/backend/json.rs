@@ -0,0 +1,109 @@
+use anyhow::{Context, Result};
+use std::collections::{HashMap, HashSet};
+use tokio::io::AsyncReadExt;
+
+use crate::backend::{BackendFuture, LoadProgress, StoreBackend};
+use crate::nix;
+use crate::store_path::StorePath;
+
+/// Serves a saved `nix path-info --json` dump, in either its map or list form
+#[derive(Debug, Clone, Default)]
+pub struct JsonBackend {
+    paths: Vec<StorePath>,
+}
+
+impl JsonBackend {
+    pub fn parse(json_str: &str) -> Result<Self> {
+        Ok(Self {
+            paths: nix::parse_store_paths(json_str)?,
+        })
+    }
+
+    /// Read a dump from a file, or from stdin when `source` is `-`
+    pub async fn from_source(source: &str) -> Result<Self> {
+        let json_str = if source == "-" {
+            let mut buf = String::new();
+            tokio::io::stdin()
+                .read_to_string(&mut buf)
+                .await
+                .context("Failed to read path info from stdin")?;
+            buf
+        } else {
+            tokio::fs::read_to_string(source)
+                .await
+                .with_context(|| format!("Failed to read {source}"))?
+        };
+
+        Self::parse(&json_str).with_context(|| format!("Invalid path info dump in {source}"))
+    }
+}
+
+impl StoreBackend for JsonBackend {
+    /// Without installables, every path that nothing else in the dump refers
+    /// to is treated as a root.
+    fn resolve_roots<'a>(
+        &'a self,
+        installables: &'a [String],
+        _progress: &'a LoadProgress,
+    ) -> BackendFuture<'a, Vec<String>> {
+        Box::pin(async move {
+            if installables.is_empty() {
+                let referenced: HashSet<&String> = self
+                    .paths
+                    .iter()
+                    .flat_map(|p| p.references.iter().filter(move |r| **r != p.path))
+                    .collect();
+                let mut roots: Vec<String> = self
+                    .paths
+                    .iter()
+                    .map(|p| &p.path)
+                    .filter(|p| !referenced.contains(p))
+                    .cloned()
+                    .collect();
+                roots.sort();
+                return Ok(roots);
+            }
+
+            for root in installables {
+                if !self.paths.iter().any(|p| &p.path == root) {
+                    anyhow::bail!("{} is not in the path info dump", root);
+                }
+            }
+            Ok(installables.to_vec())
+        })
+    }
+
+    /// Walks references from `roots` over the dump, or returns the whole
+    /// dump when there are no roots
+    fn query_closure<'a>(
+        &'a self,
+        roots: &'a [String],
+        _progress: &'a LoadProgress,
+    ) -> BackendFuture<'a, Vec<StorePath>> {
+        Box::pin(async move {
+            if roots.is_empty() {
+                return Ok(self.paths.clone());
+            }
+
+            let paths: HashMap<&String, &StorePath> =
+                self.paths.iter().map(|p| (&p.path, p)).collect();
+            let mut closure = HashSet::new();
+            let mut to_visit: Vec<&String> = roots.iter().collect();
+
+            while let Some(current) = to_visit.pop() {
+                if closure.insert(current)
+                    && let Some(store_path) = paths.get(current)
+                {
+                    to_visit.extend(store_path.references.iter());
+                }
+            }
+
+            Ok(self
+                .paths
+                .iter()
+                .filter(|p| closure.contains(&p.path))
+                .cloned()
+                .collect())
+        })
+    }
+}
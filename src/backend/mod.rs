@@ -0,0 +1,91 @@
+pub mod binary_cache;
+pub mod drv_files;
+pub mod json;
+pub mod nix_cli;
+pub mod progress;
+pub mod sqlite;
+
+pub use binary_cache::BinaryCacheBackend;
+pub use drv_files::DrvFilesBackend;
+pub use json::JsonBackend;
+pub use nix_cli::NixCliBackend;
+pub use progress::{LoadPhase, LoadProgress};
+pub use sqlite::SqliteBackend;
+
+use anyhow::Result;
+use std::future::Future;
+use std::pin::Pin;
+
+use crate::cli::Config;
+use crate::store_path::{StorePath, StorePathGraph};
+
+pub type BackendFuture<'a, T> = Pin<Box<dyn Future<Output = Result<T>> + Send + 'a>>;
+
+/// A source of store path metadata.
+///
+/// Loading happens in two steps: the installables given on the command line
+/// are turned into root store paths, then the closure of those roots is
+/// queried. Implementations don't need a nix installation, and report what
+/// they are doing through `progress`.
+///
+/// Loading may be cancelled by dropping the returned futures, so backends
+/// must not leave processes running behind them.
+pub trait StoreBackend: Send + Sync {
+    /// Turn installables into the store paths used as graph roots.
+    /// An empty list asks the backend for its default roots.
+    fn resolve_roots<'a>(
+        &'a self,
+        installables: &'a [String],
+        progress: &'a LoadProgress,
+    ) -> BackendFuture<'a, Vec<String>>;
+
+    /// Query every path in the closure of `roots`
+    fn query_closure<'a>(
+        &'a self,
+        roots: &'a [String],
+        progress: &'a LoadProgress,
+    ) -> BackendFuture<'a, Vec<StorePath>>;
+}
+
+pub async fn load_graph(
+    backend: &dyn StoreBackend,
+    installables: &[String],
+    progress: &LoadProgress,
+) -> Result<StorePathGraph> {
+    progress.set_phase(LoadPhase::Resolving);
+    let roots = backend.resolve_roots(installables, progress).await?;
+
+    progress.set_phase(LoadPhase::Querying);
+    let paths = backend.query_closure(&roots, progress).await?;
+    progress.set_parsed(paths.len());
+
+    Ok(StorePathGraph::from_paths(paths, roots))
+}
+
+/// Pick the backend selected by the command line options
+pub async fn from_config(config: &Config) -> Result<Box<dyn StoreBackend>> {
+    if let Some(source) = &config.from_json {
+        return Ok(Box::new(JsonBackend::from_source(source).await?));
+    }
+
+    if let Some(db_path) = &config.db {
+        return Ok(Box::new(SqliteBackend::open(db_path).await?));
+    }
+
+    if let Some(cache) = config
+        .store
+        .as_deref()
+        .and_then(BinaryCacheBackend::from_url)
+    {
+        return Ok(Box::new(cache));
+    }
+
+    if config.derivation
+        && config.store.is_none()
+        && let Some(drv_files) = DrvFilesBackend::from_installables(&config.paths)
+    {
+        return Ok(Box::new(drv_files));
+    }
+
+    Ok(Box::new(NixCliBackend::from_config(config)))
+}
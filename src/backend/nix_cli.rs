@@ -0,0 +1,123 @@
+use std::sync::Mutex;
+
+use crate::backend::{BackendFuture, LoadPhase, LoadProgress, StoreBackend};
+use crate::cli::Config;
+use crate::nix;
+use crate::store_path::StorePath;
+
+/// Queries store paths by running the `nix` command line tools
+#[derive(Debug, Default)]
+pub struct NixCliBackend {
+    pub derivation: bool,
+    /// Show paths missing from the store as placeholders instead of
+    /// building or substituting them
+    pub no_realise: bool,
+    pub store: Option<String>,
+    pub nix_options: Vec<(String, String)>,
+    pub file: Option<String>,
+
+    // Roots that were missing from the store when they were resolved
+    missing: Mutex<Vec<String>>,
+}
+
+impl NixCliBackend {
+    pub fn from_config(config: &Config) -> Self {
+        Self {
+            derivation: config.derivation,
+            no_realise: config.no_realise,
+            store: config.store.clone(),
+            nix_options: config.nix_options.clone(),
+            file: config.file.clone(),
+            missing: Mutex::default(),
+        }
+    }
+}
+
+impl StoreBackend for NixCliBackend {
+    fn resolve_roots<'a>(
+        &'a self,
+        installables: &'a [String],
+        progress: &'a LoadProgress,
+    ) -> BackendFuture<'a, Vec<String>> {
+        Box::pin(async move {
+            let mut paths = if installables.is_empty() {
+                nix::get_default_roots().await?
+            } else {
+                installables.to_vec()
+            };
+
+            // Resolve symlinks for paths outside the Nix store
+            for path in &mut paths {
+                if !path.starts_with("/nix/store/")
+                    && let Ok(resolved) = tokio::fs::canonicalize(&path).await
+                {
+                    *path = resolved.to_string_lossy().to_string();
+                }
+            }
+
+            let (resolved, missing) = nix::resolve_paths(
+                &paths,
+                self.derivation,
+                self.store.as_deref(),
+                &self.nix_options,
+                self.file.as_deref(),
+            )
+            .await?;
+
+            if self.no_realise {
+                *self.missing.lock().unwrap() = missing;
+            } else if !missing.is_empty() {
+                progress.set_phase(LoadPhase::Realising);
+                nix::realise_missing(&missing, self.store.as_deref(), &self.nix_options, progress)
+                    .await?;
+            }
+
+            Ok(resolved)
+        })
+    }
+
+    fn query_closure<'a>(
+        &'a self,
+        roots: &'a [String],
+        progress: &'a LoadProgress,
+    ) -> BackendFuture<'a, Vec<StorePath>> {
+        Box::pin(async move {
+            let missing = self.missing.lock().unwrap().clone();
+            let valid: Vec<String> = roots
+                .iter()
+                .filter(|r| !missing.contains(r))
+                .cloned()
+                .collect();
+
+            let mut paths = if valid.is_empty() {
+                Vec::new()
+            } else {
+                nix::query_closure(
+                    &valid,
+                    true,
+                    self.store.as_deref(),
+                    &self.nix_options,
+                    self.file.as_deref(),
+                )
+                .await?
+            };
+
+            if !missing.is_empty() {
+                // Whatever is already in the local store takes precedence
+                let unknown = nix::query_missing(
+                    &missing,
+                    self.store.as_deref(),
+                    &self.nix_options,
+                    progress,
+                )
+                .await?
+                .into_iter()
+                .filter(|m| !paths.iter().any(|p| p.path == m.path))
+                .collect::<Vec<_>>();
+                paths.extend(unknown);
+            }
+
+            Ok(paths)
+        })
+    }
+}
@@ -0,0 +1,71 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub enum LoadPhase {
+    #[default]
+    Resolving,
+    Realising,
+    Querying,
+    ComputingStats,
+}
+
+impl LoadPhase {
+    pub const ALL: [LoadPhase; 4] = [
+        LoadPhase::Resolving,
+        LoadPhase::Realising,
+        LoadPhase::Querying,
+        LoadPhase::ComputingStats,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LoadPhase::Resolving => "Resolving installables",
+            LoadPhase::Realising => "Realising missing paths",
+            LoadPhase::Querying => "Querying path info",
+            LoadPhase::ComputingStats => "Computing sizes",
+        }
+    }
+}
+
+/// Shared view of how far loading has got, updated by the backend while the
+/// UI renders it. Cloning yields another handle to the same state.
+#[derive(Debug, Clone, Default)]
+pub struct LoadProgress {
+    phase: Arc<Mutex<LoadPhase>>,
+    detail: Arc<Mutex<Option<String>>>,
+    parsed: Arc<AtomicUsize>,
+}
+
+impl LoadProgress {
+    pub fn phase(&self) -> LoadPhase {
+        *self.phase.lock().unwrap()
+    }
+
+    pub fn set_phase(&self, phase: LoadPhase) {
+        *self.phase.lock().unwrap() = phase;
+        *self.detail.lock().unwrap() = None;
+    }
+
+    /// What the current phase is working on, if it reports that
+    pub fn detail(&self) -> Option<String> {
+        self.detail.lock().unwrap().clone()
+    }
+
+    pub fn set_detail(&self, detail: String) {
+        *self.detail.lock().unwrap() = Some(detail);
+    }
+
+    /// Number of store paths parsed so far
+    pub fn parsed(&self) -> usize {
+        self.parsed.load(Ordering::Relaxed)
+    }
+
+    pub fn set_parsed(&self, count: usize) {
+        self.parsed.store(count, Ordering::Relaxed);
+    }
+
+    pub fn add_parsed(&self, count: usize) {
+        self.parsed.fetch_add(count, Ordering::Relaxed);
+    }
+}
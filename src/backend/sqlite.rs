@@ -0,0 +1,202 @@
+use anyhow::{Context, Result};
+use rusqlite::{Connection, OpenFlags};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use tokio::sync::OnceCell;
+
+use crate::backend::{BackendFuture, LoadProgress, StoreBackend};
+use crate::store_path::StorePath;
+
+pub const DEFAULT_DB_PATH: &str = "/nix/var/nix/db/db.sqlite";
+
+/// Reads the `ValidPaths` and `Refs` tables of a Nix store database directly.
+///
+/// The whole table is loaded once, on first use, which is much faster than
+/// `nix path-info --recursive` for whole-store analysis.
+#[derive(Debug)]
+pub struct SqliteBackend {
+    db_path: PathBuf,
+    paths: OnceCell<HashMap<String, StorePath>>,
+}
+
+impl SqliteBackend {
+    /// Check that the database can be opened; its contents are read lazily
+    pub async fn open(db_path: impl Into<PathBuf>) -> Result<Self> {
+        let db_path = db_path.into();
+        let check_path = db_path.clone();
+        tokio::task::spawn_blocking(move || connect(&check_path)).await??;
+
+        Ok(Self {
+            db_path,
+            paths: OnceCell::new(),
+        })
+    }
+
+    async fn paths(&self, progress: &LoadProgress) -> Result<&HashMap<String, StorePath>> {
+        self.paths
+            .get_or_try_init(|| {
+                let db_path = self.db_path.clone();
+                let progress = progress.clone();
+                async move {
+                    tokio::task::spawn_blocking(move || {
+                        let conn = connect(&db_path)?;
+                        read_paths(&conn, &progress).with_context(|| {
+                            format!("Failed to read store database {}", db_path.display())
+                        })
+                    })
+                    .await?
+                }
+            })
+            .await
+    }
+}
+
+fn connect(db_path: &Path) -> Result<Connection> {
+    let flags = OpenFlags::SQLITE_OPEN_READ_ONLY
+        | OpenFlags::SQLITE_OPEN_URI
+        | OpenFlags::SQLITE_OPEN_NO_MUTEX;
+
+    let conn = Connection::open_with_flags(format!("file:{}?mode=ro", db_path.display()), flags)
+        .with_context(|| format!("Failed to open store database {}", db_path.display()))?;
+
+    // A live database in WAL mode needs its -shm file to be writable, which
+    // it usually isn't for regular users. Fall back to treating it as
+    // immutable, like nix itself does for read-only stores.
+    if conn
+        .query_row("SELECT 1 FROM ValidPaths LIMIT 1", [], |_| Ok(()))
+        .is_err()
+    {
+        return Connection::open_with_flags(
+            format!("file:{}?immutable=1", db_path.display()),
+            flags,
+        )
+        .with_context(|| format!("Failed to open store database {}", db_path.display()));
+    }
+
+    Ok(conn)
+}
+
+fn read_paths(conn: &Connection, progress: &LoadProgress) -> Result<HashMap<String, StorePath>> {
+    let mut stmt = conn
+        .prepare("SELECT id, path, registrationTime, deriver, narSize, sigs, ca FROM ValidPaths")?;
+
+    let mut ids = HashMap::new();
+    let mut paths = HashMap::new();
+
+    let mut rows = stmt.query([])?;
+    while let Some(row) = rows.next()? {
+        let id: i64 = row.get(0)?;
+        let path: String = row.get(1)?;
+        let (hash, name) = StorePath::parse(&path)?;
+        let sigs: Option<String> = row.get(5)?;
+
+        ids.insert(id, path.clone());
+        paths.insert(
+            path.clone(),
+            StorePath {
+                path,
+                hash,
+                name,
+                nar_size: row.get::<_, Option<i64>>(4)?.unwrap_or(0) as u64,
+                closure_size: None,
+                references: Vec::new(),
+                signatures: sigs
+                    .unwrap_or_default()
+                    .split_whitespace()
+                    .map(str::to_string)
+                    .collect(),
+                registration_time: row.get::<_, Option<i64>>(2)?.map(|t| t as u64),
+                deriver: row.get(3)?,
+                ca: row.get(6)?,
+                file_size: None,
+                compression: None,
+                missing: false,
+            },
+        );
+        progress.set_parsed(paths.len());
+    }
+
+    let mut stmt = conn.prepare("SELECT referrer, reference FROM Refs")?;
+    let mut rows = stmt.query([])?;
+    while let Some(row) = rows.next()? {
+        let referrer: i64 = row.get(0)?;
+        let reference: i64 = row.get(1)?;
+
+        if let (Some(referrer), Some(reference)) = (ids.get(&referrer), ids.get(&reference))
+            && let Some(store_path) = paths.get_mut(referrer)
+        {
+            store_path.references.push(reference.clone());
+        }
+    }
+
+    Ok(paths)
+}
+
+impl StoreBackend for SqliteBackend {
+    /// Without installables, every path that no other valid path refers to
+    /// is treated as a root, covering the whole store.
+    fn resolve_roots<'a>(
+        &'a self,
+        installables: &'a [String],
+        progress: &'a LoadProgress,
+    ) -> BackendFuture<'a, Vec<String>> {
+        Box::pin(async move {
+            let paths = self.paths(progress).await?;
+            if installables.is_empty() {
+                let referenced: HashSet<&String> = paths
+                    .values()
+                    .flat_map(|p| p.references.iter().filter(move |r| **r != p.path))
+                    .collect();
+                let mut roots: Vec<String> = paths
+                    .keys()
+                    .filter(|p| !referenced.contains(p))
+                    .cloned()
+                    .collect();
+                roots.sort();
+                return Ok(roots);
+            }
+
+            let mut roots = Vec::new();
+            for installable in installables {
+                let mut path = installable.clone();
+                // Resolve symlinks for paths outside the Nix store
+                if !path.starts_with("/nix/store/")
+                    && let Ok(resolved) = tokio::fs::canonicalize(&path).await
+                {
+                    path = resolved.to_string_lossy().to_string();
+                }
+
+                if !paths.contains_key(&path) {
+                    anyhow::bail!("{} is not a valid path in the store database", installable);
+                }
+                roots.push(path);
+            }
+            Ok(roots)
+        })
+    }
+
+    fn query_closure<'a>(
+        &'a self,
+        roots: &'a [String],
+        progress: &'a LoadProgress,
+    ) -> BackendFuture<'a, Vec<StorePath>> {
+        Box::pin(async move {
+            let paths = self.paths(progress).await?;
+            let mut closure = HashSet::new();
+            let mut to_visit: Vec<&String> = roots.iter().collect();
+
+            while let Some(current) = to_visit.pop() {
+                if closure.insert(current)
+                    && let Some(store_path) = paths.get(current)
+                {
+                    to_visit.extend(store_path.references.iter());
+                }
+            }
+
+            Ok(closure
+                .into_iter()
+                .filter_map(|p| paths.get(p).cloned())
+                .collect())
+        })
+    }
+}
@@ -0,0 +1,425 @@
+//! Size budgets for CI, checked by `nix-tree check --budget <file>`.
+//!
+//! ```toml
+//! # The whole closure
+//! max_closure_size = "500MB"
+//!
+//! # Growth compared to a snapshot saved with `nix-tree report --json`,
+//! # relative to this file
+//! baseline = "baseline.json"
+//! max_growth = "5%"
+//!
+//! # Paths matching a filter expression
+//! [[limit]]
+//! match = "python3*"
+//! max_closure_size = "200MB"
+//!
+//! [[limit]]
+//! match = "name=*-dev"
+//! max_total_size = 0
+//! ```
+
+use anyhow::{Context, Result, anyhow};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+
+use crate::filter::{Filter, FilterContext, parse_size};
+use crate::path_stats::{PathStats, chain_to, shortest_parents};
+use crate::report::Report;
+use crate::store_path::{PathId, StorePath, StorePathGraph};
+
+/// How many offending paths to list for each failed check
+pub const MAX_OFFENDERS: usize = 5;
+/// How many why-depends chains to show for each offending path
+pub const MAX_CHAINS: usize = 3;
+/// The exit status of `nix-tree check` when a budget is exceeded, telling it
+/// apart from failing to load the closure or the budget, which exit with 1
+pub const EXIT_OVER_BUDGET: i32 = 2;
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum SizeValue {
+    Bytes(u64),
+    Text(String),
+}
+
+/// A size in bytes, written as a number or a string like "10MB"
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "SizeValue")]
+pub struct Size(pub u64);
+
+impl TryFrom<SizeValue> for Size {
+    type Error = anyhow::Error;
+
+    fn try_from(value: SizeValue) -> Result<Self> {
+        match value {
+            SizeValue::Bytes(bytes) => Ok(Size(bytes)),
+            SizeValue::Text(text) => parse_size(&text).map(Size),
+        }
+    }
+}
+
+/// Allowed growth over the baseline, in bytes or as a percentage
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(try_from = "SizeValue")]
+pub enum Growth {
+    Bytes(u64),
+    Percent(f64),
+}
+
+impl TryFrom<SizeValue> for Growth {
+    type Error = anyhow::Error;
+
+    fn try_from(value: SizeValue) -> Result<Self> {
+        match value {
+            SizeValue::Bytes(bytes) => Ok(Growth::Bytes(bytes)),
+            SizeValue::Text(text) => match text.strip_suffix('%') {
+                Some(percent) => percent
+                    .trim()
+                    .parse()
+                    .map(Growth::Percent)
+                    .map_err(|_| anyhow!("Invalid percentage '{text}'")),
+                None => parse_size(&text).map(Growth::Bytes),
+            },
+        }
+    }
+}
+
+impl Growth {
+    fn limit(&self, baseline_size: u64) -> u64 {
+        match self {
+            Growth::Bytes(bytes) => *bytes,
+            Growth::Percent(percent) => (baseline_size as f64 * percent / 100.0) as u64,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Budget {
+    pub max_closure_size: Option<Size>,
+    pub baseline: Option<PathBuf>,
+    pub max_growth: Option<Growth>,
+    #[serde(default, rename = "limit")]
+    pub limits: Vec<Limit>,
+}
+
+/// Limits for the paths matching a filter expression
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Limit {
+    #[serde(rename = "match")]
+    pub pattern: String,
+    /// Each matching path's own size
+    pub max_nar_size: Option<Size>,
+    pub max_closure_size: Option<Size>,
+    pub max_retained_size: Option<Size>,
+    /// The NAR sizes of all matching paths together
+    pub max_total_size: Option<Size>,
+}
+
+impl Budget {
+    /// Read a budget file. A relative baseline is taken relative to it.
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read budget {}", path.display()))?;
+        let mut budget: Budget = toml::from_str(&text)
+            .with_context(|| format!("Failed to parse budget {}", path.display()))?;
+
+        if let Some(baseline) = &budget.baseline
+            && baseline.is_relative()
+            && let Some(dir) = path.parent()
+        {
+            budget.baseline = Some(dir.join(baseline));
+        }
+
+        // Catch mistakes before the closure is loaded
+        for limit in &budget.limits {
+            Filter::parse(&limit.pattern)
+                .with_context(|| format!("Invalid match '{}' in budget", limit.pattern))?;
+        }
+        Ok(budget)
+    }
+}
+
+/// Which size a per-path limit applies to
+#[derive(Debug, Clone, Copy)]
+enum Measure {
+    Nar,
+    Closure,
+    Retained,
+}
+
+impl Measure {
+    fn name(&self) -> &'static str {
+        match self {
+            Measure::Nar => "NAR size",
+            Measure::Closure => "closure size",
+            Measure::Retained => "retained size",
+        }
+    }
+
+    fn of(&self, graph: &StorePathGraph, stats: &HashMap<String, PathStats>, path: &str) -> u64 {
+        match self {
+            Measure::Nar => graph.get_path(path).map_or(0, |p| p.nar_size),
+            Measure::Closure => stats.get(path).map_or(0, |s| s.closure_size),
+            Measure::Retained => stats.get(path).map_or(0, |s| s.retained_size),
+        }
+    }
+}
+
+/// The outcome of one budget rule
+#[derive(Debug, Clone, Serialize)]
+pub struct BudgetCheck {
+    pub rule: String,
+    /// The measured size; for per-path limits the largest matching path
+    pub actual: u64,
+    pub limit: u64,
+    pub passed: bool,
+    /// The paths responsible, largest first, if the check failed
+    pub offenders: Vec<Offender>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Offender {
+    pub path: String,
+    pub size: u64,
+    /// Shortest chains from the roots first
+    pub chains: Vec<Vec<String>>,
+}
+
+/// `path` with the shortest chains from the roots through each of its
+/// referrers, walking the breadth first `parents` of the roots
+fn offender(graph: &StorePathGraph, parents: &[Option<PathId>], path: &str, size: u64) -> Offender {
+    let mut chains: Vec<Vec<PathId>> = match graph.id(path) {
+        Some(id) if parents[id] == Some(id) => vec![vec![id]],
+        Some(id) => graph
+            .referrer_ids(id)
+            .iter()
+            .filter(|&&referrer| parents[referrer].is_some())
+            .map(|&referrer| {
+                let mut chain = chain_to(parents, referrer);
+                chain.push(id);
+                chain
+            })
+            .collect(),
+        None => Vec::new(),
+    };
+    chains.sort_by(|a, b| a.len().cmp(&b.len()).then_with(|| a.cmp(b)));
+    chains.truncate(MAX_CHAINS);
+    Offender {
+        path: path.to_string(),
+        size,
+        chains: chains
+            .into_iter()
+            .map(|chain| {
+                chain
+                    .into_iter()
+                    .map(|id| graph.path(id).path.clone())
+                    .collect()
+            })
+            .collect(),
+    }
+}
+
+/// The `MAX_OFFENDERS` largest of `sized` paths
+fn offenders(
+    graph: &StorePathGraph,
+    parents: &[Option<PathId>],
+    mut sized: Vec<(String, u64)>,
+) -> Vec<Offender> {
+    sized.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    sized
+        .into_iter()
+        .take(MAX_OFFENDERS)
+        .map(|(path, size)| offender(graph, parents, &path, size))
+        .collect()
+}
+
+/// Check every rule of `budget`. `baseline` is needed if it limits growth.
+pub fn check(
+    budget: &Budget,
+    graph: &StorePathGraph,
+    stats: &HashMap<String, PathStats>,
+    depths: &[Option<usize>],
+    baseline: Option<&Report>,
+) -> Result<Vec<BudgetCheck>> {
+    let mut checks = Vec::new();
+    let roots = graph.root_ids();
+    let parents = shortest_parents(graph, &roots);
+    // The graph may hold paths outside the roots' closure, which don't count
+    let mut visited = Vec::new();
+    let closure: Vec<&StorePath> = roots
+        .iter()
+        .flat_map(|&id| graph.closure_ids(id, &mut visited, 1))
+        .collect::<HashSet<PathId>>()
+        .into_iter()
+        .map(|id| graph.path(id))
+        .collect();
+    let closure_size: u64 = closure.iter().map(|p| p.nar_size).sum();
+
+    if let Some(Size(limit)) = budget.max_closure_size {
+        let passed = closure_size <= limit;
+        // What retains the most, leaving out the roots, which retain
+        // everything
+        let offenders = if passed {
+            Vec::new()
+        } else {
+            let roots: HashSet<&String> = graph.roots.iter().collect();
+            let sized = closure
+                .iter()
+                .filter(|p| !roots.contains(&p.path))
+                .map(|p| {
+                    let retained = stats.get(&p.path).map_or(0, |s| s.retained_size);
+                    (p.path.clone(), retained)
+                })
+                .collect();
+            offenders(graph, &parents, sized)
+        };
+        checks.push(BudgetCheck {
+            rule: "closure size".to_string(),
+            actual: closure_size,
+            limit,
+            passed,
+            offenders,
+        });
+    }
+
+    if let Some(growth) = budget.max_growth {
+        let baseline =
+            baseline.context("max_growth needs a baseline, in the budget or from --baseline")?;
+        let limit = growth.limit(baseline.closure_size);
+        let actual = closure_size.saturating_sub(baseline.closure_size);
+        let passed = actual <= limit;
+        // Paths that weren't there before
+        let offenders = if passed {
+            Vec::new()
+        } else {
+            let old: HashSet<&str> = baseline.paths.iter().map(|p| p.path.as_str()).collect();
+            let sized = closure
+                .iter()
+                .filter(|p| !old.contains(p.path.as_str()))
+                .map(|p| (p.path.clone(), p.nar_size))
+                .collect();
+            offenders(graph, &parents, sized)
+        };
+        checks.push(BudgetCheck {
+            rule: "growth since baseline".to_string(),
+            actual,
+            limit,
+            passed,
+            offenders,
+        });
+    }
+
+    let ctx = FilterContext {
+        graph,
+        stats,
+        depths,
+    };
+    for limit in &budget.limits {
+        let filter = Filter::parse(&limit.pattern)
+            .with_context(|| format!("Invalid match '{}' in budget", limit.pattern))?;
+        let matching: Vec<&str> = graph
+            .paths()
+            .iter()
+            .map(|p| p.path.as_str())
+            .filter(|p| filter.matches(&ctx, p))
+            .collect();
+
+        for (measure, max) in [
+            (Measure::Nar, limit.max_nar_size),
+            (Measure::Closure, limit.max_closure_size),
+            (Measure::Retained, limit.max_retained_size),
+        ] {
+            let Some(Size(max)) = max else {
+                continue;
+            };
+            let sized: Vec<(String, u64)> = matching
+                .iter()
+                .map(|p| (p.to_string(), measure.of(graph, stats, p)))
+                .collect();
+            let actual = sized.iter().map(|(_, size)| *size).max().unwrap_or(0);
+            let over = sized.into_iter().filter(|(_, size)| *size > max).collect();
+            checks.push(BudgetCheck {
+                rule: format!("{} of {}", measure.name(), limit.pattern),
+                actual,
+                limit: max,
+                passed: actual <= max,
+                offenders: offenders(graph, &parents, over),
+            });
+        }
+
+        if let Some(Size(max)) = limit.max_total_size {
+            let actual = matching
+                .iter()
+                .map(|p| Measure::Nar.of(graph, stats, p))
+                .sum();
+            let passed = actual <= max;
+            let offenders = if passed {
+                Vec::new()
+            } else {
+                let sized = matching
+                    .iter()
+                    .map(|p| (p.to_string(), Measure::Nar.of(graph, stats, p)))
+                    .collect();
+                offenders(graph, &parents, sized)
+            };
+            checks.push(BudgetCheck {
+                rule: format!("total size of {}", limit.pattern),
+                actual,
+                limit: max,
+                passed,
+                offenders,
+            });
+        }
+    }
+
+    Ok(checks)
+}
+
+pub fn render_text(checks: &[BudgetCheck], graph: &StorePathGraph) -> String {
+    let short_name = |path: &str| {
+        graph
+            .get_path(path)
+            .map_or(path.to_string(), |p| p.full_name().to_string())
+    };
+
+    let mut out = String::new();
+    for check in checks {
+        let (status, comparison) = if check.passed {
+            ("ok  ", "<=")
+        } else {
+            ("FAIL", ">")
+        };
+        let _ = writeln!(
+            out,
+            "{status} {}: {} {comparison} {}",
+            check.rule,
+            bytesize::ByteSize(check.actual),
+            bytesize::ByteSize(check.limit)
+        );
+        for offender in &check.offenders {
+            let _ = writeln!(
+                out,
+                "       {} ({})",
+                short_name(&offender.path),
+                bytesize::ByteSize(offender.size)
+            );
+            for chain in &offender.chains {
+                let names: Vec<String> = chain.iter().map(|p| short_name(p)).collect();
+                let _ = writeln!(out, "         {}", names.join(" → "));
+            }
+        }
+    }
+
+    let failed = checks.iter().filter(|c| !c.passed).count();
+    if failed == 0 {
+        let _ = writeln!(out, "\nAll {} budget checks passed", checks.len());
+    } else {
+        let _ = writeln!(out, "\n{failed} of {} budget checks failed", checks.len());
+    }
+    out
+}
@@ -1,14 +1,49 @@
-use anyhow::{Result, bail};
+use anyhow::{Context, Result, bail};
+
+use crate::export::{DotOptions, Prune};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Command {
+    /// Browse the closure of the given paths
+    #[default]
+    Browse,
+    /// Compare the closures of two paths
+    Diff,
+    /// Report packages present in more than one version
+    Duplicates,
+    /// Print the sizes and relations of every path
+    Report,
+    /// Check the closure against a size budget
+    Check,
+    /// Report forbidden dependencies
+    Lint,
+}
 
 #[derive(Debug, Clone, Default)]
 pub struct Config {
+    pub command: Command,
     pub paths: Vec<String>,
     pub derivation: bool,
+    pub no_realise: bool,
     pub store: Option<String>,
     pub help: bool,
     pub version: bool,
     pub nix_options: Vec<(String, String)>,
     pub file: Option<String>,
+    pub from_json: Option<String>,
+    pub db: Option<String>,
+    pub json: bool,
+    pub tui: bool,
+    pub filter: Option<String>,
+    /// Write the graph in this format instead of opening the TUI
+    pub export: Option<String>,
+    pub dot_options: DotOptions,
+    pub budget: Option<String>,
+    /// Overrides the baseline named in the budget
+    pub baseline: Option<String>,
+    pub rules: Option<String>,
+    /// Read instead of the config file in `$XDG_CONFIG_HOME`
+    pub config_file: Option<String>,
 }
 
 pub fn parse_args() -> Result<Config> {
@@ -18,6 +53,21 @@ pub fn parse_args() -> Result<Config> {
     let mut i = 1;
     while i < args.len() {
         match args[i].as_str() {
+            "diff" if i == 1 => {
+                config.command = Command::Diff;
+            }
+            "duplicates" if i == 1 => {
+                config.command = Command::Duplicates;
+            }
+            "report" if i == 1 => {
+                config.command = Command::Report;
+            }
+            "check" if i == 1 => {
+                config.command = Command::Check;
+            }
+            "lint" if i == 1 => {
+                config.command = Command::Lint;
+            }
             "-h" | "--help" => {
                 config.help = true;
                 return Ok(config);
@@ -29,6 +79,9 @@ pub fn parse_args() -> Result<Config> {
             "-d" | "--derivation" => {
                 config.derivation = true;
             }
+            "--no-realise" => {
+                config.no_realise = true;
+            }
             "--store" => {
                 i += 1;
                 if i >= args.len() {
@@ -59,6 +112,109 @@ pub fn parse_args() -> Result<Config> {
             arg if arg.starts_with("--file=") => {
                 config.file = Some(arg.strip_prefix("--file=").unwrap().to_string());
             }
+            "--from-json" => {
+                i += 1;
+                if i >= args.len() {
+                    bail!("--from-json requires an argument");
+                }
+                config.from_json = Some(args[i].clone());
+            }
+            arg if arg.starts_with("--from-json=") => {
+                config.from_json = Some(arg.strip_prefix("--from-json=").unwrap().to_string());
+            }
+            "--db" => {
+                config.db = Some(crate::backend::sqlite::DEFAULT_DB_PATH.to_string());
+            }
+            arg if arg.starts_with("--db=") => {
+                config.db = Some(arg.strip_prefix("--db=").unwrap().to_string());
+            }
+            "--filter" => {
+                i += 1;
+                if i >= args.len() {
+                    bail!("--filter requires an argument");
+                }
+                config.filter = Some(args[i].clone());
+            }
+            arg if arg.starts_with("--filter=") => {
+                config.filter = Some(arg.strip_prefix("--filter=").unwrap().to_string());
+            }
+            "--export" => {
+                i += 1;
+                if i >= args.len() {
+                    bail!("--export requires an argument");
+                }
+                config.export = Some(args[i].clone());
+            }
+            arg if arg.starts_with("--export=") => {
+                config.export = Some(arg.strip_prefix("--export=").unwrap().to_string());
+            }
+            "--depth" => {
+                i += 1;
+                if i >= args.len() {
+                    bail!("--depth requires an argument");
+                }
+                config.dot_options.max_depth = Some(parse_depth(&args[i])?);
+            }
+            arg if arg.starts_with("--depth=") => {
+                config.dot_options.max_depth =
+                    Some(parse_depth(arg.strip_prefix("--depth=").unwrap())?);
+            }
+            "--prune" => {
+                i += 1;
+                if i >= args.len() {
+                    bail!("--prune requires an argument");
+                }
+                config.dot_options.prune = Prune::parse(&args[i])?;
+            }
+            arg if arg.starts_with("--prune=") => {
+                config.dot_options.prune = Prune::parse(arg.strip_prefix("--prune=").unwrap())?;
+            }
+            "--budget" => {
+                i += 1;
+                if i >= args.len() {
+                    bail!("--budget requires an argument");
+                }
+                config.budget = Some(args[i].clone());
+            }
+            arg if arg.starts_with("--budget=") => {
+                config.budget = Some(arg.strip_prefix("--budget=").unwrap().to_string());
+            }
+            "--baseline" => {
+                i += 1;
+                if i >= args.len() {
+                    bail!("--baseline requires an argument");
+                }
+                config.baseline = Some(args[i].clone());
+            }
+            arg if arg.starts_with("--baseline=") => {
+                config.baseline = Some(arg.strip_prefix("--baseline=").unwrap().to_string());
+            }
+            "--rules" => {
+                i += 1;
+                if i >= args.len() {
+                    bail!("--rules requires an argument");
+                }
+                config.rules = Some(args[i].clone());
+            }
+            arg if arg.starts_with("--rules=") => {
+                config.rules = Some(arg.strip_prefix("--rules=").unwrap().to_string());
+            }
+            "--config" => {
+                i += 1;
+                if i >= args.len() {
+                    bail!("--config requires an argument");
+                }
+                config.config_file = Some(args[i].clone());
+            }
+            arg if arg.starts_with("--config=") => {
+                config.config_file = Some(arg.strip_prefix("--config=").unwrap().to_string());
+            }
+            "--json" => {
+                config.json = true;
+            }
+            "--tui" => {
+                config.tui = true;
+            }
             arg if arg.starts_with('-') => {
                 bail!("Unknown option: {}", arg);
             }
@@ -69,36 +225,103 @@ pub fn parse_args() -> Result<Config> {
         i += 1;
     }
 
+    if config.command == Command::Diff && config.paths.len() != 2 {
+        bail!("diff requires two paths: <OLD> <NEW>");
+    }
+
+    if config.command == Command::Check && config.budget.is_none() {
+        bail!("check requires --budget <FILE>");
+    }
+
+    if config.command == Command::Lint && config.rules.is_none() {
+        bail!("lint requires --rules <FILE>");
+    }
+
+    if let Some(format) = &config.export
+        && format != "dot"
+    {
+        bail!("Unsupported export format '{format}', expected dot");
+    }
+
     Ok(config)
 }
 
+fn parse_depth(value: &str) -> Result<usize> {
+    value
+        .parse()
+        .with_context(|| format!("Invalid depth '{value}'"))
+}
+
 pub fn print_help() {
     println!(
         r#"nix-tree - Interactively browse dependency graphs of Nix derivations
 
 USAGE:
     nix-tree [OPTIONS] [PATHS]...
+    nix-tree diff [OPTIONS] <OLD> <NEW>
+    nix-tree duplicates [OPTIONS] [PATHS]...
+    nix-tree report [OPTIONS] [PATHS]...
+    nix-tree check --budget <FILE> [OPTIONS] [PATHS]...
+    nix-tree lint --rules <FILE> [OPTIONS] [PATHS]...
 
 OPTIONS:
     -h, --help              Display help message
     -v, --version           Display version
     -d, --derivation        Operate on derivation store paths
+                            .drv files given directly are read without nix, sized by file length
+    --no-realise            Don't build or substitute missing paths; show them as placeholders
+                            filled in from substituters and their derivations
     --store <STORE>         The URL of the Nix store, e.g. "daemon" or "https://cache.nixos.org"
                             See "nix help-stores" for supported store types and settings
+                            file:// binary caches are read directly, without nix
     --option <NAME> <VALUE> Pass option to nix commands
     -f, --file <FILE>       Interpret installables as attribute paths relative to the Nix expression in file
+    --from-json <FILE>      Load the graph from a saved `nix path-info --json --recursive` dump
+                            instead of querying nix ("-" reads from stdin)
+    --db[=<PATH>]           Read the Nix store database directly instead of querying nix
+                            (defaults to /nix/var/nix/db/db.sqlite)
+    --filter <EXPR>         Print the paths matching EXPR instead of opening the TUI, e.g.
+                            'name=*-dev or (closure>100MB and not signed)'; see the README
+                            report: only include the paths matching EXPR
+    --export dot            Print the graph as Graphviz DOT instead of opening the TUI
+    --depth <N>             DOT export: only include paths up to N references from the roots
+    --prune <MODE>          DOT export: drop edges, "transitive" for references also reachable
+                            another way, "tree" for all but the shortest chain to each path
+    --budget <FILE>         check: the size limits to enforce, as TOML; see the README
+                            Exits with status 2 if any is exceeded, and 1 on errors
+    --baseline <FILE>       check: a `nix-tree report --json` snapshot to measure growth
+                            against, instead of the one named in the budget
+    --rules <FILE>          lint: forbidden dependencies, as TOML; see the README
+                            Otherwise highlights the paths breaking them in the TUI
+                            lint exits with status 2 if any is broken, and 1 on errors
+    --json                  diff, duplicates, report, check, lint: print JSON instead of text
+    --tui                   diff: browse both closures, highlighting added and removed paths
+    --config <FILE>         Read keybindings, colours and defaults for the TUI from FILE instead
+                            of $XDG_CONFIG_HOME/nix-tree/config.toml; see the README
 
 ARGUMENTS:
-    [PATHS]...          Paths to explore (defaults to current system profile)
+    [PATHS]...          Paths to explore (defaults to current system profile, or to
+                        the paths nothing refers to with --from-json or --db)
+    <OLD> <NEW>         Closures to compare, e.g. two system generations
 
-KEYBINDINGS:
+KEYBINDINGS (the defaults; see --config to change them):
     q/Esc               Quit
     j/Down              Move down
     k/Up                Move up
     h/Left              Move to previous pane  
     l/Right             Move to next pane
     /                   Search
+    n/N                 Go to the next or previous match of the last search
     s                   Change sort order
+    p                   Show packages, grouping versions and outputs
+    d                   Show only packages present in more than one version
+    x                   Export the selected path's closure as DOT
+    y/Y                 Copy the selected path, or its name, to the clipboard
+    !                   Open $SHELL in the selected path
+    o                   Open the selected path, or a listing of it, in $PAGER
+    b                   Open the selected path in the configured file browser
+    t                   Toggle the treemap of the selected path's references
+    v                   Toggle the tree view, at the selected path
     ?                   Show help
 "#
     );
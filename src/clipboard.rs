@@ -0,0 +1,86 @@
+//! Copying to the system clipboard. The OSC 52 escape sequence asks the
+//! terminal to do it, so it works over SSH; a command like `wl-copy` can be
+//! configured as well for terminals that don't support it. The terminal
+//! doesn't answer, so there's no telling whether the sequence worked, and
+//! both are used when both are enabled.
+
+use anyhow::{Context, Result, bail};
+use base64::Engine;
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Clipboard {
+    /// Whether to send the OSC 52 escape sequence
+    pub osc52: bool,
+    /// Run this as well as sending the sequence, with the text on its
+    /// standard input
+    pub command: Option<Vec<String>>,
+}
+
+impl Default for Clipboard {
+    fn default() -> Self {
+        Self {
+            osc52: true,
+            command: None,
+        }
+    }
+}
+
+/// The escape sequence setting the clipboard to `text`. For `tmux` it is
+/// wrapped in a passthrough sequence, with its escapes doubled, so tmux hands
+/// it on to the outer terminal.
+pub fn osc52(text: &str, tmux: bool) -> String {
+    let encoded = base64::engine::general_purpose::STANDARD.encode(text);
+    let sequence = format!("\x1b]52;c;{encoded}\x07");
+    if tmux {
+        format!("\x1bPtmux;{}\x1b\\", sequence.replace('\x1b', "\x1b\x1b"))
+    } else {
+        sequence
+    }
+}
+
+/// Whether we're running inside tmux
+pub fn in_tmux() -> bool {
+    std::env::var_os("TMUX").is_some_and(|tmux| !tmux.is_empty())
+}
+
+impl Clipboard {
+    /// Copy `text`, writing the escape sequence to `terminal` and running the
+    /// command. An error from the command doesn't stop the sequence being
+    /// sent.
+    pub fn copy(&self, text: &str, terminal: &mut impl Write) -> Result<()> {
+        if self.osc52 {
+            terminal.write_all(osc52(text, in_tmux()).as_bytes())?;
+            terminal.flush()?;
+        }
+        if let Some(command) = &self.command {
+            run(command, text)?;
+        }
+        Ok(())
+    }
+}
+
+fn run(command: &[String], text: &str) -> Result<()> {
+    let Some((program, args)) = command.split_first() else {
+        bail!("The clipboard command is empty");
+    };
+    let mut child = Command::new(program)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .with_context(|| format!("Failed to run {program}"))?;
+    child
+        .stdin
+        .take()
+        .expect("stdin is piped")
+        .write_all(text.as_bytes())
+        .with_context(|| format!("Failed to write to {program}"))?;
+    let status = child.wait()?;
+    if !status.success() {
+        bail!("{program} failed with {status}");
+    }
+    Ok(())
+}
@@ -0,0 +1,396 @@
+use anyhow::{Context, Result, bail};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+
+use crate::store_path::{StorePath, StorePathGraph};
+
+/// A store derivation as written to disk in ATerm format.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Derivation {
+    pub outputs: BTreeMap<String, DerivationOutput>,
+    /// Input derivations and the names of the outputs used from each
+    pub input_drvs: BTreeMap<String, BTreeSet<String>>,
+    pub input_srcs: BTreeSet<String>,
+    pub system: String,
+    pub builder: String,
+    pub args: Vec<String>,
+    pub env: BTreeMap<String, String>,
+}
+
+/// A single output of a derivation.
+///
+/// Input-addressed outputs only have a path. Fixed-output derivations carry
+/// the path together with the expected hash, while floating content-addressed
+/// outputs know their hash algorithm but neither path nor hash.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DerivationOutput {
+    pub path: Option<String>,
+    pub hash_algo: Option<String>,
+    pub hash: Option<String>,
+}
+
+impl DerivationOutput {
+    pub fn is_content_addressed(&self) -> bool {
+        self.hash_algo.is_some()
+    }
+
+    pub fn is_fixed_output(&self) -> bool {
+        self.hash_algo.is_some() && self.hash.is_some()
+    }
+
+    /// Whether the output is hashed as a NAR (`r:`) rather than a flat file
+    pub fn is_recursive(&self) -> bool {
+        self.hash_algo
+            .as_deref()
+            .is_some_and(|algo| algo.starts_with("r:"))
+    }
+}
+
+impl Derivation {
+    pub fn parse(input: &str) -> Result<Self> {
+        let mut parser = Parser::new(input);
+        let drv = parser.derivation()?;
+        parser.skip_whitespace();
+        if !parser.at_end() {
+            bail!("Malformed derivation: trailing data at byte {}", parser.pos);
+        }
+        Ok(drv)
+    }
+
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read derivation {}", path.display()))?;
+        Self::parse(&contents).with_context(|| format!("Failed to parse {}", path.display()))
+    }
+
+    /// Whether the derivation was instantiated with `__structuredAttrs = true`
+    pub fn has_structured_attrs(&self) -> bool {
+        self.env.contains_key("__json")
+    }
+
+    /// The attributes passed to the builder as JSON when structured attrs are enabled
+    pub fn structured_attrs(&self) -> Result<Option<serde_json::Value>> {
+        match self.env.get("__json") {
+            Some(json) => {
+                let value = serde_json::from_str(json).context("Invalid __json attribute")?;
+                Ok(Some(value))
+            }
+            None => Ok(None),
+        }
+    }
+}
+
+struct Parser<'a> {
+    input: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn new(input: &'a str) -> Self {
+        Self {
+            input: input.as_bytes(),
+            pos: 0,
+        }
+    }
+
+    fn at_end(&self) -> bool {
+        self.pos >= self.input.len()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self
+            .input
+            .get(self.pos)
+            .is_some_and(|c| c.is_ascii_whitespace())
+        {
+            self.pos += 1;
+        }
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.input.get(self.pos).copied()
+    }
+
+    fn describe(&self) -> String {
+        match self.peek() {
+            Some(c) => format!("'{}'", c as char),
+            None => "end of input".to_string(),
+        }
+    }
+
+    fn expect(&mut self, c: u8) -> Result<()> {
+        if self.peek() == Some(c) {
+            self.pos += 1;
+            Ok(())
+        } else {
+            bail!(
+                "Malformed derivation: expected '{}' at byte {}, found {}",
+                c as char,
+                self.pos,
+                self.describe()
+            )
+        }
+    }
+
+    fn eat(&mut self, c: u8) -> bool {
+        if self.peek() == Some(c) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect_keyword(&mut self, keyword: &str) -> Result<()> {
+        if self.input[self.pos..].starts_with(keyword.as_bytes()) {
+            self.pos += keyword.len();
+            Ok(())
+        } else {
+            bail!(
+                "Malformed derivation: expected '{}' at byte {}",
+                keyword,
+                self.pos
+            )
+        }
+    }
+
+    fn string(&mut self) -> Result<String> {
+        let start = self.pos;
+        self.expect(b'"')?;
+        let mut bytes = Vec::new();
+        loop {
+            match self.peek() {
+                None => bail!(
+                    "Malformed derivation: unterminated string starting at byte {}",
+                    start
+                ),
+                Some(b'"') => {
+                    self.pos += 1;
+                    break;
+                }
+                Some(b'\\') => {
+                    self.pos += 1;
+                    let escaped = match self.peek() {
+                        Some(b'n') => b'\n',
+                        Some(b'r') => b'\r',
+                        Some(b't') => b'\t',
+                        Some(c) => c,
+                        None => bail!(
+                            "Malformed derivation: unterminated string starting at byte {}",
+                            start
+                        ),
+                    };
+                    bytes.push(escaped);
+                    self.pos += 1;
+                }
+                Some(c) => {
+                    bytes.push(c);
+                    self.pos += 1;
+                }
+            }
+        }
+        String::from_utf8(bytes).with_context(|| format!("Invalid UTF-8 in string at byte {start}"))
+    }
+
+    fn path(&mut self) -> Result<String> {
+        let start = self.pos;
+        let path = self.string()?;
+        if !path.starts_with('/') {
+            bail!(
+                "Malformed derivation: expected a store path at byte {}, found \"{}\"",
+                start,
+                path
+            );
+        }
+        Ok(path)
+    }
+
+    /// Parse `[item, item, ...]`
+    fn list<T>(&mut self, mut item: impl FnMut(&mut Self) -> Result<T>) -> Result<Vec<T>> {
+        self.expect(b'[')?;
+        let mut items = Vec::new();
+        if self.eat(b']') {
+            return Ok(items);
+        }
+        loop {
+            items.push(item(self)?);
+            if self.eat(b']') {
+                return Ok(items);
+            }
+            self.expect(b',')?;
+        }
+    }
+
+    fn derivation(&mut self) -> Result<Derivation> {
+        self.skip_whitespace();
+        let dynamic = if self.input[self.pos..].starts_with(b"DrvWithVersion(") {
+            self.expect_keyword("DrvWithVersion(")?;
+            let version = self.string()?;
+            if version != "xp-dyn-drv" {
+                bail!("Unsupported derivation version \"{}\"", version);
+            }
+            self.expect(b',')?;
+            true
+        } else {
+            self.expect_keyword("Derive(")?;
+            false
+        };
+
+        let outputs = self
+            .list(|p| p.output())?
+            .into_iter()
+            .collect::<BTreeMap<_, _>>();
+        self.expect(b',')?;
+
+        let mut input_drvs = BTreeMap::new();
+        for (path, outputs) in self.list(|p| p.input_drv(dynamic))? {
+            input_drvs.insert(path, outputs);
+        }
+        self.expect(b',')?;
+
+        let input_srcs = self.list(|p| p.path())?.into_iter().collect();
+        self.expect(b',')?;
+        let system = self.string()?;
+        self.expect(b',')?;
+        let builder = self.string()?;
+        self.expect(b',')?;
+        let args = self.list(|p| p.string())?;
+        self.expect(b',')?;
+
+        let mut env = BTreeMap::new();
+        for (name, value) in self.list(|p| p.env_pair())? {
+            env.insert(name, value);
+        }
+        self.expect(b')')?;
+
+        Ok(Derivation {
+            outputs,
+            input_drvs,
+            input_srcs,
+            system,
+            builder,
+            args,
+            env,
+        })
+    }
+
+    fn output(&mut self) -> Result<(String, DerivationOutput)> {
+        self.expect(b'(')?;
+        let name = self.string()?;
+        self.expect(b',')?;
+        let path = self.string()?;
+        self.expect(b',')?;
+        let hash_algo = self.string()?;
+        self.expect(b',')?;
+        let hash = self.string()?;
+        self.expect(b')')?;
+
+        let non_empty = |s: String| if s.is_empty() { None } else { Some(s) };
+        Ok((
+            name,
+            DerivationOutput {
+                path: non_empty(path),
+                hash_algo: non_empty(hash_algo),
+                hash: non_empty(hash),
+            },
+        ))
+    }
+
+    fn input_drv(&mut self, dynamic: bool) -> Result<(String, BTreeSet<String>)> {
+        self.expect(b'(')?;
+        let path = self.path()?;
+        self.expect(b',')?;
+        let outputs = if dynamic && self.peek() == Some(b'(') {
+            // Dynamic derivations: (["out"], [("out", [...]), ...]). Only the
+            // directly used outputs matter for the graph.
+            self.expect(b'(')?;
+            let outputs = self.list(|p| p.string())?;
+            self.expect(b',')?;
+            self.skip_dynamic_outputs()?;
+            self.expect(b')')?;
+            outputs
+        } else {
+            self.list(|p| p.string())?
+        };
+        self.expect(b')')?;
+        Ok((path, outputs.into_iter().collect()))
+    }
+
+    fn skip_dynamic_outputs(&mut self) -> Result<()> {
+        self.list(|p| {
+            p.expect(b'(')?;
+            p.string()?;
+            p.expect(b',')?;
+            p.expect(b'(')?;
+            p.list(|p| p.string())?;
+            p.expect(b',')?;
+            p.skip_dynamic_outputs()?;
+            p.expect(b')')?;
+            p.expect(b')')
+        })?;
+        Ok(())
+    }
+
+    fn env_pair(&mut self) -> Result<(String, String)> {
+        self.expect(b'(')?;
+        let name = self.string()?;
+        self.expect(b',')?;
+        let value = self.string()?;
+        self.expect(b')')?;
+        Ok((name, value))
+    }
+}
+
+/// Load the derivation graph rooted at `roots` straight from `.drv` files.
+///
+/// Store paths are looked up by file name inside `store_dir`, so this works
+/// against `/nix/store` as well as a directory of copied `.drv` files. Input
+/// sources are added as leaves sized by their file length when present.
+pub fn load_graph(roots: &[String], store_dir: &Path) -> Result<StorePathGraph> {
+    Ok(StorePathGraph::from_paths(
+        load_closure(roots, store_dir)?,
+        roots.to_vec(),
+    ))
+}
+
+/// The paths of the derivation graph rooted at `roots`, as for `load_graph`
+pub fn load_closure(roots: &[String], store_dir: &Path) -> Result<Vec<StorePath>> {
+    let mut paths = Vec::new();
+    let mut seen = BTreeSet::new();
+    let mut to_visit: Vec<String> = roots.to_vec();
+
+    while let Some(path) = to_visit.pop() {
+        if !seen.insert(path.clone()) {
+            continue;
+        }
+
+        let (hash, name) = StorePath::parse(&path)?;
+        let file = store_dir.join(format!("{hash}-{name}"));
+        let nar_size = std::fs::symlink_metadata(&file)
+            .map(|m| if m.is_file() { m.len() } else { 0 })
+            .unwrap_or(0);
+
+        let references = if name.ends_with(".drv") {
+            let drv = Derivation::from_file(&file)?;
+            let references: Vec<String> =
+                drv.input_drvs.into_keys().chain(drv.input_srcs).collect();
+            to_visit.extend(references.iter().cloned());
+            references
+        } else {
+            Vec::new()
+        };
+
+        paths.push(StorePath {
+            path,
+            hash,
+            name,
+            nar_size,
+            closure_size: None,
+            references,
+            ..Default::default()
+        });
+    }
+
+    Ok(paths)
+}
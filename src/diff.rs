@@ -0,0 +1,237 @@
+use serde::Serialize;
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::fmt::Write;
+
+use crate::package::compare_versions;
+use crate::store_path::{StorePath, StorePathGraph};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChangeKind {
+    Upgraded,
+    Downgraded,
+    /// Same newest version, but a different set of versions
+    Changed,
+    Added,
+    Removed,
+}
+
+impl ChangeKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ChangeKind::Upgraded => "Upgraded",
+            ChangeKind::Downgraded => "Downgraded",
+            ChangeKind::Changed => "Changed",
+            ChangeKind::Added => "Added",
+            ChangeKind::Removed => "Removed",
+        }
+    }
+}
+
+/// How one package differs between the two closures
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PackageChange {
+    pub name: String,
+    pub kind: ChangeKind,
+    pub old_versions: Vec<String>,
+    pub new_versions: Vec<String>,
+    /// Total NAR size of the package's paths in each closure
+    pub old_size: u64,
+    pub new_size: u64,
+}
+
+impl PackageChange {
+    pub fn size_delta(&self) -> i64 {
+        self.new_size as i64 - self.old_size as i64
+    }
+}
+
+/// The package-level difference between two closures, matching paths by
+/// package name and comparing their versions
+#[derive(Debug, Clone, Serialize)]
+pub struct ClosureDiff {
+    pub old_roots: Vec<String>,
+    pub new_roots: Vec<String>,
+    pub old_closure_size: u64,
+    pub new_closure_size: u64,
+    pub changes: Vec<PackageChange>,
+}
+
+#[derive(Default)]
+struct Package {
+    versions: BTreeSet<String>,
+    size: u64,
+}
+
+/// Outputs of one package are merged, so only version changes show up
+fn packages(graph: &StorePathGraph) -> BTreeMap<String, Package> {
+    let mut packages: BTreeMap<String, Package> = BTreeMap::new();
+    for path in graph.paths() {
+        let name = path.package_name();
+        let package = packages.entry(name.pname).or_default();
+        package.versions.insert(name.version);
+        package.size += path.nar_size;
+    }
+    packages
+}
+
+fn sorted_versions(versions: &BTreeSet<String>) -> Vec<String> {
+    let mut versions: Vec<String> = versions.iter().cloned().collect();
+    versions.sort_by(|a, b| compare_versions(a, b));
+    versions
+}
+
+impl ClosureDiff {
+    pub fn compute(old: &StorePathGraph, new: &StorePathGraph) -> Self {
+        let old_packages = packages(old);
+        let new_packages = packages(new);
+        let empty = Package::default();
+
+        let names: BTreeSet<&String> = old_packages.keys().chain(new_packages.keys()).collect();
+        let mut changes = Vec::new();
+        for name in names {
+            let old_package = old_packages.get(name).unwrap_or(&empty);
+            let new_package = new_packages.get(name).unwrap_or(&empty);
+            if old_package.versions == new_package.versions {
+                continue;
+            }
+
+            let old_versions = sorted_versions(&old_package.versions);
+            let new_versions = sorted_versions(&new_package.versions);
+            let kind = match (old_versions.last(), new_versions.last()) {
+                (None, _) => ChangeKind::Added,
+                (_, None) => ChangeKind::Removed,
+                (Some(old), Some(new)) => match compare_versions(old, new) {
+                    Ordering::Less => ChangeKind::Upgraded,
+                    Ordering::Greater => ChangeKind::Downgraded,
+                    Ordering::Equal => ChangeKind::Changed,
+                },
+            };
+
+            changes.push(PackageChange {
+                name: name.clone(),
+                kind,
+                old_versions,
+                new_versions,
+                old_size: old_package.size,
+                new_size: new_package.size,
+            });
+        }
+        changes.sort_by(|a, b| a.kind.cmp(&b.kind).then_with(|| a.name.cmp(&b.name)));
+
+        let closure_size = |graph: &StorePathGraph| graph.paths().iter().map(|p| p.nar_size).sum();
+
+        Self {
+            old_roots: old.roots.clone(),
+            new_roots: new.roots.clone(),
+            old_closure_size: closure_size(old),
+            new_closure_size: closure_size(new),
+            changes,
+        }
+    }
+
+    pub fn changes_of(&self, kind: ChangeKind) -> impl Iterator<Item = &PackageChange> {
+        self.changes.iter().filter(move |c| c.kind == kind)
+    }
+
+    pub fn render_text(&self) -> String {
+        let mut out = String::new();
+
+        let versions = |change: &PackageChange| match change.kind {
+            ChangeKind::Added => change.new_versions.join(", "),
+            ChangeKind::Removed => change.old_versions.join(", "),
+            _ => format!(
+                "{} → {}",
+                change.old_versions.join(", "),
+                change.new_versions.join(", ")
+            ),
+        };
+        let name_width = self.changes.iter().map(|c| c.name.len()).max().unwrap_or(0);
+        let versions_width = self
+            .changes
+            .iter()
+            .map(|c| versions(c).chars().count())
+            .max()
+            .unwrap_or(0);
+
+        for kind in [
+            ChangeKind::Upgraded,
+            ChangeKind::Downgraded,
+            ChangeKind::Changed,
+            ChangeKind::Added,
+            ChangeKind::Removed,
+        ] {
+            let changes: Vec<&PackageChange> = self.changes_of(kind).collect();
+            if changes.is_empty() {
+                continue;
+            }
+
+            let _ = writeln!(out, "{} ({}):", kind.as_str(), changes.len());
+            for change in changes {
+                let _ = writeln!(
+                    out,
+                    "  {:name_width$}  {:versions_width$}  {}",
+                    change.name,
+                    versions(change),
+                    format_size_delta(change.size_delta())
+                );
+            }
+            out.push('\n');
+        }
+
+        if self.changes.is_empty() {
+            out.push_str("No package changes\n\n");
+        }
+
+        let _ = writeln!(
+            out,
+            "Closure size: {} → {} ({})",
+            bytesize::ByteSize(self.old_closure_size),
+            bytesize::ByteSize(self.new_closure_size),
+            format_size_delta(self.new_closure_size as i64 - self.old_closure_size as i64)
+        );
+
+        out
+    }
+}
+
+pub fn format_size_delta(delta: i64) -> String {
+    let sign = if delta < 0 { '-' } else { '+' };
+    format!("{sign}{}", bytesize::ByteSize(delta.unsigned_abs()))
+}
+
+/// Combine two closures into one graph with the roots of both, for
+/// browsing them side by side
+pub fn merge_graphs(old: &StorePathGraph, new: &StorePathGraph) -> StorePathGraph {
+    let paths = old
+        .paths()
+        .iter()
+        .chain(new.paths())
+        .map(|p| StorePath {
+            name: p.full_name().to_string(),
+            ..p.clone()
+        })
+        .collect();
+
+    let mut roots = old.roots.clone();
+    roots.extend(new.roots.iter().filter(|r| !old.roots.contains(r)).cloned());
+
+    StorePathGraph::from_paths(paths, roots)
+}
+
+/// Paths only in the new closure and paths only in the old one
+pub fn path_changes(old: &StorePathGraph, new: &StorePathGraph) -> (Vec<String>, Vec<String>) {
+    let old_paths: HashSet<&str> = old.paths().iter().map(|p| p.path.as_str()).collect();
+    let new_paths: HashSet<&str> = new.paths().iter().map(|p| p.path.as_str()).collect();
+
+    let added = new_paths
+        .difference(&old_paths)
+        .map(|p| p.to_string())
+        .collect();
+    let removed = old_paths
+        .difference(&new_paths)
+        .map(|p| p.to_string())
+        .collect();
+    (added, removed)
+}
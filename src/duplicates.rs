@@ -0,0 +1,222 @@
+use serde::Serialize;
+use std::collections::HashSet;
+use std::fmt::Write;
+
+use crate::package::{PackageGroup, compare_versions, group_packages};
+use crate::store_path::{PathId, StorePathGraph};
+
+/// How many why-depends chains to keep for each extra version
+pub const MAX_CHAINS: usize = 5;
+
+/// A package present in more than one version
+#[derive(Debug, Clone, Serialize)]
+pub struct Duplicate {
+    pub pname: String,
+    /// The most referred to version first, then the extra ones
+    pub versions: Vec<DuplicateVersion>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DuplicateVersion {
+    pub version: String,
+    pub paths: Vec<String>,
+    /// Paths of other packages referring to this version
+    pub referrers: usize,
+    /// Bytes that would be freed if nothing referred to this version,
+    /// including dependencies only it needs. Zero for the kept version.
+    pub extra_size: u64,
+    /// Chains from the roots to this version, empty for the kept version
+    pub chains: Vec<Vec<String>>,
+}
+
+impl Duplicate {
+    pub fn extra_size(&self) -> u64 {
+        self.versions.iter().map(|v| v.extra_size).sum()
+    }
+}
+
+/// Packages of `graph` that are present in more than one version. Paths
+/// without a version, like `hello.tar.gz` next to `hello-2.12`, are left out
+/// as they aren't another version of the package.
+pub fn duplicated_packages(graph: &StorePathGraph) -> Vec<PackageGroup> {
+    group_packages(graph)
+        .into_iter()
+        .map(|mut group| {
+            group.versions.retain(|v| !v.is_empty());
+            group.paths.retain(|p| {
+                graph
+                    .get_path(p)
+                    .is_some_and(|sp| !sp.package_name().version.is_empty())
+            });
+            group.size = group
+                .paths
+                .iter()
+                .filter_map(|p| graph.get_path(p))
+                .map(|sp| sp.nar_size)
+                .sum();
+            group
+        })
+        .filter(|g| g.versions.len() > 1)
+        .collect()
+}
+
+/// Find every package present in more than one version, with what each
+/// extra version costs and why it is there. The largest costs come first.
+pub fn find_duplicates(graph: &StorePathGraph) -> Vec<Duplicate> {
+    let total_size = reachable_size(graph, &[]);
+
+    let mut duplicates: Vec<Duplicate> = duplicated_packages(graph)
+        .into_iter()
+        .map(|group| {
+            let mut versions: Vec<DuplicateVersion> = group
+                .versions
+                .iter()
+                .map(|version| {
+                    let paths: Vec<String> = group
+                        .paths
+                        .iter()
+                        .filter(|p| {
+                            graph
+                                .get_path(p)
+                                .is_some_and(|sp| sp.package_name().version == *version)
+                        })
+                        .cloned()
+                        .collect();
+                    let ids: Vec<PathId> = paths.iter().filter_map(|p| graph.id(p)).collect();
+                    let referrers = ids
+                        .iter()
+                        .flat_map(|&id| graph.referrer_ids(id))
+                        .filter(|r| !ids.contains(r))
+                        .collect::<HashSet<_>>()
+                        .len();
+
+                    DuplicateVersion {
+                        version: version.clone(),
+                        paths,
+                        referrers,
+                        extra_size: total_size - reachable_size(graph, &ids),
+                        chains: Vec::new(),
+                    }
+                })
+                .collect();
+
+            // Keep the version most of the closure uses, preferring newer ones
+            versions.sort_by(|a, b| {
+                b.referrers
+                    .cmp(&a.referrers)
+                    .then_with(|| compare_versions(&b.version, &a.version))
+            });
+            versions[0].extra_size = 0;
+
+            for version in &mut versions[1..] {
+                // Explain the main output if there is one
+                let path = version
+                    .paths
+                    .iter()
+                    .find(|p| {
+                        graph
+                            .get_path(p)
+                            .is_some_and(|sp| sp.package_name().output.is_none())
+                    })
+                    .or(version.paths.first());
+                if let Some(path) = path {
+                    version.chains = crate::path_stats::why_depends(graph, path);
+                    version.chains.truncate(MAX_CHAINS);
+                }
+            }
+
+            Duplicate {
+                pname: group.pname,
+                versions,
+            }
+        })
+        .collect();
+
+    duplicates.sort_by(|a, b| {
+        b.extra_size()
+            .cmp(&a.extra_size())
+            .then_with(|| a.pname.cmp(&b.pname))
+    });
+    duplicates
+}
+
+/// Total size of the paths reachable from the roots without going through
+/// `excluded`
+fn reachable_size(graph: &StorePathGraph, excluded: &[PathId]) -> u64 {
+    let mut visited = vec![false; graph.len()];
+    for &id in excluded {
+        visited[id] = true;
+    }
+
+    let mut size = 0;
+    let mut to_visit: Vec<PathId> = graph.root_ids();
+    while let Some(id) = to_visit.pop() {
+        if visited[id] {
+            continue;
+        }
+        visited[id] = true;
+        size += graph.path(id).nar_size;
+        to_visit.extend(graph.reference_ids(id).iter().filter(|&&r| !visited[r]));
+    }
+    size
+}
+
+pub fn render_text(duplicates: &[Duplicate], graph: &StorePathGraph) -> String {
+    if duplicates.is_empty() {
+        return "No package is present in more than one version\n".to_string();
+    }
+
+    let short_name = |path: &str| {
+        graph
+            .get_path(path)
+            .map_or(path.to_string(), |p| p.full_name().to_string())
+    };
+
+    let mut out = String::new();
+    for duplicate in duplicates {
+        let _ = writeln!(
+            out,
+            "{} ({} versions, +{})",
+            duplicate.pname,
+            duplicate.versions.len(),
+            bytesize::ByteSize(duplicate.extra_size())
+        );
+
+        for (i, version) in duplicate.versions.iter().enumerate() {
+            let version_name = if version.version.is_empty() {
+                "(no version)"
+            } else {
+                &version.version
+            };
+            if i == 0 {
+                let _ = writeln!(
+                    out,
+                    "  {version_name}: {} referrers (kept)",
+                    version.referrers
+                );
+                continue;
+            }
+
+            let _ = writeln!(
+                out,
+                "  {version_name}: {} referrers, +{}",
+                version.referrers,
+                bytesize::ByteSize(version.extra_size)
+            );
+            for chain in &version.chains {
+                let names: Vec<String> = chain.iter().map(|p| short_name(p)).collect();
+                let _ = writeln!(out, "    {}", names.join(" → "));
+            }
+        }
+        out.push('\n');
+    }
+
+    let total: u64 = duplicates.iter().map(Duplicate::extra_size).sum();
+    let _ = writeln!(
+        out,
+        "{} duplicated packages, {} in extra versions",
+        duplicates.len(),
+        bytesize::ByteSize(total)
+    );
+    out
+}
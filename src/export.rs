@@ -0,0 +1,182 @@
+//! Graphviz DOT export of a closure.
+//!
+//! Nodes are labelled with their name, NAR size and closure size, and filled
+//! from yellow to red by NAR size on a log scale, so the heavy paths stand
+//! out. Roots get a thicker border, and nodes whose references were cut off
+//! by the depth limit are dashed.
+
+use anyhow::{Result, bail};
+use std::collections::{HashMap, VecDeque};
+use std::fmt::Write;
+
+use crate::path_stats::PathStats;
+use crate::store_path::{PathId, StorePathGraph};
+
+/// Which edges to leave out, to keep large graphs readable
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Prune {
+    /// Every reference
+    #[default]
+    None,
+    /// Drop references that are also reachable through another reference
+    Transitive,
+    /// Keep only the edge each path was first reached by from the roots,
+    /// leaving a tree of shortest chains
+    Tree,
+}
+
+impl Prune {
+    pub fn parse(name: &str) -> Result<Self> {
+        Ok(match name {
+            "none" => Prune::None,
+            "transitive" => Prune::Transitive,
+            "tree" => Prune::Tree,
+            _ => bail!("Unknown pruning '{name}', expected none, transitive or tree"),
+        })
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct DotOptions {
+    /// Only include paths at most this many references away from the roots
+    pub max_depth: Option<usize>,
+    pub prune: Prune,
+}
+
+/// Render the closure of `roots` as a DOT digraph
+pub fn render_dot(
+    graph: &StorePathGraph,
+    stats: &HashMap<String, PathStats>,
+    roots: &[PathId],
+    options: &DotOptions,
+) -> String {
+    // Breadth first, so each path gets its shortest distance from the roots
+    // and its parent on a shortest chain
+    let mut depth: Vec<Option<usize>> = vec![None; graph.len()];
+    let mut parent: Vec<Option<PathId>> = vec![None; graph.len()];
+    let mut order = Vec::new();
+    let mut queue = VecDeque::new();
+    for &root in roots {
+        if depth[root].is_none() {
+            depth[root] = Some(0);
+            queue.push_back(root);
+        }
+    }
+    while let Some(id) = queue.pop_front() {
+        order.push(id);
+        let d = depth[id].unwrap_or(0);
+        if options.max_depth.is_some_and(|max| d >= max) {
+            continue;
+        }
+        for &r in graph.reference_ids(id) {
+            if depth[r].is_none() {
+                depth[r] = Some(d + 1);
+                parent[r] = Some(id);
+                queue.push_back(r);
+            }
+        }
+    }
+    let included = |id: PathId| depth[id].is_some();
+
+    let edges: Vec<(PathId, PathId)> = match options.prune {
+        Prune::Tree => order
+            .iter()
+            .filter_map(|&id| parent[id].map(|p| (p, id)))
+            .collect(),
+        Prune::None | Prune::Transitive => {
+            let mut edges = Vec::new();
+            // Generation stamps avoid clearing the visited set per path
+            let mut visited = vec![0u32; graph.len()];
+            let mut generation = 0;
+            for &id in &order {
+                let references: Vec<PathId> = graph
+                    .reference_ids(id)
+                    .iter()
+                    .copied()
+                    .filter(|&r| included(r))
+                    .collect();
+                if options.prune == Prune::None {
+                    edges.extend(references.iter().map(|&r| (id, r)));
+                    continue;
+                }
+
+                // A reference is redundant if another reference leads to it
+                generation += 1;
+                visited[id] = generation;
+                let mut to_visit: Vec<PathId> = references
+                    .iter()
+                    .flat_map(|&r| graph.reference_ids(r).iter().copied())
+                    .collect();
+                while let Some(next) = to_visit.pop() {
+                    if visited[next] == generation || !included(next) {
+                        continue;
+                    }
+                    visited[next] = generation;
+                    to_visit.extend(graph.reference_ids(next));
+                }
+                edges.extend(
+                    references
+                        .iter()
+                        .filter(|&&r| visited[r] != generation)
+                        .map(|&r| (id, r)),
+                );
+            }
+            edges
+        }
+    };
+
+    let max_size = order
+        .iter()
+        .map(|&id| graph.path(id).nar_size)
+        .max()
+        .unwrap_or(0);
+
+    let mut out = String::new();
+    out.push_str("digraph \"nix-tree\" {\n");
+    out.push_str("  rankdir=LR;\n");
+    out.push_str("  node [shape=box, style=\"rounded,filled\", fontname=\"sans-serif\"];\n");
+    for &id in &order {
+        let path = graph.path(id);
+        let closure_size = stats.get(&path.path).map_or(0, |s| s.closure_size);
+
+        let mut style = String::from("rounded,filled");
+        let truncated = options.max_depth.is_some_and(|max| depth[id] == Some(max))
+            && !graph.reference_ids(id).is_empty();
+        if truncated {
+            style.push_str(",dashed");
+        }
+
+        let _ = write!(
+            out,
+            "  n{id} [label=\"{}\\n{} / {}\", tooltip=\"{}\", fillcolor=\"{}\", style=\"{style}\"",
+            escape(path.short_name()),
+            bytesize::ByteSize(path.nar_size),
+            bytesize::ByteSize(closure_size),
+            escape(&path.path),
+            size_color(path.nar_size, max_size),
+        );
+        if depth[id] == Some(0) {
+            out.push_str(", penwidth=2");
+        }
+        out.push_str("];\n");
+    }
+    for (from, to) in edges {
+        let _ = writeln!(out, "  n{from} -> n{to};");
+    }
+    out.push_str("}\n");
+    out
+}
+
+/// An HSV colour from pale yellow for small paths to red for the largest
+fn size_color(size: u64, max_size: u64) -> String {
+    let t = if max_size == 0 {
+        0.0
+    } else {
+        (size as f64).ln_1p() / (max_size as f64).ln_1p()
+    };
+    format!("{:.3} {:.3} 1.000", 0.15 * (1.0 - t), 0.1 + 0.65 * t)
+}
+
+fn escape(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('"', "\\\"")
+}
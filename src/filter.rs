@@ -0,0 +1,409 @@
+//! A small expression language for selecting store paths.
+//!
+//! ```text
+//! firefox                      name contains "firefox" (any case)
+//! name=lib*-dev                name matches a glob, as does a bare lib*-dev
+//! name~'^python3\.\d+-'        name matches a regular expression
+//! closure>100MiB and nar<1MB   sizes: nar, closure, added and retained
+//! refs=0 or referrers>=10      reference and referrer counts
+//! unsigned and depth<=2        signatures and distance from the roots
+//! not (name=*-doc or name=*-man)
+//! ```
+//!
+//! Predicates next to each other are combined with `and`, which binds
+//! tighter than `or`. Values containing spaces or parentheses can be quoted
+//! with `'` or `"`.
+
+use anyhow::{Context, Result, bail};
+use regex::Regex;
+use std::collections::HashMap;
+
+use crate::path_stats::PathStats;
+use crate::store_path::StorePathGraph;
+
+#[derive(Debug, Clone)]
+enum NameMatcher {
+    /// Lowercase, matched against the lowercased name
+    Substring(String),
+    /// Globs are compiled to anchored regular expressions
+    Pattern(Regex),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Field {
+    NarSize,
+    ClosureSize,
+    /// What a root adds to the closure of all the roots; other paths have
+    /// none, as theirs depends on the referrer
+    AddedSize,
+    /// The path's own size plus everything only reachable through it, from
+    /// the dominator tree
+    RetainedSize,
+    References,
+    Referrers,
+    Depth,
+}
+
+impl Field {
+    fn parse(name: &str) -> Option<Self> {
+        Some(match name {
+            "nar" | "size" => Field::NarSize,
+            "closure" => Field::ClosureSize,
+            "added" => Field::AddedSize,
+            "retained" => Field::RetainedSize,
+            "refs" | "references" => Field::References,
+            "referrers" => Field::Referrers,
+            "depth" => Field::Depth,
+            _ => return None,
+        })
+    }
+
+    fn is_size(&self) -> bool {
+        matches!(
+            self,
+            Field::NarSize | Field::ClosureSize | Field::AddedSize | Field::RetainedSize
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Comparison {
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+    Equal,
+    NotEqual,
+}
+
+impl Comparison {
+    fn test(&self, a: u64, b: u64) -> bool {
+        match self {
+            Comparison::Less => a < b,
+            Comparison::LessOrEqual => a <= b,
+            Comparison::Greater => a > b,
+            Comparison::GreaterOrEqual => a >= b,
+            Comparison::Equal => a == b,
+            Comparison::NotEqual => a != b,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Expr {
+    Name(NameMatcher),
+    Compare(Field, Comparison, u64),
+    Signed(bool),
+    Not(Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+}
+
+/// A parsed filter expression
+#[derive(Debug, Clone)]
+pub struct Filter {
+    query: String,
+    expr: Expr,
+}
+
+/// What a filter needs to know about the graph besides the paths themselves
+pub struct FilterContext<'a> {
+    pub graph: &'a StorePathGraph,
+    pub stats: &'a HashMap<String, PathStats>,
+    /// From `path_stats::root_depths`
+    pub depths: &'a [Option<usize>],
+}
+
+impl Filter {
+    pub fn parse(query: &str) -> Result<Self> {
+        let tokens = tokenize(query)?;
+        if tokens.is_empty() {
+            bail!("Empty filter");
+        }
+
+        let mut parser = Parser { tokens, pos: 0 };
+        let expr = parser.parse_or()?;
+        if let Some(token) = parser.tokens.get(parser.pos) {
+            bail!("Unexpected {} in filter", token.describe());
+        }
+
+        Ok(Self {
+            query: query.to_string(),
+            expr,
+        })
+    }
+
+    pub fn query(&self) -> &str {
+        &self.query
+    }
+
+    pub fn matches(&self, ctx: &FilterContext, path: &str) -> bool {
+        eval(&self.expr, ctx, path)
+    }
+
+    /// The paths among `paths` that match, in the same order
+    pub fn apply(&self, ctx: &FilterContext, paths: &[String]) -> Vec<String> {
+        paths
+            .iter()
+            .filter(|p| self.matches(ctx, p))
+            .cloned()
+            .collect()
+    }
+}
+
+fn eval(expr: &Expr, ctx: &FilterContext, path: &str) -> bool {
+    let Some(id) = ctx.graph.id(path) else {
+        return false;
+    };
+    let store_path = ctx.graph.path(id);
+
+    match expr {
+        Expr::Name(NameMatcher::Substring(s)) => store_path.full_name().to_lowercase().contains(s),
+        Expr::Name(NameMatcher::Pattern(re)) => re.is_match(store_path.full_name()),
+        Expr::Compare(field, comparison, value) => {
+            let stats = ctx.stats.get(path);
+            let actual = match field {
+                Field::NarSize => Some(store_path.nar_size),
+                Field::ClosureSize => stats.map(|s| s.closure_size),
+                Field::AddedSize => stats.and_then(|s| s.added_size),
+                Field::RetainedSize => stats.map(|s| s.retained_size),
+                Field::References => Some(ctx.graph.reference_ids(id).len() as u64),
+                Field::Referrers => Some(ctx.graph.referrer_ids(id).len() as u64),
+                Field::Depth => ctx.depths.get(id).copied().flatten().map(|d| d as u64),
+            };
+            actual.is_some_and(|actual| comparison.test(actual, *value))
+        }
+        Expr::Signed(signed) => store_path.is_signed() == *signed,
+        Expr::Not(inner) => !eval(inner, ctx, path),
+        Expr::And(a, b) => eval(a, ctx, path) && eval(b, ctx, path),
+        Expr::Or(a, b) => eval(a, ctx, path) || eval(b, ctx, path),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Open,
+    Close,
+    Not,
+    Word(String),
+}
+
+impl Token {
+    fn describe(&self) -> String {
+        match self {
+            Token::Open => "'('".to_string(),
+            Token::Close => "')'".to_string(),
+            Token::Not => "'!'".to_string(),
+            Token::Word(word) => format!("'{word}'"),
+        }
+    }
+}
+
+fn tokenize(query: &str) -> Result<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut chars = query.chars().peekable();
+
+    while let Some(&c) = chars.peek() {
+        match c {
+            c if c.is_whitespace() => {
+                chars.next();
+            }
+            '(' => {
+                chars.next();
+                tokens.push(Token::Open);
+            }
+            ')' => {
+                chars.next();
+                tokens.push(Token::Close);
+            }
+            '!' => {
+                chars.next();
+                tokens.push(Token::Not);
+            }
+            _ => {
+                // A word runs until whitespace or a parenthesis outside quotes
+                let mut word = String::new();
+                let mut quote = None;
+                while let Some(&c) = chars.peek() {
+                    match quote {
+                        Some(q) if c == q => quote = None,
+                        Some(_) => word.push(c),
+                        None if c == '\'' || c == '"' => quote = Some(c),
+                        None if c.is_whitespace() || c == '(' || c == ')' => break,
+                        None => word.push(c),
+                    }
+                    chars.next();
+                }
+                if quote.is_some() {
+                    bail!("Unterminated quote in filter");
+                }
+                tokens.push(Token::Word(word));
+            }
+        }
+    }
+
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek_keyword(&self, keyword: &str) -> bool {
+        matches!(self.tokens.get(self.pos), Some(Token::Word(w)) if w.eq_ignore_ascii_case(keyword))
+    }
+
+    fn parse_or(&mut self) -> Result<Expr> {
+        let mut expr = self.parse_and()?;
+        while self.peek_keyword("or") {
+            self.pos += 1;
+            expr = Expr::Or(Box::new(expr), Box::new(self.parse_and()?));
+        }
+        Ok(expr)
+    }
+
+    fn parse_and(&mut self) -> Result<Expr> {
+        let mut expr = self.parse_unary()?;
+        loop {
+            if self.peek_keyword("and") {
+                self.pos += 1;
+            } else if self.pos >= self.tokens.len()
+                || self.tokens[self.pos] == Token::Close
+                || self.peek_keyword("or")
+            {
+                return Ok(expr);
+            }
+            expr = Expr::And(Box::new(expr), Box::new(self.parse_unary()?));
+        }
+    }
+
+    fn parse_unary(&mut self) -> Result<Expr> {
+        let Some(token) = self.tokens.get(self.pos).cloned() else {
+            bail!("Filter ends unexpectedly");
+        };
+        self.pos += 1;
+
+        match token {
+            Token::Not => Ok(Expr::Not(Box::new(self.parse_unary()?))),
+            Token::Word(w) if w.eq_ignore_ascii_case("not") => {
+                Ok(Expr::Not(Box::new(self.parse_unary()?)))
+            }
+            Token::Open => {
+                let expr = self.parse_or()?;
+                if self.tokens.get(self.pos) != Some(&Token::Close) {
+                    bail!("Missing ')' in filter");
+                }
+                self.pos += 1;
+                Ok(expr)
+            }
+            Token::Word(w) if w.eq_ignore_ascii_case("and") || w.eq_ignore_ascii_case("or") => {
+                bail!("Expected a predicate before '{w}'")
+            }
+            Token::Word(w) => parse_predicate(&w),
+            Token::Close => bail!("Unexpected ')' in filter"),
+        }
+    }
+}
+
+fn parse_predicate(word: &str) -> Result<Expr> {
+    match word {
+        "signed" => return Ok(Expr::Signed(true)),
+        "unsigned" => return Ok(Expr::Signed(false)),
+        _ => {}
+    }
+
+    let Some(op_start) = word.find(['<', '>', '=', '!', '~']) else {
+        if word.contains(['*', '?']) {
+            return Ok(Expr::Name(NameMatcher::Pattern(Regex::new(
+                &glob_to_regex(word),
+            )?)));
+        }
+        return Ok(Expr::Name(NameMatcher::Substring(word.to_lowercase())));
+    };
+    let field = &word[..op_start];
+    let rest = &word[op_start..];
+
+    let (op, value) = ["<=", ">=", "!=", "<", ">", "=", "~"]
+        .into_iter()
+        .find_map(|op| rest.strip_prefix(op).map(|value| (op, value)))
+        .with_context(|| format!("Invalid operator in '{word}'"))?;
+
+    if field == "name" {
+        let (pattern, negate) = match op {
+            "=" => (glob_to_regex(value), false),
+            "!=" => (glob_to_regex(value), true),
+            "~" => (value.to_string(), false),
+            _ => bail!("name supports =, != and ~, not {op}"),
+        };
+        let re = Regex::new(&pattern).with_context(|| format!("Invalid pattern '{value}'"))?;
+        let expr = Expr::Name(NameMatcher::Pattern(re));
+        return Ok(if negate {
+            Expr::Not(Box::new(expr))
+        } else {
+            expr
+        });
+    }
+
+    let field = Field::parse(field).with_context(|| format!("Unknown field '{field}'"))?;
+    let comparison = match op {
+        "<" => Comparison::Less,
+        "<=" => Comparison::LessOrEqual,
+        ">" => Comparison::Greater,
+        ">=" => Comparison::GreaterOrEqual,
+        "=" => Comparison::Equal,
+        "!=" => Comparison::NotEqual,
+        _ => bail!("{op} only applies to name"),
+    };
+    let value = if field.is_size() {
+        parse_size(value)?
+    } else {
+        value
+            .parse()
+            .with_context(|| format!("Invalid number '{value}'"))?
+    };
+
+    Ok(Expr::Compare(field, comparison, value))
+}
+
+/// An anchored regular expression matching the same names as `glob`
+pub(crate) fn glob_to_regex(glob: &str) -> String {
+    let mut re = String::from("^");
+    for c in glob.chars() {
+        match c {
+            '*' => re.push_str(".*"),
+            '?' => re.push('.'),
+            c => re.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    re.push('$');
+    re
+}
+
+/// Parse sizes like `512`, `10KB`, `1.5MiB` or `2G`. Units are
+/// case-insensitive; `K`, `M` and `G` alone are binary.
+pub fn parse_size(value: &str) -> Result<u64> {
+    let split = value
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(value.len());
+    let (number, unit) = value.split_at(split);
+    let number: f64 = number
+        .parse()
+        .with_context(|| format!("Invalid size '{value}'"))?;
+
+    let multiplier: u64 = match unit.trim().to_lowercase().as_str() {
+        "" | "b" => 1,
+        "kb" => 1000,
+        "k" | "kib" => 1 << 10,
+        "mb" => 1000 * 1000,
+        "m" | "mib" => 1 << 20,
+        "gb" => 1000 * 1000 * 1000,
+        "g" | "gib" => 1 << 30,
+        "tb" => 1000 * 1000 * 1000 * 1000,
+        "t" | "tib" => 1 << 40,
+        _ => bail!("Unknown size unit in '{value}'"),
+    };
+
+    Ok((number * multiplier as f64) as u64)
+}
@@ -0,0 +1,129 @@
+//! Fuzzy matching for the search box, scored like fzf: the query's
+//! characters have to appear in order, and matches are better the more of
+//! them are consecutive or start a word.
+
+/// Points for each matched character
+const SCORE_MATCH: i32 = 16;
+/// Penalty for the first character skipped between two matched ones
+const GAP_START: i32 = -3;
+/// Penalty for each further character skipped
+const GAP_EXTENSION: i32 = -1;
+/// Bonus for matching the first character of a word
+const BONUS_BOUNDARY: i32 = 8;
+/// Bonus for matching right after the previous matched character, at
+/// least; a run of them all get the bonus of its first character
+const BONUS_CONSECUTIVE: i32 = 4;
+/// The first character of the query counts this many times its bonus
+const FIRST_CHAR_MULTIPLIER: i32 = 2;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FuzzyMatch {
+    pub score: i32,
+    /// Indices of the matched characters (not bytes) in the text
+    pub positions: Vec<usize>,
+}
+
+/// Whether `query` is a plain word to match fuzzily, rather than a filter
+/// expression like `name=*-dev` or `closure>10MB and unsigned`
+pub fn is_fuzzy_query(query: &str) -> bool {
+    let query = query.trim();
+    !query.is_empty()
+        && !query.contains(|c: char| c.is_whitespace() || "<>=!~*?()'\"".contains(c))
+        && !matches!(query, "signed" | "unsigned" | "not" | "and" | "or")
+}
+
+fn bonus(text: &[char], i: usize) -> i32 {
+    match i.checked_sub(1).map(|prev| text[prev]) {
+        None => BONUS_BOUNDARY,
+        Some(prev) if !prev.is_alphanumeric() => BONUS_BOUNDARY,
+        // camelCase and the start of a version number
+        Some(prev) if prev.is_lowercase() && text[i].is_uppercase() => BONUS_BOUNDARY - 1,
+        Some(prev) if !prev.is_ascii_digit() && text[i].is_ascii_digit() => BONUS_BOUNDARY - 1,
+        Some(_) => 0,
+    }
+}
+
+/// Match `query` against `text`, ignoring case unless the query has
+/// uppercase letters. Returns the best scoring alignment, if the query is a
+/// subsequence of the text at all.
+pub fn fuzzy_match(query: &str, text: &str) -> Option<FuzzyMatch> {
+    let case_sensitive = query.chars().any(char::is_uppercase);
+    let fold = |c: char| {
+        if case_sensitive {
+            c
+        } else {
+            c.to_lowercase().next().unwrap_or(c)
+        }
+    };
+    let pattern: Vec<char> = query.chars().map(fold).collect();
+    let chars: Vec<char> = text.chars().collect();
+    let folded: Vec<char> = chars.iter().map(|&c| fold(c)).collect();
+    if pattern.is_empty() {
+        return None;
+    }
+
+    // Cheap rejection before scoring
+    let mut rest = folded.iter();
+    if !pattern.iter().all(|p| rest.any(|c| c == p)) {
+        return None;
+    }
+
+    // scores[i][j]: the best score with pattern[i] matched at text[j],
+    // from[i][j] the position pattern[i - 1] was matched at for it, and
+    // runs[i][j] the bonus of the run of consecutive matches it ends
+    let n = chars.len();
+    let mut scores = vec![vec![None; n]; pattern.len()];
+    let mut from = vec![vec![0; n]; pattern.len()];
+    let mut runs = vec![vec![0; n]; pattern.len()];
+    for (i, &p) in pattern.iter().enumerate() {
+        // The best score of the previous character matched at least two
+        // positions back, with the gap to here already charged
+        let mut gapped: Option<(i32, usize)> = None;
+        for j in 0..n {
+            if i > 0 && j >= 2 {
+                let extended = gapped.map(|(score, k)| (score + GAP_EXTENSION, k));
+                let started = scores[i - 1][j - 2].map(|score: i32| (score + GAP_START, j - 2));
+                gapped = match (extended, started) {
+                    (Some(a), Some(b)) => Some(if b.0 >= a.0 { b } else { a }),
+                    (a, b) => a.or(b),
+                };
+            }
+            if folded[j] != p {
+                continue;
+            }
+
+            let bonus = bonus(&chars, j);
+            if i == 0 {
+                scores[i][j] = Some(SCORE_MATCH + bonus * FIRST_CHAR_MULTIPLIER);
+                runs[i][j] = bonus;
+                continue;
+            }
+            let consecutive = j.checked_sub(1).and_then(|k| {
+                let run = bonus.max(runs[i - 1][k]).max(BONUS_CONSECUTIVE);
+                scores[i - 1][k].map(|score| (score + run, k, run))
+            });
+            let gap = gapped.map(|(score, k)| (score + bonus, k, bonus));
+            let best = match (consecutive, gap) {
+                (Some(a), Some(b)) => Some(if a.0 >= b.0 { a } else { b }),
+                (a, b) => a.or(b),
+            };
+            if let Some((score, k, run)) = best {
+                scores[i][j] = Some(score + SCORE_MATCH);
+                from[i][j] = k;
+                runs[i][j] = run;
+            }
+        }
+    }
+
+    let last = pattern.len() - 1;
+    let (mut j, score) = (0..n)
+        .filter_map(|j| scores[last][j].map(|score| (j, score)))
+        .max_by_key(|&(j, score)| (score, std::cmp::Reverse(j)))?;
+    let mut positions = vec![j; pattern.len()];
+    for i in (1..pattern.len()).rev() {
+        j = from[i][j];
+        positions[i - 1] = j;
+    }
+
+    Some(FuzzyMatch { score, positions })
+}
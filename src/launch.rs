@@ -0,0 +1,183 @@
+//! Looking inside a store path with a shell, pager or file browser. The
+//! main loop suspends the TUI while they run.
+
+use anyhow::{Context, Result, bail};
+use std::fs;
+use std::io::{self, BufWriter, Write};
+use std::os::unix::process::CommandExt;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Program {
+    /// `$SHELL`, working in the path, or in the store path a file one
+    /// links into
+    Shell,
+    /// `$PAGER` on the path, or on a listing of it if it's a directory
+    Pager,
+    /// A configured file browser, given the path
+    Browser,
+}
+
+/// The commands to run, overriding the environment
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Launcher {
+    /// Instead of `$SHELL`
+    pub shell: Option<Vec<String>>,
+    /// Instead of `$PAGER`
+    pub pager: Option<Vec<String>>,
+    /// Run with the path as its last argument
+    pub browser: Option<Vec<String>>,
+}
+
+/// `$name` split at whitespace, if it's set
+fn from_env(name: &str) -> Option<Vec<String>> {
+    let value = std::env::var(name).ok()?;
+    let command: Vec<String> = value.split_whitespace().map(str::to_string).collect();
+    (!command.is_empty()).then_some(command)
+}
+
+impl Launcher {
+    /// The command line to run `program` with
+    pub fn command_line(&self, program: Program) -> Result<Vec<String>> {
+        let command = match program {
+            Program::Shell => self.shell.clone().or_else(|| from_env("SHELL")),
+            Program::Pager => self.pager.clone().or_else(|| from_env("PAGER")),
+            Program::Browser => self.browser.clone(),
+        };
+        Ok(match (program, command) {
+            (_, Some(command)) => command,
+            (Program::Shell, None) => vec!["sh".to_string()],
+            (Program::Pager, None) => vec!["less".to_string()],
+            (Program::Browser, None) => {
+                bail!("No file browser is configured, set open.browser in the config file")
+            }
+        })
+    }
+
+    /// Run `program` on the store path `path` and wait for it. The terminal
+    /// must already be given back to it.
+    pub fn run(&self, program: Program, path: &Path) -> Result<()> {
+        let metadata = fs::metadata(path)
+            .with_context(|| format!("{} isn't in the local store", path.display()))?;
+        let command = self.command_line(program)?;
+        let (name, args) = command.split_first().expect("command lines aren't empty");
+
+        let mut cmd = Command::new(name);
+        cmd.args(args);
+        match program {
+            Program::Shell => {
+                cmd.current_dir(shell_dir(path)?);
+            }
+            Program::Pager if metadata.is_dir() => {
+                cmd.stdin(Stdio::piped());
+            }
+            Program::Pager | Program::Browser => {
+                cmd.arg(path);
+            }
+        }
+        // Ctrl-C is for the program, which gets back the default handling
+        // of the SIGINT we ignore while it runs. Only async-signal-safe
+        // calls are allowed between fork and exec, which signal is.
+        unsafe {
+            cmd.pre_exec(|| {
+                libc::signal(libc::SIGINT, libc::SIG_DFL);
+                Ok(())
+            });
+        }
+
+        let _interrupts = IgnoreInterrupts::new()?;
+        let mut child = cmd
+            .spawn()
+            .with_context(|| format!("Failed to run {name}"))?;
+        // A directory is listed to the pager as it reads
+        let listed = match child.stdin.take() {
+            Some(stdin) => write_listing(path, &mut BufWriter::new(stdin)),
+            None => Ok(()),
+        };
+        let status = child.wait()?;
+        match listed {
+            // The pager may quit before reading it all
+            Err(e) if e.kind() == io::ErrorKind::BrokenPipe => {}
+            Err(e) => {
+                return Err(e).with_context(|| format!("Failed to list {}", path.display()));
+            }
+            Ok(()) => {}
+        }
+        // A shell exits with whatever the last command it ran did
+        if !status.success() && program != Program::Shell {
+            bail!("{name} failed with {status}");
+        }
+        Ok(())
+    }
+}
+
+/// Ignores SIGINT until dropped, then puts back how it was handled before
+struct IgnoreInterrupts(libc::sigaction);
+
+impl IgnoreInterrupts {
+    fn new() -> io::Result<Self> {
+        unsafe {
+            let mut ignore: libc::sigaction = std::mem::zeroed();
+            ignore.sa_sigaction = libc::SIG_IGN;
+            let mut previous: libc::sigaction = std::mem::zeroed();
+            if libc::sigaction(libc::SIGINT, &ignore, &mut previous) != 0 {
+                return Err(io::Error::last_os_error());
+            }
+            Ok(Self(previous))
+        }
+    }
+}
+
+impl Drop for IgnoreInterrupts {
+    fn drop(&mut self) {
+        unsafe {
+            libc::sigaction(libc::SIGINT, &self.0, std::ptr::null_mut());
+        }
+    }
+}
+
+/// Where the shell works for the store path `path`: the path itself if it's
+/// a directory, otherwise the store path containing the file it resolves
+/// to, like the package a link points into. A file that is a store path of
+/// its own has only the store to work in.
+pub fn shell_dir(path: &Path) -> Result<PathBuf> {
+    let resolved =
+        fs::canonicalize(path).with_context(|| format!("Failed to resolve {}", path.display()))?;
+    if resolved.is_dir() {
+        return Ok(resolved);
+    }
+    let store = fs::canonicalize(path.parent().unwrap_or(path))?;
+    Ok(resolved
+        .ancestors()
+        .find(|dir| dir.parent() == Some(store.as_path()) && dir.is_dir())
+        .unwrap_or(&store)
+        .to_path_buf())
+}
+
+/// Write everything under `dir`, one entry a line like `ls -R` but with the
+/// relative path of each, marking directories with `/` and showing where
+/// links point
+pub fn write_listing(dir: &Path, out: &mut impl Write) -> io::Result<()> {
+    list_into(dir, Path::new(""), out)?;
+    out.flush()
+}
+
+fn list_into(dir: &Path, prefix: &Path, out: &mut impl Write) -> io::Result<()> {
+    let mut entries = fs::read_dir(dir)?.collect::<io::Result<Vec<_>>>()?;
+    entries.sort_by_key(|e| e.file_name());
+    for entry in entries {
+        let relative = prefix.join(entry.file_name());
+        let file_type = entry.file_type()?;
+        if file_type.is_symlink() {
+            let target = fs::read_link(entry.path())?;
+            writeln!(out, "{} -> {}", relative.display(), target.display())?;
+        } else if file_type.is_dir() {
+            writeln!(out, "{}/", relative.display())?;
+            list_into(&entry.path(), &relative, out)?;
+        } else {
+            writeln!(out, "{}", relative.display())?;
+        }
+    }
+    Ok(())
+}
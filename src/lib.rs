@@ -1,5 +1,19 @@
+pub mod backend;
+pub mod budget;
 pub mod cli;
+pub mod clipboard;
+pub mod derivation;
+pub mod diff;
+pub mod duplicates;
+pub mod export;
+pub mod filter;
+pub mod fuzzy;
+pub mod launch;
+pub mod lint;
 pub mod nix;
+pub mod package;
 pub mod path_stats;
+pub mod report;
+pub mod settings;
 pub mod store_path;
 pub mod ui;
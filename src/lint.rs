@@ -0,0 +1,208 @@
+//! Forbidden dependencies, checked by `nix-tree lint --rules <file>`.
+//!
+//! ```toml
+//! [[rule]]
+//! forbid = ["gcc", "perl", "*-dev"]
+//! reason = "runtime closures must not ship build tools"
+//!
+//! [[rule]]
+//! # Only the closures of these paths, instead of the roots'
+//! closure_of = ["nginx-*"]
+//! forbid = ["python3"]
+//! ```
+//!
+//! Patterns are globs matched against both the package name (`gcc` matches
+//! `gcc-13.2.0` and `gcc-13.2.0-lib`) and the full name (`*-dev` matches
+//! `zlib-1.3-dev`).
+
+use anyhow::{Context, Result, bail};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::fmt::Write;
+use std::path::Path;
+
+use crate::filter::glob_to_regex;
+use crate::path_stats::{chain_to, shortest_parents};
+use crate::store_path::{PathId, StorePath, StorePathGraph};
+
+/// The exit status of `nix-tree lint` when a rule is broken, telling it apart
+/// from failing to load the closure or the rules, which exit with 1
+pub const EXIT_VIOLATIONS: i32 = 2;
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Rules {
+    #[serde(default, rename = "rule")]
+    pub rules: Vec<Rule>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Rule {
+    /// Paths whose closures the rule applies to; the roots if empty. The
+    /// paths themselves are what the rule is about, so they can't break it.
+    #[serde(default)]
+    pub closure_of: Vec<String>,
+    pub forbid: Vec<String>,
+    /// Shown with each violation
+    pub reason: Option<String>,
+}
+
+impl Rule {
+    pub fn describe(&self) -> String {
+        let scope = if self.closure_of.is_empty() {
+            "the closure".to_string()
+        } else {
+            format!("the closure of {}", self.closure_of.join(", "))
+        };
+        format!("{scope} must not contain {}", self.forbid.join(", "))
+    }
+}
+
+impl Rules {
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read rules {}", path.display()))?;
+        let rules: Rules = toml::from_str(&text)
+            .with_context(|| format!("Failed to parse rules {}", path.display()))?;
+
+        if let Some(rule) = rules.rules.iter().find(|r| r.forbid.is_empty()) {
+            bail!("A rule forbids nothing: {}", rule.describe());
+        }
+        Ok(rules)
+    }
+}
+
+/// A forbidden path in the closure a rule applies to
+#[derive(Debug, Clone, Serialize)]
+pub struct Violation {
+    /// The position of the broken rule in the rules file
+    pub rule_index: usize,
+    pub rule: String,
+    pub reason: Option<String>,
+    /// The `forbid` pattern that matched
+    pub pattern: String,
+    pub path: String,
+    /// The shortest chain from the roots, through one of the rule's
+    /// `closure_of` paths if it has any
+    pub chain: Vec<String>,
+}
+
+struct Pattern {
+    glob: String,
+    re: Regex,
+}
+
+impl Pattern {
+    fn new(glob: &str) -> Self {
+        Self {
+            glob: glob.to_string(),
+            re: Regex::new(&glob_to_regex(glob)).expect("globs are escaped"),
+        }
+    }
+
+    fn matches(&self, path: &StorePath) -> bool {
+        self.re.is_match(&path.package_name().pname) || self.re.is_match(path.full_name())
+    }
+}
+
+/// Every violation of `rules`, in rule order and then by chain length
+pub fn check(rules: &Rules, graph: &StorePathGraph) -> Vec<Violation> {
+    let root_parents = shortest_parents(graph, &graph.root_ids());
+    let mut violations = Vec::new();
+
+    for (rule_index, rule) in rules.rules.iter().enumerate() {
+        let sources: Vec<PathId> = if rule.closure_of.is_empty() {
+            graph.root_ids()
+        } else {
+            let scope: Vec<Pattern> = rule.closure_of.iter().map(|g| Pattern::new(g)).collect();
+            (0..graph.len())
+                .filter(|&id| scope.iter().any(|p| p.matches(graph.path(id))))
+                .collect()
+        };
+        let parents = shortest_parents(graph, &sources);
+        let mut is_source = vec![false; graph.len()];
+        for &id in &sources {
+            is_source[id] = true;
+        }
+        let forbidden: Vec<Pattern> = rule.forbid.iter().map(|g| Pattern::new(g)).collect();
+
+        let mut rule_violations: Vec<Violation> = (0..graph.len())
+            .filter(|&id| parents[id].is_some() && !is_source[id])
+            .filter_map(|id| {
+                let pattern = forbidden.iter().find(|p| p.matches(graph.path(id)))?;
+
+                let mut chain = chain_to(&parents, id);
+                // Lead up to the source from the roots
+                if root_parents[chain[0]].is_some() {
+                    let mut lead = chain_to(&root_parents, chain[0]);
+                    lead.pop();
+                    lead.append(&mut chain);
+                    chain = lead;
+                }
+
+                Some(Violation {
+                    rule_index,
+                    rule: rule.describe(),
+                    reason: rule.reason.clone(),
+                    pattern: pattern.glob.clone(),
+                    path: graph.path(id).path.clone(),
+                    chain: chain
+                        .into_iter()
+                        .map(|id| graph.path(id).path.clone())
+                        .collect(),
+                })
+            })
+            .collect();
+        rule_violations.sort_by(|a, b| {
+            a.chain
+                .len()
+                .cmp(&b.chain.len())
+                .then_with(|| a.path.cmp(&b.path))
+        });
+        violations.extend(rule_violations);
+    }
+
+    violations
+}
+
+pub fn render_text(violations: &[Violation], graph: &StorePathGraph) -> String {
+    if violations.is_empty() {
+        return "No forbidden dependencies\n".to_string();
+    }
+
+    let short_name = |path: &str| {
+        graph
+            .get_path(path)
+            .map_or(path.to_string(), |p| p.full_name().to_string())
+    };
+
+    let mut out = String::new();
+    let mut last_rule = None;
+    for violation in violations {
+        // Rules can read the same, so they're told apart by position
+        if last_rule != Some(violation.rule_index) {
+            if last_rule.is_some() {
+                out.push('\n');
+            }
+            let _ = write!(out, "{}", violation.rule);
+            if let Some(reason) = &violation.reason {
+                let _ = write!(out, " ({reason})");
+            }
+            out.push_str(":\n");
+            last_rule = Some(violation.rule_index);
+        }
+
+        let names: Vec<String> = violation.chain.iter().map(|p| short_name(p)).collect();
+        let _ = writeln!(
+            out,
+            "  {} matches {}\n    {}",
+            short_name(&violation.path),
+            violation.pattern,
+            names.join(" → ")
+        );
+    }
+
+    let _ = writeln!(out, "\n{} forbidden dependencies", violations.len());
+    out
+}
@@ -1,22 +1,25 @@
-mod cli;
-mod nix;
-mod path_stats;
-mod store_path;
-mod ui;
-
-use anyhow::Result;
+use anyhow::{Context, Result};
 use crossterm::{
-    event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyEventKind},
+    event::{
+        self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyEventKind, KeyModifiers,
+    },
     execute,
     terminal::{EnterAlternateScreen, LeaveAlternateScreen, disable_raw_mode, enable_raw_mode},
 };
-use ratatui::{
-    Terminal,
-    backend::CrosstermBackend,
-    layout::{Constraint, Layout},
-};
+use nix_tree::backend::{self, LoadProgress};
+use nix_tree::budget::{self, Budget};
+use nix_tree::diff::ClosureDiff;
+use nix_tree::filter::{Filter, FilterContext};
+use nix_tree::lint::{self, Rules};
+use nix_tree::report::Report;
+use nix_tree::settings::Settings;
+use nix_tree::store_path::StorePathGraph;
+use nix_tree::{cli, duplicates, export, path_stats, ui};
+use ratatui::{Terminal, backend::CrosstermBackend, layout::Rect};
 use std::io;
-use std::time::Duration;
+use std::path::Path;
+use std::time::{Duration, Instant};
+use tokio::task::JoinHandle;
 
 #[tokio::main]
 async fn main() -> Result<()> {
@@ -32,40 +35,245 @@ async fn main() -> Result<()> {
         return Ok(());
     }
 
-    let mut paths = if config.paths.is_empty() {
-        nix::get_default_roots().await?
-    } else {
-        config.paths
-    };
-
-    // Resolve symlinks for paths outside the Nix store
-    for path in &mut paths {
-        if !path.starts_with("/nix/store/") {
-            if let Ok(resolved) = tokio::fs::canonicalize(&path).await {
-                *path = resolved.to_string_lossy().to_string();
-            }
+    match config.command {
+        cli::Command::Browse if config.export.is_some() => run_export(config).await,
+        cli::Command::Browse if config.filter.is_some() => run_filter(config).await,
+        cli::Command::Browse => {
+            let settings = load_settings(&config)?;
+            let rules = config
+                .rules
+                .as_deref()
+                .map(|path| Rules::load(Path::new(path)))
+                .transpose()?;
+            let progress = LoadProgress::default();
+            let loader = {
+                let progress = progress.clone();
+                tokio::spawn(async move {
+                    let store_backend = backend::from_config(&config).await?;
+                    let mut app =
+                        ui::App::from_backend(store_backend.as_ref(), &config.paths, &progress)
+                            .await?;
+                    app.dot_options = config.dot_options.clone();
+                    if let Some(rules) = &rules {
+                        app.highlight_violations(&lint::check(rules, &app.graph));
+                    }
+                    Ok(app)
+                })
+            };
+            run_tui(loader, &progress, &settings).await
         }
+        cli::Command::Diff => run_diff(config).await,
+        cli::Command::Duplicates => run_duplicates(config).await,
+        cli::Command::Report => run_report(config).await,
+        cli::Command::Check => run_check(config).await,
+        cli::Command::Lint => run_lint(config).await,
     }
+}
 
-    println!("Loading store paths...");
-    let graph = nix::query_path_info(
-        &paths,
-        true,
-        config.store.as_deref(),
-        &config.nix_options,
-        config.file.as_deref(),
+/// The config file given with `--config`, or the one in `$XDG_CONFIG_HOME`
+fn load_settings(config: &cli::Config) -> Result<Settings> {
+    Settings::load(config.config_file.as_deref().map(Path::new))
+}
+
+/// Load the graph without the TUI, for the reporting subcommands
+async fn load_graph(config: &cli::Config) -> Result<StorePathGraph> {
+    let store_backend = backend::from_config(config).await?;
+    backend::load_graph(
+        store_backend.as_ref(),
+        &config.paths,
+        &LoadProgress::default(),
     )
-    .await?;
+    .await
+}
+
+/// Print the closure of the roots as Graphviz DOT
+async fn run_export(config: cli::Config) -> Result<()> {
+    let graph = load_graph(&config).await?;
+    let stats = path_stats::calculate_stats(&graph);
+    print!(
+        "{}",
+        export::render_dot(&graph, &stats, &graph.root_ids(), &config.dot_options)
+    );
+    Ok(())
+}
+
+/// Print the paths matching `--filter`, largest closures first
+async fn run_filter(config: cli::Config) -> Result<()> {
+    let filter = Filter::parse(config.filter.as_deref().unwrap_or_default())?;
+
+    let graph = load_graph(&config).await?;
+    let stats = path_stats::calculate_stats(&graph);
+    let depths = path_stats::root_depths(&graph);
+    let ctx = FilterContext {
+        graph: &graph,
+        stats: &stats,
+        depths: &depths,
+    };
+
+    let mut paths: Vec<String> = graph
+        .paths()
+        .iter()
+        .filter(|p| filter.matches(&ctx, &p.path))
+        .map(|p| p.path.clone())
+        .collect();
+    path_stats::sort_paths(&mut paths, &stats, path_stats::SortOrder::ClosureSize);
+
+    for path in paths {
+        println!("{path}");
+    }
+
+    Ok(())
+}
+
+async fn run_report(config: cli::Config) -> Result<()> {
+    let filter = config.filter.as_deref().map(Filter::parse).transpose()?;
+
+    let graph = load_graph(&config).await?;
+    let stats = path_stats::calculate_stats(&graph);
+    let depths = path_stats::root_depths(&graph);
+    let ctx = FilterContext {
+        graph: &graph,
+        stats: &stats,
+        depths: &depths,
+    };
+    let report = Report::new(&graph, &stats, &depths, |path| {
+        filter.as_ref().is_none_or(|f| f.matches(&ctx, path))
+    });
+
+    if config.json {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    } else {
+        print!("{}", report.render_text());
+    }
 
-    println!("Calculating sizes...");
+    Ok(())
+}
+
+/// Exits with `budget::EXIT_OVER_BUDGET` if any budget check fails
+async fn run_check(config: cli::Config) -> Result<()> {
+    let mut budget = Budget::load(Path::new(config.budget.as_deref().unwrap_or_default()))?;
+    if let Some(baseline) = &config.baseline {
+        budget.baseline = Some(baseline.into());
+    }
+    let baseline: Option<Report> = match &budget.baseline {
+        Some(path) => {
+            let json = std::fs::read_to_string(path)
+                .with_context(|| format!("Failed to read baseline {}", path.display()))?;
+            Some(
+                serde_json::from_str(&json)
+                    .with_context(|| format!("Failed to parse baseline {}", path.display()))?,
+            )
+        }
+        None => None,
+    };
+
+    let graph = load_graph(&config).await?;
     let stats = path_stats::calculate_stats(&graph);
+    let depths = path_stats::root_depths(&graph);
+    let checks = budget::check(&budget, &graph, &stats, &depths, baseline.as_ref())?;
+
+    if config.json {
+        println!("{}", serde_json::to_string_pretty(&checks)?);
+    } else {
+        print!("{}", budget::render_text(&checks, &graph));
+    }
+
+    if checks.iter().any(|c| !c.passed) {
+        std::process::exit(budget::EXIT_OVER_BUDGET);
+    }
+    Ok(())
+}
+
+/// Exits with `lint::EXIT_VIOLATIONS` if anything is forbidden
+async fn run_lint(config: cli::Config) -> Result<()> {
+    let rules = Rules::load(Path::new(config.rules.as_deref().unwrap_or_default()))?;
+
+    let graph = load_graph(&config).await?;
+    let violations = lint::check(&rules, &graph);
+
+    if config.json {
+        println!("{}", serde_json::to_string_pretty(&violations)?);
+    } else {
+        print!("{}", lint::render_text(&violations, &graph));
+    }
+
+    if !violations.is_empty() {
+        std::process::exit(lint::EXIT_VIOLATIONS);
+    }
+    Ok(())
+}
+
+async fn run_duplicates(config: cli::Config) -> Result<()> {
+    let graph = load_graph(&config).await?;
+    let duplicates = duplicates::find_duplicates(&graph);
+
+    if config.json {
+        println!("{}", serde_json::to_string_pretty(&duplicates)?);
+    } else {
+        print!("{}", duplicates::render_text(&duplicates, &graph));
+    }
+
+    Ok(())
+}
+
+/// Load the closures of the two paths to compare with the selected backend.
+/// A dump can hold more than the closure asked for, so only what the path
+/// refers to is kept.
+async fn load_diff(
+    config: &cli::Config,
+    progress: &LoadProgress,
+) -> Result<(StorePathGraph, StorePathGraph)> {
+    let store_backend = backend::from_config(config).await?;
+    let mut closures = Vec::new();
+    for installable in &config.paths {
+        closures.push(
+            backend::load_graph(
+                store_backend.as_ref(),
+                std::slice::from_ref(installable),
+                progress,
+            )
+            .await?,
+        );
+    }
+    progress.set_parsed(closures.iter().map(StorePathGraph::len).sum());
 
-    run_tui(graph, stats).await
+    let new = closures.pop().expect("diff has two paths");
+    let old = closures.pop().expect("diff has two paths");
+    Ok((old, new))
+}
+
+async fn run_diff(config: cli::Config) -> Result<()> {
+    if config.tui {
+        let settings = load_settings(&config)?;
+        let progress = LoadProgress::default();
+        let loader = {
+            let progress = progress.clone();
+            tokio::spawn(async move {
+                let (old, new) = load_diff(&config, &progress).await?;
+
+                progress.set_phase(backend::LoadPhase::ComputingStats);
+                Ok(ui::App::from_diff(&old, &new))
+            })
+        };
+        return run_tui(loader, &progress, &settings).await;
+    }
+
+    let (old, new) = load_diff(&config, &LoadProgress::default()).await?;
+    let diff = ClosureDiff::compute(&old, &new);
+
+    if config.json {
+        println!("{}", serde_json::to_string_pretty(&diff)?);
+    } else {
+        print!("{}", diff.render_text());
+    }
+
+    Ok(())
 }
 
 async fn run_tui(
-    graph: store_path::StorePathGraph,
-    stats: std::collections::HashMap<String, path_stats::PathStats>,
+    loader: JoinHandle<Result<ui::App>>,
+    progress: &LoadProgress,
+    settings: &Settings,
 ) -> Result<()> {
     enable_raw_mode()?;
     let mut stdout = io::stdout();
@@ -73,7 +281,18 @@ async fn run_tui(
     let backend = CrosstermBackend::new(stdout);
     let mut terminal = Terminal::new(backend)?;
 
-    let result = run_app(&mut terminal, graph, stats).await;
+    // The loader runs in the background so the loading screen stays
+    // responsive
+    let loaded = run_loading(&mut terminal, loader, progress, &settings.theme).await;
+    let cancelled = matches!(loaded, Ok(None));
+    let result = match loaded {
+        Ok(Some(mut app)) => {
+            app.apply_settings(settings);
+            run_app(&mut terminal, app).await
+        }
+        Ok(None) => Ok(()),
+        Err(e) => Err(e),
+    };
 
     disable_raw_mode()?;
     execute!(
@@ -83,33 +302,129 @@ async fn run_tui(
     )?;
     terminal.show_cursor()?;
 
+    // A cancelled load may still be computing sizes, which can't be
+    // interrupted; don't wait for it on the way out.
+    if cancelled {
+        std::process::exit(0);
+    }
+
     result
 }
 
+/// Show the loading screen until the graph is ready. Returns `None` if the
+/// user cancelled.
+async fn run_loading(
+    terminal: &mut Terminal<CrosstermBackend<io::Stdout>>,
+    mut loader: JoinHandle<Result<ui::App>>,
+    progress: &LoadProgress,
+    theme: &ui::theme::Theme,
+) -> Result<Option<ui::App>> {
+    let started = Instant::now();
+
+    loop {
+        if loader.is_finished() {
+            return loader.await?.map(Some);
+        }
+
+        terminal.draw(|f| {
+            ui::widgets::render_loading(f, f.area(), theme, progress, started.elapsed());
+        })?;
+
+        if event::poll(Duration::from_millis(100))?
+            && let Event::Key(key) = event::read()?
+            && key.kind == KeyEventKind::Press
+        {
+            let cancel = matches!(key.code, KeyCode::Char('q') | KeyCode::Esc)
+                || (key.code == KeyCode::Char('c')
+                    && key.modifiers.contains(KeyModifiers::CONTROL));
+            if cancel {
+                // Dropping the task kills any nix process it is waiting on
+                loader.abort();
+                let _ = tokio::time::timeout(Duration::from_secs(1), &mut loader).await;
+                return Ok(None);
+            }
+        }
+    }
+}
+
+fn terminal_area(terminal: &Terminal<CrosstermBackend<io::Stdout>>) -> Result<Rect> {
+    let size = terminal.size()?;
+    Ok(Rect::new(0, 0, size.width, size.height))
+}
+
+/// Copy what was yanked by the last keys, now that the terminal is free to
+/// write the escape sequence to
+fn copy_yanked(terminal: &mut Terminal<CrosstermBackend<io::Stdout>>, app: &mut ui::App) {
+    if let Some(text) = app.yanked.take() {
+        app.message = Some(match app.clipboard.copy(&text, terminal.backend_mut()) {
+            Ok(()) => format!("Copied {text}"),
+            Err(e) => format!("Couldn't copy: {e:#}"),
+        });
+    }
+}
+
+/// Run the program the last keys asked for on the selected path. The TUI
+/// is suspended while it has the terminal and redrawn from scratch after.
+fn run_launch(
+    terminal: &mut Terminal<CrosstermBackend<io::Stdout>>,
+    app: &mut ui::App,
+) -> Result<()> {
+    let Some((program, path)) = app.launch.take() else {
+        return Ok(());
+    };
+
+    disable_raw_mode()?;
+    execute!(
+        terminal.backend_mut(),
+        LeaveAlternateScreen,
+        DisableMouseCapture
+    )?;
+    terminal.show_cursor()?;
+
+    let result = app.launcher.run(program, Path::new(&path));
+
+    enable_raw_mode()?;
+    execute!(
+        terminal.backend_mut(),
+        EnterAlternateScreen,
+        EnableMouseCapture
+    )?;
+    terminal.clear()?;
+
+    if let Err(e) = result {
+        app.message = Some(format!("{e:#}"));
+    }
+    Ok(())
+}
+
 async fn run_app(
     terminal: &mut Terminal<CrosstermBackend<io::Stdout>>,
-    graph: store_path::StorePathGraph,
-    stats: std::collections::HashMap<String, path_stats::PathStats>,
+    mut app: ui::App,
 ) -> Result<()> {
-    let mut app = ui::App::new(graph, stats);
     let mut needs_render = true;
 
     loop {
+        copy_yanked(terminal, &mut app);
+        run_launch(terminal, &mut app)?;
+
         // Only render when needed
         if needs_render {
             terminal.draw(|f| {
-                let chunks =
-                    Layout::vertical([Constraint::Min(1), Constraint::Length(4)]).split(f.area());
+                let chunks = ui::widgets::main_layout(f.area());
 
-                ui::pane::render_panes(f, &app, chunks[0]);
+                match app.view {
+                    ui::app::View::Panes => ui::pane::render_panes(f, &app, chunks[0]),
+                    ui::app::View::Treemap => ui::treemap::render_treemap(f, &app, chunks[0]),
+                    ui::app::View::Tree => ui::tree::render_tree(f, &app, chunks[0]),
+                }
                 ui::widgets::render_status_bar(f, &app, chunks[1]);
 
                 if app.show_help {
-                    ui::widgets::render_help(f, f.area());
+                    ui::widgets::render_help(f, &app, f.area());
                 }
 
                 if app.searching {
-                    ui::widgets::render_search(f, f.area(), &app.search_query);
+                    ui::widgets::render_search(f, &app, f.area());
                 }
 
                 // Render modal on top
@@ -130,7 +445,11 @@ async fn run_app(
 
                     // Process at most 10 additional events per frame to reduce jumpiness
                     let mut events_processed = 0;
-                    while events_processed < 10 && event::poll(Duration::from_millis(0))? {
+                    // Keys typed after one that runs a program are for it
+                    while events_processed < 10
+                        && app.launch.is_none()
+                        && event::poll(Duration::from_millis(0))?
+                    {
                         match event::read()? {
                             Event::Key(k) if k.kind == KeyEventKind::Press => {
                                 // Process the additional key event
@@ -139,11 +458,23 @@ async fn run_app(
                                 }
                                 events_processed += 1;
                             }
+                            Event::Mouse(mouse) => {
+                                if app.handle_mouse(mouse, terminal_area(terminal)?)? {
+                                    return Ok(());
+                                }
+                                events_processed += 1;
+                            }
                             Event::Resize(_, _) => needs_render = true,
                             _ => {}
                         }
                     }
                 }
+                Event::Mouse(mouse) => {
+                    if app.handle_mouse(mouse, terminal_area(terminal)?)? {
+                        break;
+                    }
+                    needs_render = true;
+                }
                 Event::Resize(_, _) => {
                     needs_render = true;
                 }
@@ -1,8 +1,10 @@
 use anyhow::{Context, Result};
 use serde::Deserialize;
+use std::collections::HashMap;
 use std::process::Stdio;
 use tokio::process::Command;
 
+use crate::backend::LoadProgress;
 use crate::store_path::{StorePath, StorePathGraph};
 
 #[derive(Debug, Deserialize)]
@@ -14,7 +16,8 @@ struct NixPathInfo {
     #[serde(rename = "narSize")]
     nar_size: Option<u64>,
 
-    #[serde(rename = "path")]
+    // Only present in the list form; the map form keys entries by path
+    #[serde(rename = "path", default)]
     path: String,
 
     references: Option<Vec<String>>,
@@ -22,15 +25,25 @@ struct NixPathInfo {
 
     #[serde(rename = "closureSize")]
     closure_size: Option<u64>,
+
+    #[serde(rename = "registrationTime")]
+    registration_time: Option<u64>,
+
+    deriver: Option<String>,
+    ca: Option<String>,
+
+    // Older nix versions mark invalid paths with `"valid": false`
+    valid: Option<bool>,
 }
 
-async fn resolve_paths(
-    paths: &[String],
+fn nix_command(
     store: Option<&str>,
     nix_options: &[(String, String)],
     file: Option<&str>,
-) -> Result<Vec<String>> {
+) -> Command {
     let mut cmd = Command::new("nix");
+    // Cancelling a load drops the future, which must take nix down with it
+    cmd.kill_on_drop(true);
     cmd.arg("--extra-experimental-features")
         .arg("nix-command flakes");
 
@@ -46,9 +59,27 @@ async fn resolve_paths(
         cmd.arg("--file").arg(file_path);
     }
 
-    cmd.arg("path-info")
-        .arg("--json")
-        .args(paths)
+    cmd
+}
+
+/// Resolve installables to store paths. Also returns the subset of those
+/// paths that are not valid in the store yet.
+pub(crate) async fn resolve_paths(
+    paths: &[String],
+    derivation: bool,
+    store: Option<&str>,
+    nix_options: &[(String, String)],
+    file: Option<&str>,
+) -> Result<(Vec<String>, Vec<String>)> {
+    let mut cmd = nix_command(store, nix_options, file);
+    cmd.arg("path-info").arg("--json");
+
+    // Map installables to their store derivations instead of their outputs
+    if derivation {
+        cmd.arg("--derivation");
+    }
+
+    cmd.args(paths)
         .stdout(Stdio::piped())
         .stderr(Stdio::piped());
 
@@ -60,17 +91,15 @@ async fn resolve_paths(
     }
 
     let json_str = String::from_utf8(output.stdout).context("Invalid UTF-8 in nix output")?;
-    let path_info_map: std::collections::HashMap<String, NixPathInfo> =
-        serde_json::from_str(&json_str).or_else(|_| {
-            let list: Vec<NixPathInfo> = serde_json::from_str(&json_str)?;
-            let map: std::collections::HashMap<String, NixPathInfo> = list
-                .into_iter()
-                .map(|info| (info.path.clone(), info))
-                .collect();
-            Ok::<_, anyhow::Error>(map)
-        })?;
+    let entries = parse_path_info_entries(&json_str)?;
+
+    let missing = entries
+        .iter()
+        .filter(|(_, info)| info.is_none())
+        .map(|(path, _)| path.clone())
+        .collect();
 
-    Ok(path_info_map.keys().cloned().collect())
+    Ok((entries.into_keys().collect(), missing))
 }
 
 pub async fn query_path_info(
@@ -80,42 +109,218 @@ pub async fn query_path_info(
     nix_options: &[(String, String)],
     file: Option<&str>,
 ) -> Result<StorePathGraph> {
-    let resolved_paths = resolve_paths(paths, store, nix_options, file).await?;
-
-    for path in &resolved_paths {
-        if !std::path::Path::new(path).exists() {
-            let status = std::process::Command::new("nix-store")
-                .arg("--realise")
-                .arg(path)
-                .status()
-                .context("Failed to run nix-store --realise")?;
-
-            if !status.success() {
-                anyhow::bail!("Failed to realise store path: {}", path);
-            }
+    let (resolved_paths, missing) = resolve_paths(paths, false, store, nix_options, file).await?;
+    realise_missing(&missing, store, nix_options, &LoadProgress::default()).await?;
+
+    let paths = query_closure(&resolved_paths, recursive, store, nix_options, file).await?;
+    Ok(StorePathGraph::from_paths(paths, resolved_paths))
+}
+
+/// Build or substitute paths that are not valid in the store yet
+pub(crate) async fn realise_missing(
+    missing: &[String],
+    store: Option<&str>,
+    nix_options: &[(String, String)],
+    progress: &LoadProgress,
+) -> Result<()> {
+    for (i, path) in missing.iter().enumerate() {
+        progress.set_detail(format!(
+            "{}/{}: {}",
+            i + 1,
+            missing.len(),
+            StorePath::parse(path).map_or(path.clone(), |(_, name)| name)
+        ));
+
+        let mut cmd = Command::new("nix-store");
+        cmd.kill_on_drop(true);
+
+        for (name, value) in nix_options {
+            cmd.arg("--option").arg(name).arg(value);
+        }
+
+        if let Some(store_url) = store {
+            cmd.arg("--store").arg(store_url);
+        }
+
+        let output = cmd
+            .arg("--realise")
+            .arg(path)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .output()
+            .await
+            .context("Failed to run nix-store --realise")?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            anyhow::bail!("Failed to realise store path {}: {}", path, stderr);
         }
     }
 
-    let mut cmd = Command::new("nix");
-    cmd.arg("--extra-experimental-features")
-        .arg("nix-command flakes");
+    Ok(())
+}
 
-    for (name, value) in nix_options {
-        cmd.arg("--option").arg(name).arg(value);
+/// Describe paths that are not in the store without building them.
+///
+/// Paths a substituter knows about get their metadata, and that of their
+/// closure, from it. Anything left becomes a placeholder that only knows
+/// the derivation producing it.
+pub(crate) async fn query_missing(
+    missing: &[String],
+    store: Option<&str>,
+    nix_options: &[(String, String)],
+    progress: &LoadProgress,
+) -> Result<Vec<StorePath>> {
+    let mut found = Vec::new();
+    let mut remaining: Vec<String> = missing.to_vec();
+
+    for substituter in substituters(store, nix_options).await {
+        if remaining.is_empty() {
+            break;
+        }
+        progress.set_detail(format!("asking {substituter}"));
+
+        // Substituters that can't be reached or don't have the paths are skipped
+        let Ok(paths) =
+            query_closure(&remaining, true, Some(&substituter), nix_options, None).await
+        else {
+            continue;
+        };
+
+        remaining.retain(|p| !paths.iter().any(|sp| &sp.path == p));
+        found.extend(paths.into_iter().map(|p| StorePath { missing: true, ..p }));
     }
 
-    if let Some(store_url) = store {
-        cmd.arg("--store").arg(store_url);
+    if !remaining.is_empty() {
+        progress.set_detail(format!("reading derivations of {} paths", remaining.len()));
+        let derivers = query_derivers(&remaining, store, nix_options)
+            .await
+            .unwrap_or_default();
+
+        for path in remaining {
+            let (hash, name) = StorePath::parse(&path)?;
+            found.push(StorePath {
+                deriver: derivers.get(&path).cloned(),
+                path,
+                hash,
+                name,
+                missing: true,
+                ..Default::default()
+            });
+        }
     }
 
-    if let Some(file_path) = file {
-        cmd.arg("--file").arg(file_path);
+    Ok(found)
+}
+
+async fn substituters(store: Option<&str>, nix_options: &[(String, String)]) -> Vec<String> {
+    let mut cmd = nix_command(store, nix_options, None);
+    cmd.arg("config")
+        .arg("show")
+        .arg("substituters")
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+
+    match cmd.output().await {
+        Ok(output) if output.status.success() => String::from_utf8_lossy(&output.stdout)
+            .split_whitespace()
+            .map(str::to_string)
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Map output paths to the derivations that produce them
+async fn query_derivers(
+    paths: &[String],
+    store: Option<&str>,
+    nix_options: &[(String, String)],
+) -> Result<HashMap<String, String>> {
+    #[derive(Deserialize)]
+    struct DerivationOutput {
+        path: Option<String>,
+    }
+
+    #[derive(Deserialize)]
+    struct DerivationInfo {
+        #[serde(default)]
+        outputs: HashMap<String, DerivationOutput>,
+    }
+
+    let mut cmd = nix_command(store, nix_options, None);
+    cmd.arg("derivation")
+        .arg("show")
+        .args(paths)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+
+    let output = cmd
+        .output()
+        .await
+        .context("Failed to run nix derivation show")?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        anyhow::bail!("nix derivation show failed: {}", stderr);
+    }
+
+    let derivations: HashMap<String, DerivationInfo> = serde_json::from_slice(&output.stdout)?;
+
+    let mut derivers = HashMap::new();
+    for (drv_path, info) in derivations {
+        // Newer nix versions print base names instead of full paths
+        let drv_path = if drv_path.starts_with('/') {
+            drv_path
+        } else {
+            format!("/nix/store/{drv_path}")
+        };
+
+        for output in info.outputs.into_values() {
+            if let Some(path) = output.path {
+                let path = if path.starts_with('/') {
+                    path
+                } else {
+                    format!("/nix/store/{path}")
+                };
+                derivers.insert(path, drv_path.clone());
+            }
+        }
     }
 
+    Ok(derivers)
+}
+
+/// Build the derivation graph of the given installables.
+///
+/// Every node is a `.drv` file (or a plain source it takes as input). The
+/// references of a store derivation are exactly its `inputDrvs` and
+/// `inputSrcs`, so querying the closure of the `.drv` files yields the build
+/// graph without realising anything.
+pub async fn query_derivation_info(
+    paths: &[String],
+    store: Option<&str>,
+    nix_options: &[(String, String)],
+    file: Option<&str>,
+) -> Result<StorePathGraph> {
+    let (resolved_paths, _) = resolve_paths(paths, true, store, nix_options, file).await?;
+
+    let paths = query_closure(&resolved_paths, true, store, nix_options, file).await?;
+    Ok(StorePathGraph::from_paths(paths, resolved_paths))
+}
+
+pub(crate) async fn query_closure(
+    resolved_paths: &[String],
+    recursive: bool,
+    store: Option<&str>,
+    nix_options: &[(String, String)],
+    file: Option<&str>,
+) -> Result<Vec<StorePath>> {
+    let mut cmd = nix_command(store, nix_options, file);
     cmd.arg("path-info")
         .arg("--json")
         .arg("--closure-size")
-        .args(&resolved_paths)
+        .args(resolved_paths)
         .stdout(Stdio::piped())
         .stderr(Stdio::piped());
 
@@ -134,39 +339,60 @@ pub async fn query_path_info(
     }
 
     let json_str = String::from_utf8(output.stdout).context("Invalid UTF-8 in nix output")?;
+    parse_store_paths(&json_str)
+}
+
+/// Parse `nix path-info --json` output into store path records
+pub(crate) fn parse_store_paths(json_str: &str) -> Result<Vec<StorePath>> {
+    parse_path_info(json_str)?
+        .into_iter()
+        .map(|(path, info)| {
+            let (hash, name) = StorePath::parse(&path)?;
+
+            Ok(StorePath {
+                path,
+                hash,
+                name,
+                nar_size: info.nar_size.unwrap_or(0),
+                closure_size: info.closure_size,
+                references: info.references.unwrap_or_default(),
+                signatures: info.signatures.unwrap_or_default(),
+                registration_time: info.registration_time,
+                deriver: info.deriver,
+                ca: info.ca,
+                file_size: None,
+                compression: None,
+                missing: false,
+            })
+        })
+        .collect()
+}
 
-    let path_info_map: std::collections::HashMap<String, NixPathInfo> =
-        serde_json::from_str(&json_str).or_else(|_| {
-            let list: Vec<NixPathInfo> = serde_json::from_str(&json_str)?;
-            let map: std::collections::HashMap<String, NixPathInfo> = list
+/// Parse `nix path-info --json` output in either its map or its list form
+fn parse_path_info(json_str: &str) -> Result<HashMap<String, NixPathInfo>> {
+    Ok(parse_path_info_entries(json_str)?
+        .into_iter()
+        .filter_map(|(path, info)| info.map(|info| (path, info)))
+        .collect())
+}
+
+/// Like `parse_path_info`, but keeps invalid paths as `None`
+fn parse_path_info_entries(json_str: &str) -> Result<HashMap<String, Option<NixPathInfo>>> {
+    // Newer nix versions report invalid paths as `null` in the map form
+    let path_info_map: HashMap<String, Option<NixPathInfo>> = serde_json::from_str(json_str)
+        .or_else(|_| {
+            let list: Vec<NixPathInfo> = serde_json::from_str(json_str)?;
+            let map: HashMap<String, Option<NixPathInfo>> = list
                 .into_iter()
-                .map(|info| (info.path.clone(), info))
+                .map(|info| (info.path.clone(), Some(info)))
                 .collect();
             Ok::<_, anyhow::Error>(map)
         })?;
 
-    let mut graph = StorePathGraph::new();
-
-    for (path, info) in path_info_map {
-        let (hash, name) = StorePath::parse(&path)?;
-
-        let store_path = StorePath {
-            path: path.clone(),
-            hash,
-            name,
-            nar_size: info.nar_size.unwrap_or(0),
-            closure_size: info.closure_size,
-            references: info.references.unwrap_or_default(),
-            signatures: info.signatures.unwrap_or_default(),
-        };
-
-        graph.add_path(store_path);
-    }
-
-    graph.roots = resolved_paths;
-    graph.disambiguate_names();
-
-    Ok(graph)
+    Ok(path_info_map
+        .into_iter()
+        .map(|(path, info)| (path, info.filter(|info| info.valid != Some(false))))
+        .collect())
 }
 
 pub async fn get_default_roots() -> Result<Vec<String>> {
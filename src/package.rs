@@ -0,0 +1,180 @@
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+
+use crate::store_path::StorePathGraph;
+
+/// Output names that nixpkgs appends to the name of a package's extra
+/// outputs, as in `openssl-3.0.13-dev`
+pub const OUTPUT_NAMES: &[&str] = &[
+    "bin", "dev", "devdoc", "debug", "doc", "info", "lib", "man", "modules", "out", "py", "python",
+    "static", "terminfo",
+];
+
+/// A store path name split into package name, version and output
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PackageName {
+    pub pname: String,
+    /// Empty for unversioned names such as `source`
+    pub version: String,
+    /// `None` for the default output
+    pub output: Option<String>,
+}
+
+impl PackageName {
+    /// Parse a name like `openssl-3.0.13-dev`. An output suffix is only
+    /// recognised after a version, so `foo-bin` stays a package name, and a
+    /// `.drv` extension is ignored.
+    pub fn parse(name: &str) -> Self {
+        let name = name.strip_suffix(".drv").unwrap_or(name);
+
+        let (name, output) = match name.rsplit_once('-') {
+            Some((rest, suffix))
+                if OUTPUT_NAMES.contains(&suffix) && !parse_drv_name(rest).1.is_empty() =>
+            {
+                (rest, Some(suffix.to_string()))
+            }
+            _ => (name, None),
+        };
+
+        let (pname, version) = parse_drv_name(name);
+        Self {
+            pname: pname.to_string(),
+            version: version.to_string(),
+            output,
+        }
+    }
+
+    pub fn output(&self) -> &str {
+        self.output.as_deref().unwrap_or("out")
+    }
+}
+
+/// All the paths of one package in a graph, across versions and outputs
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PackageGroup {
+    pub pname: String,
+    /// Oldest first
+    pub versions: Vec<String>,
+    pub outputs: Vec<String>,
+    /// Ordered by version, then output
+    pub paths: Vec<String>,
+    /// Combined NAR size of the paths
+    pub size: u64,
+}
+
+/// Group the paths in `graph` by package name, largest groups first
+pub fn group_packages(graph: &StorePathGraph) -> Vec<PackageGroup> {
+    let mut by_pname: BTreeMap<String, Vec<(PackageName, &str, u64)>> = BTreeMap::new();
+    for path in graph.paths() {
+        let name = path.package_name();
+        by_pname
+            .entry(name.pname.clone())
+            .or_default()
+            .push((name, &path.path, path.nar_size));
+    }
+
+    let mut groups: Vec<PackageGroup> = by_pname
+        .into_iter()
+        .map(|(pname, mut members)| {
+            members.sort_by(|(a, _, _), (b, _, _)| {
+                compare_versions(&a.version, &b.version).then_with(|| a.output().cmp(b.output()))
+            });
+
+            let mut versions: Vec<String> = Vec::new();
+            let mut outputs: Vec<String> = Vec::new();
+            for (name, _, _) in &members {
+                if !versions.contains(&name.version) {
+                    versions.push(name.version.clone());
+                }
+                if !outputs.iter().any(|o| o == name.output()) {
+                    outputs.push(name.output().to_string());
+                }
+            }
+
+            PackageGroup {
+                pname,
+                versions,
+                outputs,
+                size: members.iter().map(|(_, _, size)| size).sum(),
+                paths: members
+                    .iter()
+                    .map(|(_, path, _)| path.to_string())
+                    .collect(),
+            }
+        })
+        .collect();
+
+    groups.sort_by(|a, b| b.size.cmp(&a.size).then_with(|| a.pname.cmp(&b.pname)));
+    groups
+}
+
+/// Split a name into package name and version like Nix's `parseDrvName`:
+/// the version starts after the first dash that isn't followed by a letter.
+pub fn parse_drv_name(name: &str) -> (&str, &str) {
+    let bytes = name.as_bytes();
+    for (i, &b) in bytes.iter().enumerate() {
+        if b == b'-' && bytes.get(i + 1).is_some_and(|c| !c.is_ascii_alphabetic()) {
+            return (&name[..i], &name[i + 1..]);
+        }
+    }
+    (name, "")
+}
+
+/// Compare two versions like Nix's `compareVersions`
+pub fn compare_versions(a: &str, b: &str) -> Ordering {
+    let mut a = VersionComponents(a);
+    let mut b = VersionComponents(b);
+
+    loop {
+        match (a.next(), b.next()) {
+            (None, None) => return Ordering::Equal,
+            (c1, c2) => {
+                let (c1, c2) = (c1.unwrap_or(""), c2.unwrap_or(""));
+                if component_lt(c1, c2) {
+                    return Ordering::Less;
+                }
+                if component_lt(c2, c1) {
+                    return Ordering::Greater;
+                }
+            }
+        }
+    }
+}
+
+fn component_lt(c1: &str, c2: &str) -> bool {
+    let n1 = c1.parse::<u64>().ok();
+    let n2 = c2.parse::<u64>().ok();
+
+    match (n1, n2) {
+        (Some(n1), Some(n2)) => n1 < n2,
+        _ if c1.is_empty() && n2.is_some() => true,
+        _ if c1 == "pre" && c2 != "pre" => true,
+        _ if c2 == "pre" => false,
+        // Numbers are newer than anything else
+        (Some(_), None) => false,
+        (None, Some(_)) => true,
+        (None, None) => c1 < c2,
+    }
+}
+
+/// Runs of digits or of other characters, split on `.` and `-`
+struct VersionComponents<'a>(&'a str);
+
+impl<'a> Iterator for VersionComponents<'a> {
+    type Item = &'a str;
+
+    fn next(&mut self) -> Option<&'a str> {
+        let s = self.0.trim_start_matches(['.', '-']);
+        let first = s.chars().next()?;
+
+        let end = if first.is_ascii_digit() {
+            s.find(|c: char| !c.is_ascii_digit())
+        } else {
+            s.find(|c: char| c.is_ascii_digit() || c == '.' || c == '-')
+        }
+        .unwrap_or(s.len());
+
+        self.0 = &s[end..];
+        Some(&s[..end])
+    }
+}
@@ -1,80 +1,331 @@
-use crate::store_path::StorePathGraph;
-use std::collections::{HashMap, HashSet};
+use crate::store_path::{PathId, StorePathGraph};
+use std::collections::{HashMap, HashSet, VecDeque};
 
 #[derive(Debug, Clone)]
 pub struct PathStats {
     pub closure_size: u64,
-    pub added_size: Option<u64>, // None means not yet calculated
+    /// What a root adds to the closure of all the roots, as the first pane
+    /// shows it; other paths depend on the referrer and have none
+    pub added_size: Option<u64>,
+    /// Bytes that would be freed if nothing referred to this path any more:
+    /// its own size plus everything only reachable through it
+    pub retained_size: u64,
     pub immediate_parents: Vec<String>,
 }
 
 pub fn calculate_stats(graph: &StorePathGraph) -> HashMap<String, PathStats> {
-    let mut stats = HashMap::new();
+    let mut stats = HashMap::with_capacity(graph.len());
+    let retained_sizes = retained_sizes(graph, &immediate_dominators(graph));
+    let roots = graph.root_ids();
+    let root_added: HashMap<PathId, u64> = roots
+        .iter()
+        .copied()
+        .zip(added_sizes(graph, &roots))
+        .collect();
+    // When using --recursive, nix already gave us the full closure sizes
+    let closure_sizes = if graph.paths().iter().any(|p| p.closure_size.is_none()) {
+        closure_sizes(graph)
+    } else {
+        Vec::new()
+    };
 
-    // When using --recursive, nix already gave us the full closure
-    // So we can use the closure_size field directly if available
-    for path in &graph.paths {
-        let closure_size = if let Some(size) = path.closure_size {
-            size
-        } else {
-            // Fallback: calculate closure size manually if not provided
-            let mut closure_cache: HashMap<String, HashSet<String>> = HashMap::new();
-            let closure = calculate_closure(graph, &path.path, &mut closure_cache);
-            closure
-                .iter()
-                .filter_map(|p| graph.get_path(p))
-                .map(|p| p.nar_size)
-                .sum()
-        };
+    for (id, path) in graph.paths().iter().enumerate() {
+        let closure_size = path.closure_size.unwrap_or_else(|| closure_sizes[id]);
 
         let immediate_parents = graph
-            .get_referrers(&path.path)
-            .into_iter()
-            .map(|p| p.path.clone())
+            .referrer_ids(id)
+            .iter()
+            .map(|&p| graph.path(p).path.clone())
             .collect();
 
         stats.insert(
             path.path.clone(),
             PathStats {
                 closure_size,
-                added_size: None, // Will be calculated on-demand
+                added_size: root_added.get(&id).copied(),
+                retained_size: retained_sizes[id],
                 immediate_parents,
             },
         );
     }
 
-    // Skip added sizes calculation for now - it's too slow for large graphs
-    // This will be calculated on-demand when displaying in the UI
+    // Added sizes of other paths depend on the pane they're shown in, and
+    // are calculated on-demand when displaying them in the UI
 
     stats
 }
 
-fn calculate_closure(
-    graph: &StorePathGraph,
-    path: &str,
-    cache: &mut HashMap<String, HashSet<String>>,
-) -> HashSet<String> {
-    if let Some(cached) = cache.get(path) {
-        return cached.clone();
-    }
+/// The closure size of every path, indexed by `PathId`, in a single
+/// depth-first pass.
+///
+/// Each path's closure is built from those of its references as they finish,
+/// taking over the largest one nothing else still needs instead of copying
+/// it, and closures are dropped once every referrer has finished. A path
+/// referring back to one still being visited is part of a cycle, and its
+/// closure is walked instead.
+pub fn closure_sizes(graph: &StorePathGraph) -> Vec<u64> {
+    let n = graph.len();
+    let mut sizes = vec![0; n];
+    let mut closures: Vec<Option<HashSet<PathId>>> = vec![None; n];
+    // Referrers that haven't finished yet, and may still need the closure
+    let mut pending: Vec<usize> = (0..n).map(|id| graph.referrer_ids(id).len()).collect();
+    let mut visited = vec![false; n];
+    let mut walked = Vec::new();
+    let mut stack: Vec<(PathId, usize)> = Vec::new();
 
-    let mut closure = HashSet::new();
-    let mut to_visit = vec![path.to_string()];
+    for start in 0..n {
+        if visited[start] {
+            continue;
+        }
+        visited[start] = true;
+        stack.push((start, 0));
+        while let Some((node, next)) = stack.last_mut() {
+            let node = *node;
+            if let Some(&child) = graph.reference_ids(node).get(*next) {
+                *next += 1;
+                if !visited[child] {
+                    visited[child] = true;
+                    stack.push((child, 0));
+                }
+                continue;
+            }
+            stack.pop();
 
-    while let Some(current) = to_visit.pop() {
-        if closure.insert(current.clone()) {
-            if let Some(store_path) = graph.get_path(&current) {
-                for reference in &store_path.references {
-                    if !closure.contains(reference) {
-                        to_visit.push(reference.clone());
+            let references = graph.reference_ids(node);
+            let mut closure: HashSet<PathId>;
+            let mut size;
+            if references.iter().all(|&r| closures[r].is_some()) {
+                // The largest closure this is the last to need, or else
+                // the largest one
+                let base = references.iter().copied().max_by_key(|&r| {
+                    (pending[r] == 1, closures[r].as_ref().map_or(0, |c| c.len()))
+                });
+                (closure, size) = match base {
+                    Some(r) if pending[r] == 1 => (closures[r].take().unwrap(), sizes[r]),
+                    Some(r) => (closures[r].clone().unwrap(), sizes[r]),
+                    None => (HashSet::new(), 0),
+                };
+                // Already there if a reference was walked in a cycle with it
+                if closure.insert(node) {
+                    size += graph.path(node).nar_size;
+                }
+                for &r in references.iter().filter(|&&r| Some(r) != base) {
+                    for &p in closures[r].iter().flatten() {
+                        if closure.insert(p) {
+                            size += graph.path(p).nar_size;
+                        }
                     }
                 }
+            } else {
+                closure = graph
+                    .closure_ids(node, &mut walked, node as u32 + 1)
+                    .into_iter()
+                    .collect();
+                size = closure.iter().map(|&p| graph.path(p).nar_size).sum();
+            }
+
+            for &r in references {
+                pending[r] -= 1;
+                if pending[r] == 0 {
+                    closures[r] = None;
+                }
+            }
+            sizes[node] = size;
+            if pending[node] > 0 {
+                closures[node] = Some(closure);
+            }
+        }
+    }
+
+    sizes
+}
+
+/// The immediate dominator of every path, indexed by `PathId`.
+///
+/// The dominator tree is rooted at a virtual super-root referring to all of
+/// `graph.roots`, represented as `None`. Paths that aren't reachable from the
+/// roots are treated as roots themselves.
+///
+/// Uses the iterative algorithm from Cooper, Harvey and Kennedy, "A Simple,
+/// Fast Dominance Algorithm", which converges in a single pass on acyclic
+/// graphs like most store closures.
+pub fn immediate_dominators(graph: &StorePathGraph) -> Vec<Option<PathId>> {
+    let n = graph.len();
+    let super_root = n;
+
+    // The super-root refers to the roots and to anything nothing else refers
+    // to. Paths still unreachable after that can only be part of a cycle, and
+    // the first one found of each is added as well.
+    let mut is_entry: Vec<bool> = (0..n).map(|id| graph.referrer_ids(id).is_empty()).collect();
+    for id in graph.root_ids() {
+        is_entry[id] = true;
+    }
+
+    // Depth-first postorder, with the super-root last
+    let mut postorder = Vec::with_capacity(n + 1);
+    let mut order = vec![0; n + 1];
+    let mut visited = vec![false; n];
+    let mut stack: Vec<(PathId, usize)> = Vec::new();
+    let starts: Vec<PathId> = (0..n).filter(|&id| is_entry[id]).chain(0..n).collect();
+    for start in starts {
+        if visited[start] {
+            continue;
+        }
+        is_entry[start] = true;
+        visited[start] = true;
+        stack.push((start, 0));
+        while let Some((node, next)) = stack.last_mut() {
+            let node = *node;
+            if let Some(&child) = graph.reference_ids(node).get(*next) {
+                *next += 1;
+                if !visited[child] {
+                    visited[child] = true;
+                    stack.push((child, 0));
+                }
+            } else {
+                order[node] = postorder.len();
+                postorder.push(node);
+                stack.pop();
+            }
+        }
+    }
+    order[super_root] = postorder.len();
+
+    let undefined = usize::MAX;
+    let mut idom = vec![undefined; n + 1];
+    idom[super_root] = super_root;
+
+    let intersect = |idom: &[usize], mut a: usize, mut b: usize| {
+        while a != b {
+            while order[a] < order[b] {
+                a = idom[a];
+            }
+            while order[b] < order[a] {
+                b = idom[b];
+            }
+        }
+        a
+    };
+
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for &node in postorder.iter().rev() {
+            let predecessors = graph
+                .referrer_ids(node)
+                .iter()
+                .copied()
+                .chain(is_entry[node].then_some(super_root));
+
+            let mut new_idom = undefined;
+            for pred in predecessors {
+                if idom[pred] == undefined {
+                    continue;
+                }
+                new_idom = if new_idom == undefined {
+                    pred
+                } else {
+                    intersect(&idom, pred, new_idom)
+                };
+            }
+
+            if idom[node] != new_idom {
+                idom[node] = new_idom;
+                changed = true;
             }
         }
     }
 
-    cache.insert(path.to_string(), closure.clone());
-    closure
+    idom[..n]
+        .iter()
+        .map(|&d| (d != super_root).then_some(d))
+        .collect()
+}
+
+/// Sum the sizes of each path's subtree in the dominator tree
+pub fn retained_sizes(graph: &StorePathGraph, idom: &[Option<PathId>]) -> Vec<u64> {
+    let mut retained: Vec<u64> = graph.paths().iter().map(|p| p.nar_size).collect();
+
+    // Children have to be added to their dominator before it is added to
+    // its own, so go up the tree from the deepest paths
+    let mut depth = vec![None; graph.len()];
+    for id in 0..graph.len() {
+        let mut chain = Vec::new();
+        let mut current = Some(id);
+        let mut d = 0;
+        while let Some(node) = current {
+            if let Some(known) = depth[node] {
+                d = known + 1;
+                break;
+            }
+            chain.push(node);
+            current = idom[node];
+        }
+        for node in chain.into_iter().rev() {
+            depth[node] = Some(d);
+            d += 1;
+        }
+    }
+
+    let mut by_depth: Vec<PathId> = (0..graph.len()).collect();
+    by_depth.sort_by_key(|&id| std::cmp::Reverse(depth[id]));
+    for id in by_depth {
+        if let Some(dominator) = idom[id] {
+            retained[dominator] += retained[id];
+        }
+    }
+
+    retained
+}
+
+/// Shortest distance of every path from the roots, indexed by `PathId`.
+/// Roots are at depth 0; paths not reachable from them have none.
+pub fn root_depths(graph: &StorePathGraph) -> Vec<Option<usize>> {
+    let mut depths = vec![None; graph.len()];
+    let mut queue = std::collections::VecDeque::new();
+    for id in graph.root_ids() {
+        if depths[id].is_none() {
+            depths[id] = Some(0);
+            queue.push_back(id);
+        }
+    }
+
+    while let Some(id) = queue.pop_front() {
+        let depth = depths[id].map(|d| d + 1);
+        for &reference in graph.reference_ids(id) {
+            if depths[reference].is_none() {
+                depths[reference] = depth;
+                queue.push_back(reference);
+            }
+        }
+    }
+
+    depths
+}
+
+/// What each of `ids` adds to the closure of all of them: the size of the
+/// paths only its own closure contains
+pub fn added_sizes(graph: &StorePathGraph, ids: &[PathId]) -> Vec<u64> {
+    const SHARED: usize = usize::MAX;
+
+    // Which of `ids` reached each path first, or SHARED if several did
+    let mut owner: Vec<Option<usize>> = vec![None; graph.len()];
+    let mut visited = Vec::new();
+    for (i, &id) in ids.iter().enumerate() {
+        for p in graph.closure_ids(id, &mut visited, i as u32 + 1) {
+            owner[p] = Some(if owner[p].is_none() { i } else { SHARED });
+        }
+    }
+
+    let mut sizes = vec![0; ids.len()];
+    for (p, o) in owner.into_iter().enumerate() {
+        if let Some(i) = o
+            && i != SHARED
+        {
+            sizes[i] += graph.path(p).nar_size;
+        }
+    }
+    sizes
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -82,6 +333,7 @@ pub enum SortOrder {
     Alphabetical,
     ClosureSize,
     AddedSize,
+    RetainedSize,
 }
 
 impl SortOrder {
@@ -89,7 +341,8 @@ impl SortOrder {
         match self {
             SortOrder::Alphabetical => SortOrder::ClosureSize,
             SortOrder::ClosureSize => SortOrder::AddedSize,
-            SortOrder::AddedSize => SortOrder::Alphabetical,
+            SortOrder::AddedSize => SortOrder::RetainedSize,
+            SortOrder::RetainedSize => SortOrder::Alphabetical,
         }
     }
 
@@ -98,6 +351,7 @@ impl SortOrder {
             SortOrder::Alphabetical => "name",
             SortOrder::ClosureSize => "closure size",
             SortOrder::AddedSize => "added size",
+            SortOrder::RetainedSize => "retained size",
         }
     }
 }
@@ -119,6 +373,11 @@ pub fn sort_paths(paths: &mut [String], stats: &HashMap<String, PathStats>, orde
                 let size_b = stat_b.and_then(|s| s.added_size).unwrap_or(0);
                 size_b.cmp(&size_a)
             }
+            SortOrder::RetainedSize => {
+                let size_a = stat_a.map(|s| s.retained_size).unwrap_or(0);
+                let size_b = stat_b.map(|s| s.retained_size).unwrap_or(0);
+                size_b.cmp(&size_a)
+            }
         }
     });
 }
@@ -159,6 +418,42 @@ impl Treeish {
     }
 }
 
+/// Breadth first from `sources`, returning the parent each path was first
+/// reached from. Sources are their own parents; unreached paths have none.
+pub fn shortest_parents(graph: &StorePathGraph, sources: &[PathId]) -> Vec<Option<PathId>> {
+    let mut parents = vec![None; graph.len()];
+    let mut queue = VecDeque::new();
+    for &id in sources {
+        if parents[id].is_none() {
+            parents[id] = Some(id);
+            queue.push_back(id);
+        }
+    }
+    while let Some(id) = queue.pop_front() {
+        for &reference in graph.reference_ids(id) {
+            if parents[reference].is_none() {
+                parents[reference] = Some(id);
+                queue.push_back(reference);
+            }
+        }
+    }
+    parents
+}
+
+/// The chain of paths from the source `to` was reached from, down to `to`
+pub fn chain_to(parents: &[Option<PathId>], to: PathId) -> Vec<PathId> {
+    let mut chain = vec![to];
+    let mut id = to;
+    while let Some(parent) = parents[id]
+        && parent != id
+    {
+        chain.push(parent);
+        id = parent;
+    }
+    chain.reverse();
+    chain
+}
+
 /// Find all paths from roots to the target path using bottom-up approach
 pub fn why_depends(graph: &StorePathGraph, target: &str) -> Vec<Vec<String>> {
     // Early exit if target is not in the graph
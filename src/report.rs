@@ -0,0 +1,117 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt::Write;
+
+use crate::path_stats::{self, PathStats, SortOrder};
+use crate::store_path::{StorePath, StorePathGraph};
+
+/// Sizes and relations of every path in a graph, for scripts
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Report {
+    pub roots: Vec<String>,
+    /// Total NAR size of all paths
+    pub closure_size: u64,
+    /// Largest closures first
+    pub paths: Vec<PathReport>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PathReport {
+    pub path: String,
+    pub name: String,
+    pub nar_size: u64,
+    pub closure_size: u64,
+    /// What would be freed if nothing referred to this path any more
+    pub retained_size: u64,
+    pub references: Vec<String>,
+    pub referrers: Vec<String>,
+    pub signatures: Vec<String>,
+    /// Shortest distance from the roots, none for unreachable paths
+    pub depth: Option<usize>,
+}
+
+impl Report {
+    /// Report on every path of `graph` for which `include` holds
+    pub fn new(
+        graph: &StorePathGraph,
+        stats: &HashMap<String, PathStats>,
+        depths: &[Option<usize>],
+        include: impl Fn(&str) -> bool,
+    ) -> Self {
+        let mut paths: Vec<String> = graph
+            .paths()
+            .iter()
+            .map(|p| p.path.clone())
+            .filter(|p| include(p))
+            .collect();
+        paths.sort();
+        path_stats::sort_paths(&mut paths, stats, SortOrder::ClosureSize);
+
+        let path_names = |paths: Vec<&StorePath>| {
+            let mut names: Vec<String> = paths.into_iter().map(|p| p.path.clone()).collect();
+            names.sort();
+            names
+        };
+
+        let paths = paths
+            .iter()
+            .filter_map(|path| {
+                let id = graph.id(path)?;
+                let store_path = graph.path(id);
+                let stats = stats.get(path);
+                Some(PathReport {
+                    path: path.clone(),
+                    name: store_path.full_name().to_string(),
+                    nar_size: store_path.nar_size,
+                    closure_size: stats.map_or(0, |s| s.closure_size),
+                    retained_size: stats.map_or(0, |s| s.retained_size),
+                    references: path_names(graph.get_references(path)),
+                    referrers: path_names(graph.get_referrers(path)),
+                    signatures: store_path.signatures.clone(),
+                    depth: depths.get(id).copied().flatten(),
+                })
+            })
+            .collect();
+
+        Self {
+            roots: graph.roots.clone(),
+            closure_size: graph.paths().iter().map(|p| p.nar_size).sum(),
+            paths,
+        }
+    }
+
+    pub fn render_text(&self) -> String {
+        let name_width = self
+            .paths
+            .iter()
+            .map(|p| p.name.chars().count())
+            .max()
+            .unwrap_or(0)
+            .max("NAME".len());
+
+        let mut out = String::new();
+        let _ = writeln!(
+            out,
+            "{:name_width$}  {:>10}  {:>10}  {:>10}  {:>5}",
+            "NAME", "NAR", "CLOSURE", "RETAINED", "DEPTH"
+        );
+        for path in &self.paths {
+            let _ = writeln!(
+                out,
+                "{:name_width$}  {:>10}  {:>10}  {:>10}  {:>5}",
+                path.name,
+                bytesize::ByteSize(path.nar_size).to_string(),
+                bytesize::ByteSize(path.closure_size).to_string(),
+                bytesize::ByteSize(path.retained_size).to_string(),
+                path.depth.map_or("-".to_string(), |d| d.to_string()),
+            );
+        }
+        let _ = writeln!(
+            out,
+            "\n{} paths, {} in total",
+            self.paths.len(),
+            bytesize::ByteSize(self.closure_size)
+        );
+        out
+    }
+}
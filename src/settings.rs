@@ -0,0 +1,267 @@
+//! The user's configuration, read from `$XDG_CONFIG_HOME/nix-tree/config.toml`
+//! (`~/.config/nix-tree/config.toml` by default) or the file given with
+//! `--config`.
+//!
+//! ```toml
+//! # The order the panes start in: "name", "closure", "added" or "retained"
+//! sort = "retained"
+//! # Relative widths of the referrers, current and dependencies panes
+//! pane_widths = [25, 50, 25]
+//!
+//! [keys]
+//! # One key or a list of them for each action; the others keep their keys
+//! down = ["j", "Down", "Ctrl-n"]
+//! up = ["k", "Up", "Ctrl-p"]
+//! quit = ["q", "Ctrl-c"]
+//!
+//! [theme]
+//! # "default", or "monochrome" for no colours at all; NO_COLOR picks
+//! # monochrome unless this is set
+//! base = "default"
+//! selected = "magenta"
+//! matched = "#ff8700"
+//! treemap = ["blue", "cyan"]
+//!
+//! [clipboard]
+//! # Also copy with a command, given the text on its standard input
+//! command = "wl-copy"
+//! # Set to false to only use the command
+//! osc52 = true
+//!
+//! [open]
+//! # Instead of $SHELL and $PAGER
+//! shell = "bash"
+//! pager = "less -R"
+//! # Given the selected path
+//! browser = "yazi"
+//! ```
+
+use anyhow::{Context, Result, bail};
+use ratatui::style::Color;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use crate::clipboard::Clipboard;
+use crate::launch::Launcher;
+use crate::path_stats::SortOrder;
+use crate::ui::keymap::{Action, Key, Keymap};
+use crate::ui::theme::Theme;
+
+#[derive(Debug, Clone)]
+pub struct Settings {
+    pub keymap: Keymap,
+    pub theme: Theme,
+    pub sort_order: SortOrder,
+    pub pane_widths: [u16; 3],
+    pub clipboard: Clipboard,
+    pub launcher: Launcher,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            keymap: Keymap::default(),
+            theme: Theme::default(),
+            sort_order: SortOrder::ClosureSize,
+            pane_widths: [30, 40, 30],
+            clipboard: Clipboard::default(),
+            launcher: Launcher::default(),
+        }
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SettingsFile {
+    sort: Option<String>,
+    pane_widths: Option<[u16; 3]>,
+    #[serde(default)]
+    keys: BTreeMap<String, OneOrMore>,
+    #[serde(default)]
+    theme: BTreeMap<String, OneOrMore>,
+    #[serde(default)]
+    clipboard: ClipboardFile,
+    #[serde(default)]
+    open: OpenFile,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ClipboardFile {
+    osc52: Option<bool>,
+    command: Option<OneOrMore>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct OpenFile {
+    shell: Option<OneOrMore>,
+    pager: Option<OneOrMore>,
+    browser: Option<OneOrMore>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum OneOrMore {
+    One(String),
+    More(Vec<String>),
+}
+
+impl OneOrMore {
+    fn into_vec(self) -> Vec<String> {
+        match self {
+            OneOrMore::One(value) => vec![value],
+            OneOrMore::More(values) => values,
+        }
+    }
+
+    /// A command line split at whitespace, or the arguments as a list
+    fn into_command(self, name: &str) -> Result<Vec<String>> {
+        let command: Vec<String> = match self {
+            OneOrMore::One(line) => line.split_whitespace().map(str::to_string).collect(),
+            OneOrMore::More(args) => args,
+        };
+        if command.is_empty() {
+            bail!("{name} is empty");
+        }
+        Ok(command)
+    }
+}
+
+/// Whether the `NO_COLOR` convention asks for no colours
+pub fn no_color() -> bool {
+    std::env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty())
+}
+
+/// Where the config file is looked for when `--config` isn't given
+pub fn default_path() -> Option<PathBuf> {
+    let config_home = std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .filter(|p| p.is_absolute())
+        .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".config")))?;
+    Some(config_home.join("nix-tree").join("config.toml"))
+}
+
+fn parse_sort(value: &str) -> Result<SortOrder> {
+    Ok(match value {
+        "name" => SortOrder::Alphabetical,
+        "closure" => SortOrder::ClosureSize,
+        "added" => SortOrder::AddedSize,
+        "retained" => SortOrder::RetainedSize,
+        _ => bail!("Unknown sort order '{value}', expected name, closure, added or retained"),
+    })
+}
+
+fn parse_color(value: &str) -> Result<Color> {
+    Color::from_str(value).map_err(|_| anyhow::anyhow!("Unknown colour '{value}'"))
+}
+
+impl Settings {
+    /// Read `path`, or the default config file if there is one
+    pub fn load(path: Option<&Path>) -> Result<Self> {
+        let path = match path {
+            Some(path) => path.to_path_buf(),
+            None => match default_path() {
+                Some(path) if path.exists() => path,
+                _ => return Self::parse("", no_color()),
+            },
+        };
+        let text = std::fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read config {}", path.display()))?;
+        Self::parse(&text, no_color())
+            .with_context(|| format!("Failed to parse config {}", path.display()))
+    }
+
+    /// Parse a config file; `no_color` starts from the monochrome theme
+    /// unless the file picks one
+    pub fn parse(text: &str, no_color: bool) -> Result<Self> {
+        let file: SettingsFile = toml::from_str(text)?;
+        let mut settings = Settings::default();
+
+        if let Some(sort) = &file.sort {
+            settings.sort_order = parse_sort(sort)?;
+        }
+        if let Some(widths) = file.pane_widths {
+            if widths.iter().all(|&w| w == 0) {
+                bail!("pane_widths can't all be 0");
+            }
+            settings.pane_widths = widths;
+        }
+
+        if let Some(osc52) = file.clipboard.osc52 {
+            settings.clipboard.osc52 = osc52;
+        }
+        if let Some(command) = file.clipboard.command {
+            settings.clipboard.command = Some(command.into_command("clipboard.command")?);
+        }
+        let open = file.open;
+        settings.launcher = Launcher {
+            shell: open
+                .shell
+                .map(|c| c.into_command("open.shell"))
+                .transpose()?,
+            pager: open
+                .pager
+                .map(|c| c.into_command("open.pager"))
+                .transpose()?,
+            browser: open
+                .browser
+                .map(|c| c.into_command("open.browser"))
+                .transpose()?,
+        };
+
+        let mut bound: Vec<(Key, Action)> = Vec::new();
+        for (name, keys) in file.keys {
+            let Some(action) = Action::from_name(&name) else {
+                bail!("Unknown action '{name}' in [keys]");
+            };
+            let keys = keys
+                .into_vec()
+                .iter()
+                .map(|k| Key::parse(k))
+                .collect::<Result<Vec<_>>>()
+                .with_context(|| format!("Invalid key for {name}"))?;
+            for &key in &keys {
+                if let Some((_, other)) = bound.iter().find(|(k, a)| *k == key && *a != action) {
+                    bail!("{key} is bound to both {} and {name}", other.name());
+                }
+                bound.push((key, action));
+            }
+            settings.keymap.bind(action, keys);
+        }
+
+        let mut theme = file.theme;
+        settings.theme = match theme.remove("base") {
+            None if no_color => Theme::monochrome(),
+            None => Theme::default(),
+            Some(OneOrMore::One(base)) if base == "default" => Theme::default(),
+            Some(OneOrMore::One(base)) if base == "monochrome" => Theme::monochrome(),
+            Some(_) => bail!("theme.base must be \"default\" or \"monochrome\""),
+        };
+        for (name, value) in theme {
+            if name == "treemap" {
+                let colors = value.into_vec();
+                if colors.is_empty() {
+                    bail!("theme.treemap needs at least one colour");
+                }
+                settings.theme.treemap = colors
+                    .iter()
+                    .map(|c| parse_color(c))
+                    .collect::<Result<_>>()?;
+                continue;
+            }
+            let OneOrMore::One(value) = value else {
+                bail!("theme.{name} must be a single colour");
+            };
+            let color = parse_color(&value)?;
+            match settings.theme.color_mut(&name) {
+                Some(slot) => *slot = color,
+                None => bail!("Unknown colour '{name}' in [theme]"),
+            }
+        }
+
+        Ok(settings)
+    }
+}
@@ -1,8 +1,11 @@
 use anyhow::{Result, bail};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::fmt;
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
+use crate::package::PackageName;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq, Hash)]
 pub struct StorePath {
     pub path: String,
     pub hash: String,
@@ -11,6 +14,16 @@ pub struct StorePath {
     pub closure_size: Option<u64>,
     pub references: Vec<String>,
     pub signatures: Vec<String>,
+    /// Seconds since the epoch at which the path became valid
+    pub registration_time: Option<u64>,
+    pub deriver: Option<String>,
+    /// Content address, for paths that are content-addressed
+    pub ca: Option<String>,
+    /// Size of the compressed NAR in a binary cache
+    pub file_size: Option<u64>,
+    pub compression: Option<String>,
+    /// Not present in the store, so only partially known
+    pub missing: bool,
 }
 
 impl StorePath {
@@ -42,6 +55,20 @@ impl StorePath {
         &self.name
     }
 
+    /// The name as it appears in the store path, before
+    /// `StorePathGraph::disambiguate_names` prefixed it with part of the hash
+    pub fn full_name(&self) -> &str {
+        self.path
+            .rsplit('/')
+            .next()
+            .and_then(|base| base.split_once('-'))
+            .map_or(self.name.as_str(), |(_, name)| name)
+    }
+
+    pub fn package_name(&self) -> PackageName {
+        PackageName::parse(self.full_name())
+    }
+
     pub fn is_signed(&self) -> bool {
         !self.signatures.is_empty()
     }
@@ -53,9 +80,24 @@ impl fmt::Display for StorePath {
     }
 }
 
+/// Index of a path in a [`StorePathGraph`]
+pub type PathId = usize;
+
+/// The dependency graph between store paths.
+///
+/// Paths are interned: each one gets a `PathId` when it is added, and the
+/// references between them are kept as adjacency lists in both directions,
+/// so looking up a path, its references or its referrers doesn't scan the
+/// graph. References to paths that haven't been added yet are linked up
+/// when they are.
 #[derive(Debug, Clone)]
 pub struct StorePathGraph {
-    pub paths: Vec<StorePath>,
+    paths: Vec<StorePath>,
+    ids: HashMap<String, PathId>,
+    references: Vec<Vec<PathId>>,
+    referrers: Vec<Vec<PathId>>,
+    /// Referrers waiting for a referenced path to be added
+    dangling: HashMap<String, Vec<PathId>>,
     pub roots: Vec<String>,
 }
 
@@ -69,41 +111,148 @@ impl StorePathGraph {
     pub fn new() -> Self {
         Self {
             paths: Vec::new(),
+            ids: HashMap::new(),
+            references: Vec::new(),
+            referrers: Vec::new(),
+            dangling: HashMap::new(),
             roots: Vec::new(),
         }
     }
 
-    pub fn add_path(&mut self, path: StorePath) {
+    pub fn from_paths(paths: Vec<StorePath>, roots: Vec<String>) -> Self {
+        let mut graph = Self::new();
+        graph.paths.reserve(paths.len());
+        graph.ids.reserve(paths.len());
+        for path in paths {
+            graph.add_path(path);
+        }
+        graph.roots = roots;
+        graph.disambiguate_names();
+        graph
+    }
+
+    /// Add a path to the graph. Adding a path that is already present keeps
+    /// the existing entry.
+    pub fn add_path(&mut self, path: StorePath) -> PathId {
+        if let Some(&id) = self.ids.get(&path.path) {
+            return id;
+        }
+
+        let id = self.paths.len();
+        self.ids.insert(path.path.clone(), id);
+        self.references.push(Vec::new());
+        self.referrers.push(Vec::new());
+
+        for reference in &path.references {
+            if *reference == path.path {
+                continue;
+            }
+            match self.ids.get(reference) {
+                Some(&ref_id) => self.link(id, ref_id),
+                None => self.dangling.entry(reference.clone()).or_default().push(id),
+            }
+        }
+        for referrer in self.dangling.remove(&path.path).unwrap_or_default() {
+            self.link(referrer, id);
+        }
+
         self.paths.push(path);
+        id
+    }
+
+    fn link(&mut self, referrer: PathId, reference: PathId) {
+        // Guards against duplicate entries in a path's reference list
+        if !self.references[referrer].contains(&reference) {
+            self.references[referrer].push(reference);
+            self.referrers[reference].push(referrer);
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.paths.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.paths.is_empty()
+    }
+
+    /// All paths, indexed by `PathId`
+    pub fn paths(&self) -> &[StorePath] {
+        &self.paths
+    }
+
+    pub fn id(&self, path: &str) -> Option<PathId> {
+        self.ids.get(path).copied()
+    }
+
+    pub fn path(&self, id: PathId) -> &StorePath {
+        &self.paths[id]
     }
 
     pub fn get_path(&self, path: &str) -> Option<&StorePath> {
-        self.paths.iter().find(|p| p.path == path)
+        self.id(path).map(|id| &self.paths[id])
+    }
+
+    /// Paths referenced by `id` that are in the graph, without self-references
+    pub fn reference_ids(&self, id: PathId) -> &[PathId] {
+        &self.references[id]
+    }
+
+    /// Paths in the graph referring to `id`, without self-references
+    pub fn referrer_ids(&self, id: PathId) -> &[PathId] {
+        &self.referrers[id]
+    }
+
+    /// Roots that are in the graph
+    pub fn root_ids(&self) -> Vec<PathId> {
+        self.roots.iter().filter_map(|r| self.id(r)).collect()
     }
 
     pub fn get_references(&self, path: &str) -> Vec<&StorePath> {
-        if let Some(store_path) = self.get_path(path) {
-            store_path
-                .references
-                .iter()
-                .filter(|ref_path| ref_path != &path) // Filter out self-references
-                .filter_map(|ref_path| self.get_path(ref_path))
-                .collect()
-        } else {
-            Vec::new()
-        }
+        self.id(path)
+            .map(|id| {
+                self.references[id]
+                    .iter()
+                    .map(|&r| &self.paths[r])
+                    .collect()
+            })
+            .unwrap_or_default()
     }
 
     pub fn get_referrers(&self, path: &str) -> Vec<&StorePath> {
-        self.paths
-            .iter()
-            .filter(|p| p.path != path && p.references.contains(&path.to_string()))
-            .collect()
+        self.id(path)
+            .map(|id| self.referrers[id].iter().map(|&r| &self.paths[r]).collect())
+            .unwrap_or_default()
+    }
+
+    /// Every path reachable from `id`, including itself.
+    ///
+    /// `visited` is scratch space that can be reused between calls to avoid
+    /// reallocating it for each path: it's indexed by `PathId`, and a path
+    /// counts as visited when its entry equals `generation`, which must be
+    /// different for each call.
+    pub fn closure_ids(&self, id: PathId, visited: &mut Vec<u32>, generation: u32) -> Vec<PathId> {
+        visited.resize(self.paths.len(), 0);
+
+        let mut closure = Vec::new();
+        let mut to_visit = vec![id];
+        visited[id] = generation;
+
+        while let Some(current) = to_visit.pop() {
+            closure.push(current);
+            for &reference in &self.references[current] {
+                if visited[reference] != generation {
+                    visited[reference] = generation;
+                    to_visit.push(reference);
+                }
+            }
+        }
+
+        closure
     }
 
     pub fn disambiguate_names(&mut self) {
-        let mut name_counts: std::collections::HashMap<String, usize> =
-            std::collections::HashMap::new();
+        let mut name_counts: HashMap<String, usize> = HashMap::new();
 
         for path in &self.paths {
             *name_counts.entry(path.name.clone()).or_insert(0) += 1;
@@ -1,10 +1,26 @@
 use anyhow::Result;
-use crossterm::event::{KeyCode, KeyEvent};
+use crossterm::event::{KeyCode, KeyEvent, MouseButton, MouseEvent, MouseEventKind};
+use ratatui::layout::{Margin, Position, Rect};
 use ratatui::widgets::ListState;
-use std::collections::HashMap;
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::time::{Duration, Instant};
 
+use crate::backend::{LoadPhase, LoadProgress, StoreBackend};
+use crate::clipboard::Clipboard;
+use crate::export::DotOptions;
+use crate::filter::{Filter, FilterContext};
+use crate::launch::{Launcher, Program};
+use crate::package::PackageGroup;
 use crate::path_stats::{PathStats, SortOrder};
-use crate::store_path::StorePathGraph;
+use crate::settings::Settings;
+use crate::store_path::{PathId, StorePathGraph};
+use crate::ui::keymap::{Action, Keymap};
+use crate::ui::theme::Theme;
+use crate::ui::widgets::list_index;
+
+/// Two clicks on the same cell within this long make a double-click
+const DOUBLE_CLICK: Duration = Duration::from_millis(500);
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Pane {
@@ -15,6 +31,44 @@ pub enum Pane {
 
 impl Pane {}
 
+/// What the main area shows
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum View {
+    Panes,
+    /// The selected path's references as nested rectangles
+    Treemap,
+    /// The whole graph as an indented tree, like `nix-store -q --tree`
+    Tree,
+}
+
+/// What treemap tiles are sized by
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TreemapSize {
+    Retained,
+    /// What each reference adds to the selected path beyond the others
+    Added,
+}
+
+impl TreemapSize {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TreemapSize::Retained => "retained",
+            TreemapSize::Added => "added",
+        }
+    }
+}
+
+/// Marks paths that should stand out in the panes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Highlight {
+    /// Only in the new closure of a diff
+    Added,
+    /// Only in the old closure of a diff
+    Removed,
+    /// Breaks a lint rule
+    Forbidden,
+}
+
 pub enum Modal {
     WhyDepends {
         paths: Vec<Vec<String>>,
@@ -25,6 +79,73 @@ pub enum Modal {
         horizontal_scroll_state: ratatui::widgets::ScrollbarState,
         horizontal_scroll: usize,
     },
+    /// Every package in the graph, with its versions and outputs merged
+    Packages {
+        groups: Vec<PackageGroup>,
+        /// Indices of the groups showing their paths
+        expanded: HashSet<usize>,
+        selected: usize,
+    },
+}
+
+/// A line in the packages modal
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PackageRow {
+    Group(usize),
+    /// A path of a group, by group index and index in the group
+    Path(usize, usize),
+}
+
+/// What the panes show, to go back to
+#[derive(Debug, Clone)]
+pub struct PaneSnapshot {
+    navigation_history: Vec<(Vec<String>, Option<usize>)>,
+    current_items: Vec<String>,
+    previous_state: ListState,
+    current_state: ListState,
+    next_state: ListState,
+    active_pane: Pane,
+}
+
+/// A line in the tree view
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TreeRow {
+    /// The paths from a root down to this one
+    pub chain: Vec<String>,
+    pub has_children: bool,
+    pub expanded: bool,
+    /// The path is already shown further up, so its references aren't
+    /// repeated here
+    pub repeated: bool,
+    /// Whether each path in the chain below the root is the last of its
+    /// siblings, for drawing the guides
+    pub last: Vec<bool>,
+}
+
+impl TreeRow {
+    pub fn path(&self) -> &str {
+        self.chain.last().expect("chains are never empty")
+    }
+}
+
+pub fn package_rows(groups: &[PackageGroup], expanded: &HashSet<usize>) -> Vec<PackageRow> {
+    let mut rows = Vec::new();
+    for (i, group) in groups.iter().enumerate() {
+        rows.push(PackageRow::Group(i));
+        if expanded.contains(&i) {
+            rows.extend((0..group.paths.len()).map(|j| PackageRow::Path(i, j)));
+        }
+    }
+    rows
+}
+
+/// The program run by the `shell`, `pager` and `browse` actions
+fn launched_program(action: Action) -> Program {
+    match action {
+        Action::Shell => Program::Shell,
+        Action::Pager => Program::Pager,
+        _ => Program::Browser,
+    }
 }
 
 pub struct App {
@@ -35,6 +156,12 @@ pub struct App {
     pub show_help: bool,
     pub searching: bool,
     pub search_query: String,
+    /// Why the last search couldn't be applied
+    pub search_error: Option<String>,
+    /// The last search, which also filters the side panes
+    pub filter: Option<Filter>,
+    /// Distance of each path from the roots, for filtering
+    pub depths: Vec<Option<usize>>,
 
     pub previous_state: ListState,
     pub current_state: ListState,
@@ -50,6 +177,49 @@ pub struct App {
     pub navigation_history: Vec<(Vec<String>, Option<usize>)>,
 
     pub modal: Option<Modal>,
+
+    pub highlights: HashMap<String, Highlight>,
+
+    /// Used when exporting the selected closure with `x`
+    pub dot_options: DotOptions,
+    /// Shown in the status bar until the next key press
+    pub message: Option<String>,
+
+    pub view: View,
+    pub treemap_size: TreemapSize,
+    /// Index into `treemap_tiles` of the current path
+    pub treemap_selected: usize,
+    /// `treemap_tiles` of each path drawn so far, for the current size mode
+    /// and filter, since every frame and many keys ask for them again
+    treemap_cache: RefCell<HashMap<String, Vec<(String, u64)>>>,
+    /// The panes as they were when the search box was opened, restored if
+    /// it is cancelled
+    pub search_snapshot: Option<PaneSnapshot>,
+    /// How many paths in the current pane (or in the tree view, the graph)
+    /// the fuzzy query being typed matches
+    pub search_matches: Option<usize>,
+    /// The last fuzzy search, highlighted in the panes and jumped between
+    /// with `n` and `N`
+    pub last_search: Option<String>,
+    /// Chains of the expanded rows in the tree view
+    pub tree_expanded: HashSet<Vec<String>>,
+    /// Chain of the selected row in the tree view
+    pub tree_selected: Vec<String>,
+
+    pub keymap: Keymap,
+    pub theme: Theme,
+    /// Relative widths of the referrers, current and dependencies panes
+    pub pane_widths: [u16; 3],
+    pub clipboard: Clipboard,
+    /// Text to copy, left for the main loop since it owns the terminal
+    pub yanked: Option<String>,
+    pub launcher: Launcher,
+    /// A program to run on a path, left for the main loop to suspend the
+    /// TUI for
+    pub launch: Option<(Program, String)>,
+
+    /// When and where the last click was, to spot double-clicks
+    last_click: Option<(Instant, Position)>,
 }
 
 impl App {
@@ -80,6 +250,9 @@ impl App {
             show_help: false,
             searching: false,
             search_query: String::new(),
+            search_error: None,
+            filter: None,
+            depths: Vec::new(),
             previous_state: ListState::default(),
             current_state: ListState::default(),
             next_state: ListState::default(),
@@ -89,8 +262,30 @@ impl App {
             current_path: None,
             navigation_history: Vec::new(),
             modal: None,
+            highlights: HashMap::new(),
+            dot_options: DotOptions::default(),
+            message: None,
+            view: View::Panes,
+            treemap_size: TreemapSize::Retained,
+            treemap_selected: 0,
+            treemap_cache: RefCell::default(),
+            search_snapshot: None,
+            search_matches: None,
+            last_search: None,
+            tree_expanded: HashSet::new(),
+            tree_selected: Vec::new(),
+            keymap: Keymap::default(),
+            theme: Theme::default(),
+            pane_widths: [30, 40, 30],
+            clipboard: Clipboard::default(),
+            yanked: None,
+            launcher: Launcher::default(),
+            launch: None,
+            last_click: None,
         };
 
+        app.depths = crate::path_stats::root_depths(&app.graph);
+
         // Start with all roots in the current pane
         app.current_items = app.graph.roots.clone();
         crate::path_stats::sort_paths(&mut app.current_items, &app.stats, app.sort_order);
@@ -103,7 +298,352 @@ impl App {
         app
     }
 
+    /// Load the graph for `installables` from `backend` and open it
+    pub async fn from_backend(
+        backend: &dyn StoreBackend,
+        installables: &[String],
+        progress: &LoadProgress,
+    ) -> Result<Self> {
+        let graph = crate::backend::load_graph(backend, installables, progress).await?;
+
+        progress.set_phase(LoadPhase::ComputingStats);
+        let stats = crate::path_stats::calculate_stats(&graph);
+
+        Ok(Self::new(graph, stats))
+    }
+
+    /// Browse two closures together, with the paths only in one of them
+    /// highlighted
+    pub fn from_diff(old: &StorePathGraph, new: &StorePathGraph) -> Self {
+        let graph = crate::diff::merge_graphs(old, new);
+        let stats = crate::path_stats::calculate_stats(&graph);
+
+        let (added, removed) = crate::diff::path_changes(old, new);
+        let mut app = Self::new(graph, stats);
+        app.highlights = added
+            .into_iter()
+            .map(|p| (p, Highlight::Added))
+            .chain(removed.into_iter().map(|p| (p, Highlight::Removed)))
+            .collect();
+        app
+    }
+
+    /// Mark the paths breaking lint rules
+    pub fn highlight_violations(&mut self, violations: &[crate::lint::Violation]) {
+        for violation in violations {
+            self.highlights
+                .insert(violation.path.clone(), Highlight::Forbidden);
+        }
+    }
+
+    /// Use the keys, colours and defaults of the config file
+    pub fn apply_settings(&mut self, settings: &Settings) {
+        self.keymap = settings.keymap.clone();
+        self.theme = settings.theme.clone();
+        self.pane_widths = settings.pane_widths;
+        self.clipboard = settings.clipboard.clone();
+        self.launcher = settings.launcher.clone();
+        if self.sort_order != settings.sort_order {
+            self.sort_order = settings.sort_order;
+            let current = self.current_path.clone();
+            crate::path_stats::sort_paths(&mut self.current_items, &self.stats, self.sort_order);
+            let selected = current.and_then(|c| self.current_items.iter().position(|p| *p == c));
+            if let Some(index) = selected {
+                self.current_state.select(Some(index));
+            }
+            self.update_panes();
+        }
+    }
+
     pub fn handle_key(&mut self, key: KeyEvent) -> Result<bool> {
+        // The search box takes the keys as text
+        if self.searching && self.modal.is_none() {
+            self.message = None;
+            self.search_key(key);
+            self.tree_follow_current_path();
+            return Ok(false);
+        }
+        match self.keymap.action(&key) {
+            Some(action) => self.handle_action(action),
+            None => {
+                self.message = None;
+                Ok(false)
+            }
+        }
+    }
+
+    /// Do what the key bound to `action` does. Returns whether to quit.
+    pub fn handle_action(&mut self, action: Action) -> Result<bool> {
+        self.message = None;
+        let quit = self.dispatch_action(action)?;
+        self.tree_follow_current_path();
+        Ok(quit)
+    }
+
+    /// Searches and jumps from the modals move the current path, and the
+    /// tree follows it
+    fn tree_follow_current_path(&mut self) {
+        if self.view == View::Tree && self.tree_selected.last() != self.current_path.as_ref() {
+            self.tree_reveal_current();
+        }
+    }
+
+    /// Handle a mouse event in a terminal of `area`, laid out the way
+    /// `run_app` draws it. The wheel moves like the arrow keys, a click
+    /// selects and a double-click acts like Enter or moving right.
+    pub fn handle_mouse(&mut self, mouse: MouseEvent, area: Rect) -> Result<bool> {
+        match mouse.kind {
+            MouseEventKind::ScrollDown if !self.show_help => {
+                return self.handle_action(Action::Down);
+            }
+            MouseEventKind::ScrollUp if !self.show_help => {
+                return self.handle_action(Action::Up);
+            }
+            // Only the why-depends modal scrolls sideways
+            MouseEventKind::ScrollLeft if matches!(self.modal, Some(Modal::WhyDepends { .. })) => {
+                return self.handle_action(Action::Left);
+            }
+            MouseEventKind::ScrollRight if matches!(self.modal, Some(Modal::WhyDepends { .. })) => {
+                return self.handle_action(Action::Right);
+            }
+            MouseEventKind::Down(MouseButton::Left) => {}
+            _ => return Ok(false),
+        }
+
+        self.message = None;
+        let position = Position::new(mouse.column, mouse.row);
+        let now = Instant::now();
+        let double = self
+            .last_click
+            .is_some_and(|(at, last)| last == position && now.duration_since(at) < DOUBLE_CLICK);
+        self.last_click = if double { None } else { Some((now, position)) };
+
+        if self.show_help {
+            self.show_help = false;
+        } else if self.searching {
+            // The search box keeps the keyboard until it is closed
+        } else if self.modal.is_some() {
+            self.click_modal(position, area, double)?;
+        } else {
+            let [main, _] = crate::ui::widgets::main_layout(area);
+            match self.view {
+                View::Panes => self.click_panes(position, main, double),
+                View::Tree => self.click_tree(position, main, double),
+                View::Treemap => self.click_treemap(position, main, double),
+            }
+        }
+
+        self.tree_follow_current_path();
+        Ok(false)
+    }
+
+    fn click_modal(&mut self, position: Position, area: Rect, double: bool) -> Result<()> {
+        let (modal_area, visible_height, len) = match &self.modal {
+            Some(Modal::WhyDepends {
+                formatted_lines, ..
+            }) => {
+                let modal_area = crate::ui::widgets::why_depends_area(area);
+                // The last line is left for the horizontal scrollbar
+                let height = modal_area.height.saturating_sub(3);
+                (modal_area, height, formatted_lines.len())
+            }
+            Some(Modal::Packages {
+                groups, expanded, ..
+            }) => {
+                let modal_area = crate::ui::widgets::packages_area(area);
+                let height = modal_area.height.saturating_sub(2);
+                (modal_area, height, package_rows(groups, expanded).len())
+            }
+            None => return Ok(()),
+        };
+
+        // Clicking outside closes it
+        if !modal_area.contains(position) {
+            self.modal = None;
+            return Ok(());
+        }
+
+        let inner = modal_area.inner(Margin::new(1, 1));
+        let lines = Rect {
+            height: visible_height,
+            ..inner
+        };
+        match &mut self.modal {
+            Some(Modal::WhyDepends {
+                selected,
+                vertical_scroll_state,
+                ..
+            }) => {
+                let offset =
+                    crate::ui::widgets::why_depends_offset(*selected, lines.height as usize);
+                if lines.contains(position) {
+                    let index = offset + (position.y - lines.y) as usize;
+                    if index < len {
+                        *selected = index;
+                        *vertical_scroll_state = vertical_scroll_state.position(index);
+                    }
+                }
+            }
+            Some(Modal::Packages { selected, .. }) => {
+                if let Some(index) = list_index(lines, position.y, *selected, len) {
+                    *selected = index;
+                }
+            }
+            None => {}
+        }
+
+        if double {
+            self.dispatch_action(Action::Select)?;
+        }
+        Ok(())
+    }
+
+    fn click_panes(&mut self, position: Position, area: Rect, double: bool) {
+        let [previous, current, next] = crate::ui::pane::pane_areas(area, self.pane_widths);
+        let list = |pane: Rect| pane.inner(Margin::new(1, 1));
+
+        if current.contains(position) {
+            let selected = self.current_state.selected().unwrap_or(0);
+            if let Some(index) = list_index(
+                list(current),
+                position.y,
+                selected,
+                self.current_items.len(),
+            ) {
+                self.current_state.select(Some(index));
+                self.update_panes();
+                if double {
+                    self.move_right();
+                }
+            }
+        } else if next.contains(position) {
+            let selected = self.next_state.selected().unwrap_or(0);
+            if let Some(index) = list_index(list(next), position.y, selected, self.next_items.len())
+            {
+                self.move_right();
+                self.current_state.select(Some(index));
+                self.update_panes();
+            }
+        } else if previous.contains(position) {
+            let selected = self.previous_state.selected().unwrap_or(0);
+            let index = list_index(
+                list(previous),
+                position.y,
+                selected,
+                self.previous_items.len(),
+            );
+            let Some(referrer) = index.map(|i| self.previous_items[i].clone()) else {
+                self.move_left();
+                return;
+            };
+
+            // Going back the way we came keeps the history, and any other
+            // referrer is reached from the roots
+            let came_from = self
+                .navigation_history
+                .last()
+                .and_then(|(items, selected)| items.get((*selected)?));
+            if came_from == Some(&referrer) {
+                self.move_left();
+            } else {
+                self.select_path(&referrer);
+            }
+        }
+    }
+
+    fn click_tree(&mut self, position: Position, area: Rect, double: bool) {
+        // Inside the border, below the header
+        let inner = area.inner(Margin::new(1, 1));
+        let lines = Rect {
+            y: inner.y + 1,
+            height: inner.height.saturating_sub(1),
+            ..inner
+        };
+        let rows = self.tree_rows();
+        let selected = rows
+            .iter()
+            .position(|r| r.chain == self.tree_selected)
+            .unwrap_or(0);
+        if lines.contains(position)
+            && let Some(index) = list_index(lines, position.y, selected, rows.len())
+        {
+            self.tree_select(rows[index].chain.clone());
+            if double {
+                if self.tree_expanded.contains(&self.tree_selected) {
+                    self.tree_collapse();
+                } else {
+                    self.tree_expand();
+                }
+            }
+        }
+    }
+
+    fn click_treemap(&mut self, position: Position, area: Rect, double: bool) {
+        let Some(path) = &self.current_path else {
+            return;
+        };
+        let tiles = self.treemap_tiles(path);
+        let sizes: Vec<u64> = tiles.iter().map(|(_, size)| *size).collect();
+        let rects = crate::ui::treemap::squarify(&sizes, area.inner(Margin::new(1, 1)));
+        if let Some(index) = rects.iter().position(|r| r.contains(position)) {
+            self.treemap_selected = index;
+            if double {
+                self.treemap_drill_in();
+            }
+        }
+    }
+
+    fn search_key(&mut self, key: KeyEvent) {
+        match key.code {
+            KeyCode::Esc => {
+                self.searching = false;
+                self.search_query.clear();
+                self.search_error = None;
+                if let Some(snapshot) = self.search_snapshot.take() {
+                    self.restore_panes(snapshot);
+                }
+            }
+            KeyCode::Enter => {
+                self.searching = false;
+                self.search_error = None;
+                self.perform_search();
+                if !self.searching {
+                    self.search_snapshot = None;
+                }
+            }
+            KeyCode::Backspace => {
+                self.search_query.pop();
+                self.search_error = None;
+                self.preview_search();
+            }
+            KeyCode::Char(c) => {
+                self.search_query.push(c);
+                self.search_error = None;
+                self.preview_search();
+            }
+            KeyCode::Down => self.step_search(true),
+            KeyCode::Up => self.step_search(false),
+            _ => {}
+        }
+    }
+
+    /// Move to the next (or previous) match of the query being typed in
+    /// the current pane, or just down (or up) if it isn't fuzzy
+    fn step_search(&mut self, forward: bool) {
+        let query = self.search_query.trim().to_string();
+        if !crate::fuzzy::is_fuzzy_query(&query) || self.view == View::Tree {
+            if forward {
+                self.move_down();
+            } else {
+                self.move_up();
+            }
+        } else if let Some(index) = self.next_match(&query, forward) {
+            self.current_state.select(Some(index));
+            self.update_panes();
+        }
+    }
+
+    fn dispatch_action(&mut self, action: Action) -> Result<bool> {
         // Handle modal first
         if let Some(modal) = &mut self.modal {
             match modal {
@@ -116,11 +656,11 @@ impl App {
                     horizontal_scroll_state,
                     horizontal_scroll,
                 } => {
-                    match key.code {
-                        KeyCode::Char('q') | KeyCode::Esc => {
+                    match action {
+                        Action::Back | Action::Quit => {
                             self.modal = None;
                         }
-                        KeyCode::Down | KeyCode::Char('j') => {
+                        Action::Down => {
                             if *selected < formatted_lines.len().saturating_sub(1) {
                                 *selected += 1;
                                 *vertical_scroll_state = vertical_scroll_state
@@ -128,7 +668,7 @@ impl App {
                                     .position(*selected);
                             }
                         }
-                        KeyCode::Up | KeyCode::Char('k') => {
+                        Action::Up => {
                             if *selected > 0 {
                                 *selected -= 1;
                                 *vertical_scroll_state = vertical_scroll_state
@@ -136,20 +676,26 @@ impl App {
                                     .position(*selected);
                             }
                         }
-                        KeyCode::Enter => {
+                        Action::Select => {
                             if let Some(path) = paths.get(*selected).cloned() {
                                 self.modal = None;
                                 self.select_path_from_why_depends(path);
                                 return Ok(false);
                             }
                         }
-                        KeyCode::Left | KeyCode::Char('h') => {
+                        Action::Yank => {
+                            self.yanked = paths.get(*selected).map(|chain| chain.join(" → "));
+                        }
+                        Action::YankName => {
+                            self.yanked = formatted_lines.get(*selected).cloned();
+                        }
+                        Action::Left => {
                             *horizontal_scroll = horizontal_scroll.saturating_sub(5);
                             *horizontal_scroll_state = horizontal_scroll_state
                                 .content_length(*max_line_width)
                                 .position(*horizontal_scroll);
                         }
-                        KeyCode::Right | KeyCode::Char('l') => {
+                        Action::Right => {
                             // Don't scroll beyond the longest line
                             let max_scroll = max_line_width.saturating_sub(20); // Leave some buffer
                             *horizontal_scroll = (*horizontal_scroll + 5).min(max_scroll);
@@ -157,7 +703,7 @@ impl App {
                                 .content_length(*max_line_width)
                                 .position(*horizontal_scroll);
                         }
-                        KeyCode::PageDown => {
+                        Action::PageDown => {
                             let page_size = 10; // Adjust based on your modal height
                             *selected = (*selected + page_size)
                                 .min(formatted_lines.len().saturating_sub(1));
@@ -165,7 +711,7 @@ impl App {
                                 .content_length(formatted_lines.len())
                                 .position(*selected);
                         }
-                        KeyCode::PageUp => {
+                        Action::PageUp => {
                             let page_size = 10;
                             *selected = selected.saturating_sub(page_size);
                             *vertical_scroll_state = vertical_scroll_state
@@ -175,54 +721,504 @@ impl App {
                         _ => {}
                     }
                 }
+                Modal::Packages {
+                    groups,
+                    expanded,
+                    selected,
+                } => {
+                    let rows = package_rows(groups, expanded);
+                    match action {
+                        Action::Back | Action::Quit => {
+                            self.modal = None;
+                        }
+                        Action::Down => {
+                            *selected = (*selected + 1).min(rows.len().saturating_sub(1));
+                        }
+                        Action::Up => {
+                            *selected = selected.saturating_sub(1);
+                        }
+                        Action::PageDown => {
+                            *selected = (*selected + 10).min(rows.len().saturating_sub(1));
+                        }
+                        Action::PageUp => {
+                            *selected = selected.saturating_sub(10);
+                        }
+                        Action::Right => {
+                            if let Some(PackageRow::Group(i)) = rows.get(*selected) {
+                                expanded.insert(*i);
+                            }
+                        }
+                        Action::Left => {
+                            // Collapse the group of the selected line
+                            let group = match rows.get(*selected) {
+                                Some(PackageRow::Group(i) | PackageRow::Path(i, _)) => *i,
+                                None => return Ok(false),
+                            };
+                            expanded.remove(&group);
+                            *selected = package_rows(groups, expanded)
+                                .iter()
+                                .position(|r| *r == PackageRow::Group(group))
+                                .unwrap_or(0);
+                        }
+                        Action::Select => match rows.get(*selected) {
+                            Some(PackageRow::Group(i)) => {
+                                if expanded.contains(i) {
+                                    expanded.remove(i);
+                                } else {
+                                    expanded.insert(*i);
+                                }
+                            }
+                            Some(PackageRow::Path(i, j)) => {
+                                let path = groups[*i].paths[*j].clone();
+                                self.modal = None;
+                                self.select_path(&path);
+                            }
+                            None => {}
+                        },
+                        Action::Yank | Action::YankName => {
+                            self.yanked = match rows.get(*selected) {
+                                Some(PackageRow::Group(i)) => Some(groups[*i].pname.clone()),
+                                Some(PackageRow::Path(i, j)) => {
+                                    let path = &groups[*i].paths[*j];
+                                    let name = self.graph.get_path(path).map(|p| p.full_name());
+                                    match name {
+                                        Some(name) if action == Action::YankName => {
+                                            Some(name.to_string())
+                                        }
+                                        _ => Some(path.clone()),
+                                    }
+                                }
+                                None => None,
+                            };
+                        }
+                        Action::Shell | Action::Pager | Action::Browse => {
+                            if let Some(PackageRow::Path(i, j)) = rows.get(*selected) {
+                                let path = groups[*i].paths[*j].clone();
+                                self.launch = Some((launched_program(action), path));
+                            }
+                        }
+                        _ => {}
+                    }
+                }
             }
             return Ok(false);
         }
 
-        if self.searching {
-            match key.code {
-                KeyCode::Esc => {
-                    self.searching = false;
-                    self.search_query.clear();
+        if self.view == View::Treemap {
+            let handled = match action {
+                Action::Treemap | Action::Back => {
+                    self.view = View::Panes;
+                    true
                 }
-                KeyCode::Enter => {
-                    self.searching = false;
-                    self.perform_search();
+                Action::Sort => {
+                    self.treemap_size = match self.treemap_size {
+                        TreemapSize::Retained => TreemapSize::Added,
+                        TreemapSize::Added => TreemapSize::Retained,
+                    };
+                    self.treemap_cache.get_mut().clear();
+                    self.treemap_selected = 0;
+                    true
                 }
-                KeyCode::Backspace => {
-                    self.search_query.pop();
+                Action::Down => {
+                    let count = self
+                        .current_path
+                        .as_ref()
+                        .map_or(0, |p| self.treemap_tiles(p).len());
+                    self.treemap_selected =
+                        (self.treemap_selected + 1).min(count.saturating_sub(1));
+                    true
                 }
-                KeyCode::Char(c) => {
-                    self.search_query.push(c);
+                Action::Up => {
+                    self.treemap_selected = self.treemap_selected.saturating_sub(1);
+                    true
                 }
-                _ => {}
+                Action::Right | Action::Select => {
+                    self.treemap_drill_in();
+                    true
+                }
+                Action::Left => {
+                    self.treemap_drill_out();
+                    true
+                }
+                _ => false,
+            };
+            if handled {
+                return Ok(false);
             }
-            return Ok(false);
         }
 
-        match key.code {
-            KeyCode::Char('q') | KeyCode::Esc => return Ok(true),
-            KeyCode::Char('?') => self.show_help = !self.show_help,
-            KeyCode::Char('/') => {
+        if self.view == View::Tree {
+            let handled = match action {
+                Action::Tree | Action::Back => {
+                    self.set_view(View::Panes);
+                    true
+                }
+                Action::Down => {
+                    self.tree_move(1);
+                    true
+                }
+                Action::Up => {
+                    self.tree_move(-1);
+                    true
+                }
+                Action::PageDown => {
+                    self.tree_move(20);
+                    true
+                }
+                Action::PageUp => {
+                    self.tree_move(-20);
+                    true
+                }
+                Action::Right => {
+                    self.tree_expand();
+                    true
+                }
+                Action::Left => {
+                    self.tree_collapse();
+                    true
+                }
+                Action::Select => {
+                    if self.tree_expanded.contains(&self.tree_selected) {
+                        self.tree_collapse();
+                    } else {
+                        self.tree_expand();
+                    }
+                    true
+                }
+                _ => false,
+            };
+            if handled {
+                return Ok(false);
+            }
+        }
+
+        match action {
+            Action::Back | Action::Quit => return Ok(true),
+            Action::Help => self.show_help = !self.show_help,
+            Action::Treemap => self.set_view(View::Treemap),
+            Action::Tree => self.set_view(View::Tree),
+            Action::Search => {
                 self.searching = true;
-                self.search_query.clear();
+                self.search_snapshot = Some(self.snapshot_panes());
+                self.search_matches = None;
+                // Start from the active filter so it can be refined
+                self.search_query = self
+                    .filter
+                    .as_ref()
+                    .map(|f| f.query().to_string())
+                    .unwrap_or_default();
+            }
+            Action::NextMatch => self.jump_to_match(true),
+            Action::PreviousMatch => self.jump_to_match(false),
+            Action::WhyDepends => self.show_why_depends(),
+            Action::Packages => self.show_packages(),
+            Action::Duplicates => self.filter_duplicates(),
+            Action::Export => self.export_dot(),
+            Action::Yank => self.yank_selected(false),
+            Action::YankName => self.yank_selected(true),
+            Action::Shell | Action::Pager | Action::Browse => {
+                self.launch = self
+                    .selected_path()
+                    .map(|path| (launched_program(action), path));
             }
-            KeyCode::Char('w') => self.show_why_depends(),
-            KeyCode::Char('s') => {
+            Action::Sort => {
                 self.sort_order = self.sort_order.next();
                 self.resort_current_pane();
             }
-            KeyCode::Down | KeyCode::Char('j') => self.move_down(),
-            KeyCode::Up | KeyCode::Char('k') => self.move_up(),
-            KeyCode::Left | KeyCode::Char('h') => self.move_left(),
-            KeyCode::Right | KeyCode::Char('l') => self.move_right(),
-            KeyCode::Enter => self.select_item(),
-            _ => {}
+            Action::Down => self.move_down(),
+            Action::Up => self.move_up(),
+            Action::Left => self.move_left(),
+            Action::Right => self.move_right(),
+            Action::Select => self.select_item(),
+            Action::PageDown => self.move_page(true),
+            Action::PageUp => self.move_page(false),
         }
 
         Ok(false)
     }
 
+    fn set_view(&mut self, view: View) {
+        match view {
+            View::Panes => {}
+            View::Treemap => self.treemap_selected = 0,
+            View::Tree => {
+                if self.view != View::Tree {
+                    self.tree_reveal_current();
+                }
+            }
+        }
+        self.view = view;
+    }
+
+    /// The references of `path` in the sort order
+    fn sorted_references(&self, path: &str) -> Vec<String> {
+        let mut refs: Vec<String> = self
+            .graph
+            .get_references(path)
+            .into_iter()
+            .map(|p| p.path.clone())
+            .collect();
+        crate::path_stats::sort_paths(&mut refs, &self.stats, self.sort_order);
+        refs
+    }
+
+    /// The rows of the tree view, depth first from the roots. Only the first
+    /// occurrence of a path can be expanded; later ones are marked as
+    /// repeated. Unlike the panes, the tree isn't limited to the active
+    /// filter, which would cut it off above the matching paths.
+    pub fn tree_rows(&self) -> Vec<TreeRow> {
+        let mut roots = self.graph.roots.clone();
+        crate::path_stats::sort_paths(&mut roots, &self.stats, self.sort_order);
+
+        let mut rows = Vec::new();
+        let mut seen = HashSet::new();
+        for root in roots {
+            self.push_tree_rows(vec![root], Vec::new(), &mut seen, &mut rows);
+        }
+        rows
+    }
+
+    fn push_tree_rows(
+        &self,
+        chain: Vec<String>,
+        last: Vec<bool>,
+        seen: &mut HashSet<String>,
+        rows: &mut Vec<TreeRow>,
+    ) {
+        let path = chain.last().expect("chains are never empty");
+        let repeated = !seen.insert(path.clone());
+        let children = self.sorted_references(path);
+        let expanded = !repeated && !children.is_empty() && self.tree_expanded.contains(&chain);
+
+        rows.push(TreeRow {
+            chain: chain.clone(),
+            has_children: !children.is_empty(),
+            expanded,
+            repeated,
+            last: last.clone(),
+        });
+
+        if expanded {
+            let count = children.len();
+            for (i, child) in children.into_iter().enumerate() {
+                let mut chain = chain.clone();
+                chain.push(child);
+                let mut last = last.clone();
+                last.push(i + 1 == count);
+                self.push_tree_rows(chain, last, seen, rows);
+            }
+        }
+    }
+
+    /// Select the row with `chain`, making it the current path in the panes
+    /// as well, as if they had been followed along the chain
+    fn tree_select(&mut self, chain: Vec<String>) {
+        self.navigation_history.clear();
+        let mut items = self.graph.roots.clone();
+        crate::path_stats::sort_paths(&mut items, &self.stats, self.sort_order);
+        for (i, path) in chain.iter().enumerate() {
+            let index = items.iter().position(|p| p == path).unwrap_or(0);
+            if i + 1 == chain.len() {
+                self.current_items = items;
+                self.current_state.select(Some(index));
+                break;
+            }
+            let children = self.sorted_references(path);
+            self.navigation_history
+                .push((std::mem::replace(&mut items, children), Some(index)));
+        }
+        self.active_pane = Pane::Current;
+        self.update_panes();
+        self.tree_selected = chain;
+    }
+
+    fn tree_move(&mut self, delta: isize) {
+        let rows = self.tree_rows();
+        let Some(index) = rows.iter().position(|r| r.chain == self.tree_selected) else {
+            return;
+        };
+        let target = index
+            .saturating_add_signed(delta)
+            .min(rows.len().saturating_sub(1));
+        if target != index {
+            self.tree_select(rows[target].chain.clone());
+        }
+    }
+
+    /// Expand the selected row, or go to its first reference if it already
+    /// is. On a repeated path, go to where it was first shown instead.
+    fn tree_expand(&mut self) {
+        let rows = self.tree_rows();
+        let Some(row) = rows.iter().find(|r| r.chain == self.tree_selected) else {
+            return;
+        };
+
+        if row.repeated {
+            if let Some(first) = rows.iter().find(|r| r.path() == row.path()) {
+                let chain = first.chain.clone();
+                if first.has_children {
+                    self.tree_expanded.insert(chain.clone());
+                }
+                self.tree_select(chain);
+            }
+        } else if row.expanded {
+            let mut chain = row.chain.clone();
+            if let Some(child) = self.sorted_references(row.path()).into_iter().next() {
+                chain.push(child);
+                self.tree_select(chain);
+            }
+        } else if row.has_children {
+            self.tree_expanded.insert(row.chain.clone());
+        }
+    }
+
+    /// Collapse the selected row, or go to its parent if it isn't expanded
+    fn tree_collapse(&mut self) {
+        if self.tree_expanded.remove(&self.tree_selected) {
+            return;
+        }
+        if self.tree_selected.len() > 1 {
+            let mut parent = self.tree_selected.clone();
+            parent.pop();
+            self.tree_select(parent);
+        }
+    }
+
+    /// Expand the tree down to the current path and select it. The chain
+    /// the panes took is used if there is one, and the first chain from a
+    /// root otherwise.
+    fn tree_reveal_current(&mut self) {
+        let Some(current) = self.current_path.clone() else {
+            return;
+        };
+
+        let mut chain: Vec<String> = self
+            .navigation_history
+            .iter()
+            .filter_map(|(items, selected)| items.get((*selected)?).cloned())
+            .collect();
+        chain.push(current.clone());
+        let is_chain = self.graph.roots.contains(&chain[0])
+            && chain.windows(2).all(|w| {
+                self.graph
+                    .get_references(&w[0])
+                    .iter()
+                    .any(|r| r.path == w[1])
+            });
+        if !is_chain {
+            chain = crate::path_stats::why_depends(&self.graph, &current)
+                .into_iter()
+                .next()
+                .unwrap_or_else(|| vec![current.clone()]);
+        }
+
+        for depth in 1..chain.len() {
+            self.tree_expanded.insert(chain[..depth].to_vec());
+        }
+
+        // The chain may pass through a path shown further up, so fall back
+        // to the first row with the current path, or the deepest row of the
+        // chain that is shown
+        let rows = self.tree_rows();
+        let selected = rows
+            .iter()
+            .find(|r| r.chain == chain)
+            .or_else(|| rows.iter().find(|r| r.path() == current))
+            .or_else(|| {
+                rows.iter()
+                    .filter(|r| chain.starts_with(&r.chain))
+                    .max_by_key(|r| r.chain.len())
+            })
+            .map(|r| r.chain.clone());
+        self.tree_selected = selected.unwrap_or_default();
+    }
+
+    /// The references of `path` with their treemap sizes, largest first.
+    /// Like the dependencies pane, they are limited to the active filter.
+    pub fn treemap_tiles(&self, path: &str) -> Vec<(String, u64)> {
+        if let Some(tiles) = self.treemap_cache.borrow().get(path) {
+            return tiles.clone();
+        }
+        let tiles = self.compute_treemap_tiles(path);
+        self.treemap_cache
+            .borrow_mut()
+            .insert(path.to_string(), tiles.clone());
+        tiles
+    }
+
+    fn compute_treemap_tiles(&self, path: &str) -> Vec<(String, u64)> {
+        let Some(id) = self.graph.id(path) else {
+            return Vec::new();
+        };
+        let mut ids: Vec<PathId> = self.graph.reference_ids(id).to_vec();
+        if let Some(filter) = &self.filter {
+            let ctx = FilterContext {
+                graph: &self.graph,
+                stats: &self.stats,
+                depths: &self.depths,
+            };
+            ids.retain(|&r| filter.matches(&ctx, &self.graph.path(r).path));
+        }
+
+        let sizes = match self.treemap_size {
+            TreemapSize::Retained => ids
+                .iter()
+                .map(|&r| {
+                    self.stats
+                        .get(&self.graph.path(r).path)
+                        .map_or(0, |s| s.retained_size)
+                })
+                .collect(),
+            TreemapSize::Added => crate::path_stats::added_sizes(&self.graph, &ids),
+        };
+
+        let mut tiles: Vec<(String, u64)> = ids
+            .into_iter()
+            .zip(sizes)
+            .map(|(r, size)| (self.graph.path(r).path.clone(), size))
+            .collect();
+        tiles.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        tiles
+    }
+
+    /// Make the selected tile the current path, as moving right in the
+    /// panes would
+    fn treemap_drill_in(&mut self) {
+        let Some(path) = &self.current_path else {
+            return;
+        };
+        let tiles = self.treemap_tiles(path);
+        let Some((target, _)) = tiles.get(self.treemap_selected.min(tiles.len().saturating_sub(1)))
+        else {
+            return;
+        };
+
+        self.navigation_history
+            .push((self.current_items.clone(), self.current_state.selected()));
+        self.current_items = self.next_items.clone();
+        let index = self.current_items.iter().position(|p| p == target);
+        self.current_state.select(Some(index.unwrap_or(0)));
+        self.update_panes();
+        self.treemap_selected = 0;
+    }
+
+    /// Go back to the previous level, selecting the tile we came from
+    fn treemap_drill_out(&mut self) {
+        if self.navigation_history.is_empty() {
+            return;
+        }
+        let came_from = self.current_path.clone();
+        self.move_left();
+
+        if let (Some(path), Some(came_from)) = (&self.current_path, came_from) {
+            self.treemap_selected = self
+                .treemap_tiles(path)
+                .iter()
+                .position(|(p, _)| *p == came_from)
+                .unwrap_or(0);
+        }
+    }
+
     fn move_down(&mut self) {
         // Navigate items in the current pane
         let items = &self.current_items;
@@ -246,6 +1242,18 @@ impl App {
         }
     }
 
+    fn move_page(&mut self, down: bool) {
+        if let Some(i) = self.current_state.selected() {
+            let i = if down {
+                (i + 10).min(self.current_items.len().saturating_sub(1))
+            } else {
+                i.saturating_sub(10)
+            };
+            self.current_state.select(Some(i));
+            self.update_panes();
+        }
+    }
+
     fn move_left(&mut self) {
         // Go back in navigation history
         if let Some((items, selected_idx)) = self.navigation_history.pop() {
@@ -293,14 +1301,17 @@ impl App {
             crate::path_stats::sort_paths(&mut self.previous_items, &self.stats, self.sort_order);
 
             // Update dependencies (right pane)
-            let mut refs = self
-                .graph
-                .get_references(path)
-                .into_iter()
-                .map(|p| p.path.clone())
-                .collect::<Vec<_>>();
-            crate::path_stats::sort_paths(&mut refs, &self.stats, self.sort_order);
-            self.next_items = refs;
+            self.next_items = self.sorted_references(path);
+
+            if let Some(filter) = &self.filter {
+                let ctx = FilterContext {
+                    graph: &self.graph,
+                    stats: &self.stats,
+                    depths: &self.depths,
+                };
+                self.previous_items = filter.apply(&ctx, &self.previous_items);
+                self.next_items = filter.apply(&ctx, &self.next_items);
+            }
 
             // Reset selections in side panes but keep current pane focus
             self.previous_state = ListState::default();
@@ -325,27 +1336,251 @@ impl App {
         crate::path_stats::sort_paths(&mut self.next_items, &self.stats, self.sort_order);
     }
 
+    fn snapshot_panes(&self) -> PaneSnapshot {
+        PaneSnapshot {
+            navigation_history: self.navigation_history.clone(),
+            current_items: self.current_items.clone(),
+            previous_state: self.previous_state.clone(),
+            current_state: self.current_state.clone(),
+            next_state: self.next_state.clone(),
+            active_pane: self.active_pane,
+        }
+    }
+
+    fn restore_panes(&mut self, snapshot: PaneSnapshot) {
+        self.navigation_history = snapshot.navigation_history;
+        self.current_items = snapshot.current_items;
+        self.current_state = snapshot.current_state;
+        self.update_panes();
+        self.previous_state = snapshot.previous_state;
+        self.next_state = snapshot.next_state;
+        self.active_pane = snapshot.active_pane;
+    }
+
+    /// The query to highlight matches of in the panes: the one being typed
+    /// if it is fuzzy, or else the last fuzzy search
+    pub fn highlight_query(&self) -> Option<&str> {
+        if self.searching {
+            crate::fuzzy::is_fuzzy_query(&self.search_query).then(|| self.search_query.trim())
+        } else {
+            self.last_search.as_deref()
+        }
+    }
+
+    /// Every path matching `query` fuzzily, best first, and in the sort
+    /// order among equally good matches
+    fn fuzzy_matches(&self, query: &str) -> Vec<String> {
+        let mut matches: Vec<(i32, String)> = self
+            .graph
+            .paths()
+            .iter()
+            .filter_map(|p| {
+                let m = crate::fuzzy::fuzzy_match(query, p.short_name())?;
+                Some((m.score, p.path.clone()))
+            })
+            .collect();
+        let mut paths: Vec<String> = matches.iter().map(|(_, p)| p.clone()).collect();
+        crate::path_stats::sort_paths(&mut paths, &self.stats, self.sort_order);
+        let rank: HashMap<&str, usize> = paths
+            .iter()
+            .enumerate()
+            .map(|(i, p)| (p.as_str(), i))
+            .collect();
+        matches.sort_by_key(|(score, p)| (std::cmp::Reverse(*score), rank[p.as_str()]));
+        matches.into_iter().map(|(_, p)| p).collect()
+    }
+
+    /// Indices of the items matching `query` fuzzily, best first, and in
+    /// pane order among equally good matches
+    fn pane_matches(&self, items: &[String], query: &str) -> Vec<usize> {
+        let mut matches: Vec<(i32, usize)> = items
+            .iter()
+            .enumerate()
+            .filter_map(|(i, path)| {
+                let name = self.graph.get_path(path)?.short_name();
+                Some((crate::fuzzy::fuzzy_match(query, name)?.score, i))
+            })
+            .collect();
+        matches.sort_by_key(|&(score, i)| (std::cmp::Reverse(score), i));
+        matches.into_iter().map(|(_, i)| i).collect()
+    }
+
+    /// Select the best match of the query being typed in the current pane
+    /// as it was, leaving its items alone; they are highlighted as they
+    /// match. The tree shows the whole graph, so there the best match
+    /// anywhere is selected.
+    fn preview_search(&mut self) {
+        let Some(snapshot) = self.search_snapshot.clone() else {
+            return;
+        };
+        let query = self.search_query.trim().to_string();
+        if !crate::fuzzy::is_fuzzy_query(&query) {
+            self.search_matches = None;
+            self.restore_panes(snapshot);
+            return;
+        }
+
+        if self.view == View::Tree {
+            let matches = self.fuzzy_matches(&query);
+            self.search_matches = Some(matches.len());
+            match matches.first() {
+                Some(best) => self.select_path(&best.clone()),
+                None => self.restore_panes(snapshot),
+            }
+            return;
+        }
+
+        let matches = self.pane_matches(&snapshot.current_items, &query);
+        self.search_matches = Some(matches.len());
+        self.restore_panes(snapshot);
+        if let Some(&best) = matches.first() {
+            self.current_state.select(Some(best));
+            self.active_pane = Pane::Current;
+            self.update_panes();
+        }
+    }
+
+    /// Apply the search query. A plain word keeps the best match selected,
+    /// for `n` and `N` to move between the matches from. Anything else is a
+    /// filter: the current pane lists every matching path, and the side
+    /// panes only show matching paths. An empty query clears the filter.
     fn perform_search(&mut self) {
-        if self.search_query.is_empty() {
+        let query = self.search_query.trim().to_string();
+        if crate::fuzzy::is_fuzzy_query(&query) {
+            self.preview_search();
+            if self.search_matches == Some(0) {
+                self.search_error = Some("No paths match".to_string());
+                self.searching = true;
+                return;
+            }
+            self.last_search = Some(query);
+            return;
+        }
+        self.last_search = None;
+
+        if self.search_query.trim().is_empty() {
+            self.filter = None;
+            self.treemap_cache.get_mut().clear();
+            self.update_panes();
             return;
         }
 
-        let query = self.search_query.to_lowercase();
+        let filter = match Filter::parse(&self.search_query) {
+            Ok(filter) => filter,
+            Err(e) => {
+                self.search_error = Some(e.to_string());
+                self.searching = true;
+                return;
+            }
+        };
+
+        let ctx = FilterContext {
+            graph: &self.graph,
+            stats: &self.stats,
+            depths: &self.depths,
+        };
         let matching_paths: Vec<String> = self
             .graph
-            .paths
+            .paths()
             .iter()
-            .filter(|p| p.name.to_lowercase().contains(&query))
+            .filter(|p| filter.matches(&ctx, &p.path))
             .map(|p| p.path.clone())
             .collect();
 
-        if !matching_paths.is_empty() {
-            self.current_items = matching_paths;
-            crate::path_stats::sort_paths(&mut self.current_items, &self.stats, self.sort_order);
-            self.current_state.select(Some(0));
-            self.active_pane = Pane::Current;
-            self.update_panes();
+        if matching_paths.is_empty() {
+            self.search_error = Some("No paths match".to_string());
+            self.searching = true;
+            return;
+        }
+
+        self.navigation_history
+            .push((self.current_items.clone(), self.current_state.selected()));
+        self.current_items = matching_paths;
+        crate::path_stats::sort_paths(&mut self.current_items, &self.stats, self.sort_order);
+        self.filter = Some(filter);
+        self.treemap_cache.get_mut().clear();
+        self.current_state.select(Some(0));
+        self.active_pane = Pane::Current;
+        self.update_panes();
+    }
+
+    /// Select the next (or previous) path in the current pane matching the
+    /// last fuzzy search, wrapping around
+    fn jump_to_match(&mut self, forward: bool) {
+        let Some(query) = self.last_search.clone() else {
+            return;
+        };
+        match self.next_match(&query, forward) {
+            Some(i) => {
+                self.current_state.select(Some(i));
+                self.update_panes();
+            }
+            None => self.message = Some(format!("No matches for {query} in this pane")),
+        }
+    }
+
+    /// The index of the next (or previous) item in the current pane
+    /// matching `query`, wrapping around
+    fn next_match(&self, query: &str, forward: bool) -> Option<usize> {
+        let len = self.current_items.len();
+        let start = self.current_state.selected().unwrap_or(0);
+        (1..=len)
+            .map(|step| {
+                if forward {
+                    (start + step) % len
+                } else {
+                    (start + len - step % len) % len
+                }
+            })
+            .find(|&i| {
+                self.graph
+                    .get_path(&self.current_items[i])
+                    .and_then(|p| crate::fuzzy::fuzzy_match(query, p.short_name()))
+                    .is_some()
+            })
+    }
+
+    /// Write the closure of the selected path as DOT to `<name>.dot` in the
+    /// working directory
+    fn export_dot(&mut self) {
+        let Some(path) = &self.current_path else {
+            return;
+        };
+        let Some(id) = self.graph.id(path) else {
+            return;
+        };
+
+        let dot = crate::export::render_dot(&self.graph, &self.stats, &[id], &self.dot_options);
+        let file = format!("{}.dot", self.graph.path(id).short_name());
+        self.message = Some(match std::fs::write(&file, dot) {
+            Ok(()) => format!("Wrote {file}"),
+            Err(e) => format!("Couldn't write {file}: {e}"),
+        });
+    }
+
+    /// The path the keys act on: the current path, or the selected tile in
+    /// the treemap
+    fn selected_path(&self) -> Option<String> {
+        let path = self.current_path.clone()?;
+        if self.view == View::Treemap {
+            let tile = self
+                .treemap_tiles(&path)
+                .into_iter()
+                .nth(self.treemap_selected);
+            return tile.map(|(tile, _)| tile);
         }
+        Some(path)
+    }
+
+    /// Leave the selected path, or its name, to be copied
+    fn yank_selected(&mut self, name: bool) {
+        let Some(path) = self.selected_path() else {
+            return;
+        };
+        self.yanked = match self.graph.get_path(&path) {
+            Some(store_path) if name => Some(store_path.full_name().to_string()),
+            _ => Some(path),
+        };
     }
 
     fn show_why_depends(&mut self) {
@@ -391,6 +1626,47 @@ impl App {
         }
     }
 
+    fn show_packages(&mut self) {
+        let groups = crate::package::group_packages(&self.graph);
+        if !groups.is_empty() {
+            self.modal = Some(Modal::Packages {
+                groups,
+                expanded: HashSet::new(),
+                selected: 0,
+            });
+        }
+    }
+
+    /// Show the paths of every package present in more than one version in
+    /// the current pane. Going left returns to the previous items.
+    fn filter_duplicates(&mut self) {
+        let mut paths: Vec<String> = crate::duplicates::duplicated_packages(&self.graph)
+            .into_iter()
+            .flat_map(|g| g.paths)
+            .collect();
+        if paths.is_empty() {
+            return;
+        }
+        crate::path_stats::sort_paths(&mut paths, &self.stats, self.sort_order);
+
+        self.navigation_history
+            .push((self.current_items.clone(), self.current_state.selected()));
+        self.current_items = paths;
+        self.current_state.select(Some(0));
+        self.active_pane = Pane::Current;
+        self.update_panes();
+    }
+
+    /// Navigate to `path` along the first chain from a root to it
+    fn select_path(&mut self, path: &str) {
+        if let Some(chain) = crate::path_stats::why_depends(&self.graph, path)
+            .into_iter()
+            .next()
+        {
+            self.select_path_from_why_depends(chain);
+        }
+    }
+
     fn select_path_from_why_depends(&mut self, path: Vec<String>) {
         // Clear navigation history
         self.navigation_history.clear();
@@ -0,0 +1,323 @@
+use anyhow::{Result, bail};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use std::fmt;
+
+/// What a key does. The same action means the equivalent thing in each
+/// view and modal, e.g. `Right` goes into a pane, expands a tree row or
+/// drills into a treemap tile.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Action {
+    Down,
+    Up,
+    Left,
+    Right,
+    PageDown,
+    PageUp,
+    Select,
+    Search,
+    NextMatch,
+    PreviousMatch,
+    WhyDepends,
+    Packages,
+    Duplicates,
+    Export,
+    /// Copy the selected path, or the why-depends chain
+    Yank,
+    /// Copy the name of the selected path, or of those in the chain
+    YankName,
+    /// Run a shell in the selected path
+    Shell,
+    /// Page through the selected path, or a listing of it
+    Pager,
+    /// Open the selected path in the configured file browser
+    Browse,
+    Treemap,
+    Tree,
+    Sort,
+    Help,
+    /// Close the modal or view, or quit from the panes
+    Back,
+    Quit,
+}
+
+impl Action {
+    pub const ALL: [Action; 25] = [
+        Action::Down,
+        Action::Up,
+        Action::Left,
+        Action::Right,
+        Action::PageDown,
+        Action::PageUp,
+        Action::Select,
+        Action::Search,
+        Action::NextMatch,
+        Action::PreviousMatch,
+        Action::WhyDepends,
+        Action::Packages,
+        Action::Duplicates,
+        Action::Export,
+        Action::Yank,
+        Action::YankName,
+        Action::Shell,
+        Action::Pager,
+        Action::Browse,
+        Action::Treemap,
+        Action::Tree,
+        Action::Sort,
+        Action::Help,
+        Action::Back,
+        Action::Quit,
+    ];
+
+    /// The name of the action in the `[keys]` table of the config file
+    pub fn name(self) -> &'static str {
+        match self {
+            Action::Down => "down",
+            Action::Up => "up",
+            Action::Left => "left",
+            Action::Right => "right",
+            Action::PageDown => "page_down",
+            Action::PageUp => "page_up",
+            Action::Select => "select",
+            Action::Search => "search",
+            Action::NextMatch => "next_match",
+            Action::PreviousMatch => "previous_match",
+            Action::WhyDepends => "why_depends",
+            Action::Packages => "packages",
+            Action::Duplicates => "duplicates",
+            Action::Export => "export",
+            Action::Yank => "yank",
+            Action::YankName => "yank_name",
+            Action::Shell => "shell",
+            Action::Pager => "pager",
+            Action::Browse => "browse",
+            Action::Treemap => "treemap",
+            Action::Tree => "tree",
+            Action::Sort => "sort",
+            Action::Help => "help",
+            Action::Back => "back",
+            Action::Quit => "quit",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Action> {
+        Action::ALL.into_iter().find(|a| a.name() == name)
+    }
+
+    /// What it does from the panes, for the help screen
+    pub fn description(self) -> &'static str {
+        match self {
+            Action::Down => "Move down",
+            Action::Up => "Move up",
+            Action::Left => "Move to previous pane",
+            Action::Right => "Move to next pane",
+            Action::PageDown => "Move down a page",
+            Action::PageUp => "Move up a page",
+            Action::Select => "Select item",
+            Action::Search => "Search as you type, or filter, e.g. name=*-dev and closure>10MB",
+            Action::NextMatch => "Go to the next match of the last search",
+            Action::PreviousMatch => "Go to the previous match of the last search",
+            Action::WhyDepends => "Show why-depends",
+            Action::Packages => "Show packages, grouping versions and outputs",
+            Action::Duplicates => "Show only packages present in more than one version",
+            Action::Export => "Export the selected path's closure as DOT",
+            Action::Yank => "Copy the selected path, or why-depends chain, to the clipboard",
+            Action::YankName => "Copy the selected path's name, or the chain's names",
+            Action::Shell => "Open $SHELL in the selected path",
+            Action::Pager => "Open the selected path, or a listing of it, in $PAGER",
+            Action::Browse => "Open the selected path in the configured file browser",
+            Action::Treemap => "Toggle the treemap of the selected path's references",
+            Action::Tree => "Toggle the tree view, at the selected path",
+            Action::Sort => "Change sort order",
+            Action::Help => "Toggle this help",
+            Action::Back => "Close the modal or view, or quit",
+            Action::Quit => "Quit",
+        }
+    }
+
+    fn default_keys(self) -> &'static [&'static str] {
+        match self {
+            Action::Down => &["j", "Down"],
+            Action::Up => &["k", "Up"],
+            Action::Left => &["h", "Left"],
+            Action::Right => &["l", "Right"],
+            Action::PageDown => &["PageDown"],
+            Action::PageUp => &["PageUp"],
+            Action::Select => &["Enter", "Space"],
+            Action::Search => &["/"],
+            Action::NextMatch => &["n"],
+            Action::PreviousMatch => &["N"],
+            Action::WhyDepends => &["w"],
+            Action::Packages => &["p"],
+            Action::Duplicates => &["d"],
+            Action::Export => &["x"],
+            Action::Yank => &["y"],
+            Action::YankName => &["Y"],
+            Action::Shell => &["!"],
+            Action::Pager => &["o"],
+            Action::Browse => &["b"],
+            Action::Treemap => &["t"],
+            Action::Tree => &["v"],
+            Action::Sort => &["s"],
+            Action::Help => &["?"],
+            Action::Back => &["Esc"],
+            Action::Quit => &["q"],
+        }
+    }
+}
+
+/// A key with the modifiers held for it. Shift is part of the character
+/// rather than a modifier, so `N` is written as such and not `Shift-n`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Key {
+    pub code: KeyCode,
+    pub modifiers: KeyModifiers,
+}
+
+impl Key {
+    /// Parse a key like `j`, `PageDown`, `Space`, `F5` or `Ctrl-n`
+    pub fn parse(text: &str) -> Result<Key> {
+        let mut modifiers = KeyModifiers::NONE;
+        let mut rest = text;
+        while let Some((modifier, key)) = rest.split_once('-')
+            && !key.is_empty()
+        {
+            modifiers |= match modifier.to_ascii_lowercase().as_str() {
+                "ctrl" | "c" => KeyModifiers::CONTROL,
+                "alt" | "m" => KeyModifiers::ALT,
+                _ => bail!("Unknown modifier '{modifier}' in key '{text}'"),
+            };
+            rest = key;
+        }
+
+        let mut chars = rest.chars();
+        let code = match (chars.next(), chars.next()) {
+            (Some(c), None) => KeyCode::Char(c),
+            _ => match rest.to_ascii_lowercase().as_str() {
+                "esc" | "escape" => KeyCode::Esc,
+                "enter" | "return" => KeyCode::Enter,
+                "space" => KeyCode::Char(' '),
+                "tab" => KeyCode::Tab,
+                "backtab" => KeyCode::BackTab,
+                "backspace" => KeyCode::Backspace,
+                "delete" | "del" => KeyCode::Delete,
+                "insert" => KeyCode::Insert,
+                "home" => KeyCode::Home,
+                "end" => KeyCode::End,
+                "pageup" => KeyCode::PageUp,
+                "pagedown" => KeyCode::PageDown,
+                "up" => KeyCode::Up,
+                "down" => KeyCode::Down,
+                "left" => KeyCode::Left,
+                "right" => KeyCode::Right,
+                name => match name.strip_prefix('f').and_then(|n| n.parse().ok()) {
+                    Some(n @ 1..=24) => KeyCode::F(n),
+                    _ => bail!("Unknown key '{text}'"),
+                },
+            },
+        };
+        Ok(Key { code, modifiers })
+    }
+
+    pub fn matches(&self, event: &KeyEvent) -> bool {
+        event.code == self.code && event.modifiers.difference(KeyModifiers::SHIFT) == self.modifiers
+    }
+}
+
+impl fmt::Display for Key {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.modifiers.contains(KeyModifiers::CONTROL) {
+            write!(f, "Ctrl-")?;
+        }
+        if self.modifiers.contains(KeyModifiers::ALT) {
+            write!(f, "Alt-")?;
+        }
+        match self.code {
+            KeyCode::Char(' ') => write!(f, "Space"),
+            KeyCode::Char(c) => write!(f, "{c}"),
+            KeyCode::Esc => write!(f, "Esc"),
+            KeyCode::Enter => write!(f, "Enter"),
+            KeyCode::Tab => write!(f, "Tab"),
+            KeyCode::BackTab => write!(f, "BackTab"),
+            KeyCode::Backspace => write!(f, "Backspace"),
+            KeyCode::Delete => write!(f, "Delete"),
+            KeyCode::Insert => write!(f, "Insert"),
+            KeyCode::Home => write!(f, "Home"),
+            KeyCode::End => write!(f, "End"),
+            KeyCode::PageUp => write!(f, "PageUp"),
+            KeyCode::PageDown => write!(f, "PageDown"),
+            KeyCode::Up => write!(f, "Up"),
+            KeyCode::Down => write!(f, "Down"),
+            KeyCode::Left => write!(f, "Left"),
+            KeyCode::Right => write!(f, "Right"),
+            KeyCode::F(n) => write!(f, "F{n}"),
+            code => write!(f, "{code:?}"),
+        }
+    }
+}
+
+/// The keys bound to each action
+#[derive(Debug, Clone)]
+pub struct Keymap {
+    bindings: Vec<(Action, Vec<Key>)>,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        let bindings = Action::ALL
+            .into_iter()
+            .map(|action| {
+                let keys = action.default_keys().iter();
+                let keys = keys.map(|k| Key::parse(k).expect("default keys parse"));
+                (action, keys.collect())
+            })
+            .collect();
+        Self { bindings }
+    }
+}
+
+impl Keymap {
+    /// The action bound to the key pressed, if any
+    pub fn action(&self, event: &KeyEvent) -> Option<Action> {
+        self.bindings
+            .iter()
+            .find(|(_, keys)| keys.iter().any(|k| k.matches(event)))
+            .map(|(action, _)| *action)
+    }
+
+    pub fn keys(&self, action: Action) -> &[Key] {
+        self.bindings
+            .iter()
+            .find(|(a, _)| *a == action)
+            .map_or(&[], |(_, keys)| keys)
+    }
+
+    /// Bind `keys` to `action` instead of its current keys, taking them
+    /// from any other action they were bound to
+    pub fn bind(&mut self, action: Action, keys: Vec<Key>) {
+        for (other, bound) in &mut self.bindings {
+            if *other == action {
+                bound.clone_from(&keys);
+            } else {
+                bound.retain(|k| !keys.contains(k));
+            }
+        }
+    }
+
+    /// All the keys of `action`, like `j/Down`
+    pub fn describe(&self, action: Action) -> String {
+        let keys: Vec<String> = self.keys(action).iter().map(Key::to_string).collect();
+        if keys.is_empty() {
+            "(none)".to_string()
+        } else {
+            keys.join("/")
+        }
+    }
+
+    /// The first key of `action`, for hints
+    pub fn primary(&self, action: Action) -> String {
+        self.keys(action)
+            .first()
+            .map_or("(none)".to_string(), Key::to_string)
+    }
+}
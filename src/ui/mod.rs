@@ -1,5 +1,9 @@
 pub mod app;
+pub mod keymap;
 pub mod pane;
+pub mod theme;
+pub mod tree;
+pub mod treemap;
 pub mod widgets;
 
 pub use app::App;
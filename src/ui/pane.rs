@@ -7,17 +7,22 @@ use ratatui::{
 };
 
 use crate::path_stats::PathStats;
-use crate::store_path::StorePathGraph;
-use crate::ui::app::{App, Pane};
+use crate::store_path::{StorePath, StorePathGraph};
+use crate::ui::app::{App, Highlight, Pane};
+use crate::ui::theme::Theme;
 use std::collections::HashMap;
 
+/// The referrers, current and dependencies panes, in proportion to `widths`
+pub fn pane_areas(area: Rect, widths: [u16; 3]) -> [Rect; 3] {
+    let total: u16 = widths.iter().sum();
+    ratatui::layout::Layout::horizontal(
+        widths.map(|w| Constraint::Ratio(u32::from(w), u32::from(total.max(1)))),
+    )
+    .areas(area)
+}
+
 pub fn render_panes(f: &mut Frame, app: &App, area: Rect) {
-    let chunks = ratatui::layout::Layout::horizontal([
-        Constraint::Percentage(30),
-        Constraint::Percentage(40),
-        Constraint::Percentage(30),
-    ])
-    .split(area);
+    let chunks = pane_areas(area, app.pane_widths);
 
     render_pane(
         f,
@@ -29,6 +34,9 @@ pub fn render_panes(f: &mut Frame, app: &App, area: Rect) {
             is_active: app.active_pane == Pane::Previous,
             graph: &app.graph,
             stats: &app.stats,
+            highlights: &app.highlights,
+            query: app.highlight_query(),
+            theme: &app.theme,
         },
     );
 
@@ -42,6 +50,9 @@ pub fn render_panes(f: &mut Frame, app: &App, area: Rect) {
             is_active: app.active_pane == Pane::Current,
             graph: &app.graph,
             stats: &app.stats,
+            highlights: &app.highlights,
+            query: app.highlight_query(),
+            theme: &app.theme,
         },
     );
 
@@ -55,6 +66,9 @@ pub fn render_panes(f: &mut Frame, app: &App, area: Rect) {
             is_active: app.active_pane == Pane::Next,
             graph: &app.graph,
             stats: &app.stats,
+            highlights: &app.highlights,
+            query: app.highlight_query(),
+            theme: &app.theme,
         },
     );
 }
@@ -65,11 +79,16 @@ struct PaneRenderContext<'a> {
     is_active: bool,
     graph: &'a StorePathGraph,
     stats: &'a HashMap<String, PathStats>,
+    highlights: &'a HashMap<String, Highlight>,
+    /// Fuzzy search whose matched characters are highlighted
+    query: Option<&'a str>,
+    theme: &'a Theme,
 }
 
 fn render_pane(f: &mut Frame, area: Rect, title: &str, ctx: &PaneRenderContext) {
+    let theme = ctx.theme;
     let border_style = if ctx.is_active {
-        Style::default().fg(Color::Yellow)
+        theme.active_border()
     } else {
         Style::default()
     };
@@ -91,25 +110,19 @@ fn render_pane(f: &mut Frame, area: Rect, title: &str, ctx: &PaneRenderContext)
                 String::new()
             };
 
-            let signed = store_path
-                .map(|p| if p.is_signed() { "✓ " } else { "  " })
-                .unwrap_or("  ");
+            let (signed, marker_color, name_style) =
+                path_marker(theme, store_path, ctx.highlights.get(path));
 
-            let style = if is_selected && ctx.is_active {
-                Style::default()
-                    .bg(Color::Blue)
-                    .add_modifier(Modifier::BOLD)
-            } else if is_selected {
-                Style::default().bg(Color::DarkGray)
+            let style = if is_selected {
+                theme.selection(ctx.is_active)
             } else {
                 Style::default()
             };
 
-            let line = Line::from(vec![
-                Span::styled(signed, Style::default().fg(Color::Cyan)),
-                Span::raw(name),
-                Span::styled(size_str, Style::default().fg(Color::Green)),
-            ]);
+            let mut spans = vec![Span::styled(signed, theme.fg(marker_color))];
+            spans.extend(highlighted_name(theme, name, name_style, ctx.query));
+            spans.push(Span::styled(size_str, theme.fg(theme.closure_size)));
+            let line = Line::from(spans);
 
             ListItem::new(line).style(style)
         })
@@ -124,3 +137,77 @@ fn render_pane(f: &mut Frame, area: Rect, title: &str, ctx: &PaneRenderContext)
 
     f.render_stateful_widget(list, area, &mut ctx.state.clone());
 }
+
+/// The marker in front of a path, its colour, and the style of the path's
+/// name
+pub(crate) fn path_marker(
+    theme: &Theme,
+    store_path: Option<&StorePath>,
+    highlight: Option<&Highlight>,
+) -> (&'static str, Color, Style) {
+    let (signed, marker_color) = match highlight {
+        Some(Highlight::Added) => ("+ ", theme.added),
+        Some(Highlight::Removed) => ("- ", theme.removed),
+        Some(Highlight::Forbidden) => ("! ", theme.forbidden),
+        None => {
+            let signed = store_path
+                .map(|p| {
+                    if p.missing {
+                        "? "
+                    } else if p.is_signed() {
+                        "✓ "
+                    } else {
+                        "  "
+                    }
+                })
+                .unwrap_or("  ");
+            (signed, theme.signed)
+        }
+    };
+
+    // Placeholders for paths that aren't in the store are dimmed
+    let name_style = match highlight {
+        Some(Highlight::Added) => theme.fg(theme.added),
+        Some(Highlight::Removed) => theme.fg(theme.removed),
+        Some(Highlight::Forbidden) => theme.fg(theme.forbidden).add_modifier(Modifier::BOLD),
+        None if store_path.is_some_and(|p| p.missing) => theme.fg(theme.dimmed),
+        None => Style::default(),
+    };
+
+    (signed, marker_color, name_style)
+}
+
+/// `name` in `style`, with the characters matching `query` picked out
+pub(crate) fn highlighted_name<'a>(
+    theme: &Theme,
+    name: &'a str,
+    style: Style,
+    query: Option<&str>,
+) -> Vec<Span<'a>> {
+    let Some(m) = query.and_then(|q| crate::fuzzy::fuzzy_match(q, name)) else {
+        return vec![Span::styled(name, style)];
+    };
+
+    let matched_style = style
+        .fg(theme.matched)
+        .add_modifier(Modifier::BOLD | Modifier::UNDERLINED);
+    let mut spans = Vec::new();
+    let mut start = 0;
+    let mut matched = false;
+    for (i, (offset, _)) in name.char_indices().enumerate() {
+        let is_match = m.positions.contains(&i);
+        if is_match != matched && offset > start {
+            spans.push(Span::styled(
+                &name[start..offset],
+                if matched { matched_style } else { style },
+            ));
+            start = offset;
+        }
+        matched = is_match;
+    }
+    spans.push(Span::styled(
+        &name[start..],
+        if matched { matched_style } else { style },
+    ));
+    spans
+}
@@ -0,0 +1,170 @@
+use ratatui::style::{Color, Modifier, Style};
+
+/// The colours of the interface, by what they mark
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Theme {
+    /// No colours at all, for `NO_COLOR`: the selection is reversed and the
+    /// active pane's border bold instead
+    pub monochrome: bool,
+    /// The border of the active pane or view, and the filter in the status
+    /// bar
+    pub accent: Color,
+    /// Background of the selected path in the active pane
+    pub selected: Color,
+    /// Background of the selected path in the other panes
+    pub selected_inactive: Color,
+    /// The characters matching a fuzzy search
+    pub matched: Color,
+    /// The signature marker
+    pub signed: Color,
+    /// Paths only in the new closure of a diff
+    pub added: Color,
+    /// Paths only in the old closure of a diff
+    pub removed: Color,
+    /// Paths breaking lint rules
+    pub forbidden: Color,
+    /// Paths that aren't in the store, tree guides and hints
+    pub dimmed: Color,
+    pub message: Color,
+    pub error: Color,
+    pub help_text: Color,
+    pub help_background: Color,
+    pub nar_size: Color,
+    /// Closure sizes, in the panes and the status bar
+    pub closure_size: Color,
+    pub added_size: Color,
+    pub retained_size: Color,
+    /// Signatures and download sizes in the status bar
+    pub details: Color,
+    /// Outputs in the packages modal, and the count of paths loaded
+    pub info: Color,
+    pub parents: Color,
+    /// Colours the treemap's tiles cycle through
+    pub treemap: Vec<Color>,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self {
+            monochrome: false,
+            accent: Color::Yellow,
+            selected: Color::Blue,
+            selected_inactive: Color::DarkGray,
+            matched: Color::Yellow,
+            signed: Color::Cyan,
+            added: Color::Green,
+            removed: Color::Red,
+            forbidden: Color::LightRed,
+            dimmed: Color::DarkGray,
+            message: Color::Green,
+            error: Color::Red,
+            help_text: Color::Black,
+            help_background: Color::White,
+            nar_size: Color::Yellow,
+            closure_size: Color::Green,
+            added_size: Color::Cyan,
+            retained_size: Color::Red,
+            details: Color::Magenta,
+            info: Color::Cyan,
+            parents: Color::Blue,
+            treemap: vec![
+                Color::Cyan,
+                Color::Green,
+                Color::Magenta,
+                Color::Blue,
+                Color::Yellow,
+                Color::Red,
+            ],
+        }
+    }
+}
+
+impl Theme {
+    /// The names of the colours in the `[theme]` table of the config file
+    pub const COLORS: [&str; 20] = [
+        "accent",
+        "selected",
+        "selected_inactive",
+        "matched",
+        "signed",
+        "added",
+        "removed",
+        "forbidden",
+        "dimmed",
+        "message",
+        "error",
+        "help_text",
+        "help_background",
+        "nar_size",
+        "closure_size",
+        "added_size",
+        "retained_size",
+        "details",
+        "info",
+        "parents",
+    ];
+
+    /// The terminal's own colours everywhere
+    pub fn monochrome() -> Self {
+        let mut theme = Self {
+            monochrome: true,
+            treemap: vec![Color::Reset],
+            ..Self::default()
+        };
+        for name in Self::COLORS {
+            *theme.color_mut(name).expect("listed colours exist") = Color::Reset;
+        }
+        theme
+    }
+
+    pub fn color_mut(&mut self, name: &str) -> Option<&mut Color> {
+        Some(match name {
+            "accent" => &mut self.accent,
+            "selected" => &mut self.selected,
+            "selected_inactive" => &mut self.selected_inactive,
+            "matched" => &mut self.matched,
+            "signed" => &mut self.signed,
+            "added" => &mut self.added,
+            "removed" => &mut self.removed,
+            "forbidden" => &mut self.forbidden,
+            "dimmed" => &mut self.dimmed,
+            "message" => &mut self.message,
+            "error" => &mut self.error,
+            "help_text" => &mut self.help_text,
+            "help_background" => &mut self.help_background,
+            "nar_size" => &mut self.nar_size,
+            "closure_size" => &mut self.closure_size,
+            "added_size" => &mut self.added_size,
+            "retained_size" => &mut self.retained_size,
+            "details" => &mut self.details,
+            "info" => &mut self.info,
+            "parents" => &mut self.parents,
+            _ => return None,
+        })
+    }
+
+    pub fn fg(&self, color: Color) -> Style {
+        Style::default().fg(color)
+    }
+
+    /// The border of the pane or view with the focus
+    pub fn active_border(&self) -> Style {
+        if self.monochrome {
+            self.fg(self.accent).add_modifier(Modifier::BOLD)
+        } else {
+            self.fg(self.accent)
+        }
+    }
+
+    /// The selected line, in the active pane or another one
+    pub fn selection(&self, active: bool) -> Style {
+        match (self.monochrome, active) {
+            (true, true) => Style::default().add_modifier(Modifier::REVERSED | Modifier::BOLD),
+            (true, false) => Style::default().add_modifier(Modifier::UNDERLINED),
+            (false, true) => Style::default()
+                .bg(self.selected)
+                .add_modifier(Modifier::BOLD),
+            (false, false) => Style::default().bg(self.selected_inactive),
+        }
+    }
+}
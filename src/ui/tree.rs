@@ -0,0 +1,101 @@
+use ratatui::{
+    Frame,
+    layout::{Constraint, Rect},
+    style::{Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Cell, Row, Table, TableState},
+};
+
+use crate::ui::app::App;
+use crate::ui::pane::{highlighted_name, path_marker};
+
+fn size_cell(size: Option<u64>) -> Cell<'static> {
+    let text = size.map_or(String::new(), |s| bytesize::ByteSize(s).to_string());
+    Cell::from(Line::from(text).right_aligned())
+}
+
+/// The expanded part of the graph as an indented tree, with the sizes of
+/// each path in columns
+pub fn render_tree(f: &mut Frame, app: &App, area: Rect) {
+    let rows = app.tree_rows();
+    let selected = rows.iter().position(|r| r.chain == app.tree_selected);
+    let theme = &app.theme;
+    let guide_style = theme.fg(theme.dimmed);
+    let query = app.highlight_query();
+
+    let table_rows: Vec<Row> = rows
+        .iter()
+        .map(|row| {
+            let path = row.path();
+            let store_path = app.graph.get_path(path);
+            let stats = app.stats.get(path);
+            let (marker, marker_color, name_style) =
+                path_marker(theme, store_path, app.highlights.get(path));
+
+            let mut guides = String::new();
+            if let Some((is_last, ancestors)) = row.last.split_last() {
+                for &ancestor_is_last in ancestors {
+                    guides.push_str(if ancestor_is_last { "   " } else { "│  " });
+                }
+                guides.push_str(if *is_last { "└─ " } else { "├─ " });
+            }
+            let expander = if row.repeated || !row.has_children {
+                "  "
+            } else if row.expanded {
+                "▾ "
+            } else {
+                "▸ "
+            };
+            let name = store_path.map_or(path, |p| p.short_name());
+
+            let mut spans = vec![
+                Span::styled(guides, guide_style),
+                Span::raw(expander),
+                Span::styled(marker, theme.fg(marker_color)),
+            ];
+            spans.extend(highlighted_name(theme, name, name_style, query));
+            if row.repeated && row.has_children {
+                spans.push(Span::styled(" [...]", guide_style));
+            }
+
+            Row::new(vec![
+                Cell::from(Line::from(spans)),
+                size_cell(store_path.map(|p| p.nar_size)),
+                size_cell(stats.map(|s| s.closure_size)),
+                size_cell(stats.map(|s| s.retained_size)),
+            ])
+        })
+        .collect();
+
+    let header = Row::new(vec![
+        Cell::from("Path"),
+        Cell::from(Line::from("NAR").right_aligned()),
+        Cell::from(Line::from("Closure").right_aligned()),
+        Cell::from(Line::from("Retained").right_aligned()),
+    ])
+    .style(Style::default().add_modifier(Modifier::BOLD));
+
+    let table = Table::new(
+        table_rows,
+        [
+            Constraint::Min(20),
+            Constraint::Length(11),
+            Constraint::Length(11),
+            Constraint::Length(11),
+        ],
+    )
+    .header(header)
+    .block(
+        Block::default()
+            .title(" Tree ")
+            .borders(Borders::ALL)
+            .border_style(theme.active_border()),
+    )
+    .row_highlight_style(theme.selection(true));
+
+    f.render_stateful_widget(
+        table,
+        area,
+        &mut TableState::default().with_selected(selected),
+    );
+}
@@ -0,0 +1,230 @@
+use ratatui::{
+    Frame,
+    layout::Rect,
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, BorderType, Borders, Paragraph},
+};
+
+use crate::ui::app::App;
+
+/// Terminal cells are about twice as tall as they are wide
+const CELL_ASPECT: f64 = 2.0;
+
+#[derive(Debug, Clone, Copy)]
+struct FRect {
+    x: f64,
+    y: f64,
+    w: f64,
+    h: f64,
+}
+
+/// The worst aspect ratio of a row of `values` laid along a side of `side`
+fn worst_ratio(values: &[f64], side: f64) -> f64 {
+    let sum: f64 = values.iter().sum();
+    let max = values.iter().copied().fold(0.0, f64::max);
+    let min = values.iter().copied().fold(f64::INFINITY, f64::min);
+    let side2 = side * side;
+    let sum2 = sum * sum;
+    (side2 * max / sum2).max(sum2 / (side2 * min))
+}
+
+/// Lay out `sizes`, largest first, as rectangles filling `area` in
+/// proportion to them, keeping each as close to square as possible. This is
+/// the squarified algorithm of Bruls, Huizing and van Wijk. Zero sizes get
+/// empty rectangles.
+pub fn squarify(sizes: &[u64], area: Rect) -> Vec<Rect> {
+    let mut out = vec![Rect::new(area.x, area.y, 0, 0); sizes.len()];
+    let total: u64 = sizes.iter().sum();
+    if total == 0 || area.is_empty() {
+        return out;
+    }
+
+    // Work in units where cells are square, so the tiles look square
+    let width = f64::from(area.width);
+    let height = f64::from(area.height) * CELL_ASPECT;
+    let scale = width * height / total as f64;
+    let values: Vec<f64> = sizes.iter().map(|&s| s as f64 * scale).collect();
+    let count = values.iter().take_while(|&&v| v > 0.0).count();
+
+    let mut free = FRect {
+        x: 0.0,
+        y: 0.0,
+        w: width,
+        h: height,
+    };
+    let mut frects = Vec::with_capacity(count);
+    let mut start = 0;
+    while start < count {
+        let side = free.w.min(free.h);
+        let mut end = start + 1;
+        while end < count
+            && worst_ratio(&values[start..=end], side) <= worst_ratio(&values[start..end], side)
+        {
+            end += 1;
+        }
+
+        let row = &values[start..end];
+        let row_sum: f64 = row.iter().sum();
+        if free.w >= free.h {
+            // A column along the left
+            let column_width = row_sum / free.h;
+            let mut y = free.y;
+            for &v in row {
+                let h = v / column_width;
+                frects.push(FRect {
+                    x: free.x,
+                    y,
+                    w: column_width,
+                    h,
+                });
+                y += h;
+            }
+            free.x += column_width;
+            free.w -= column_width;
+        } else {
+            // A row along the top
+            let row_height = row_sum / free.w;
+            let mut x = free.x;
+            for &v in row {
+                let w = v / row_height;
+                frects.push(FRect {
+                    x,
+                    y: free.y,
+                    w,
+                    h: row_height,
+                });
+                x += w;
+            }
+            free.y += row_height;
+            free.h -= row_height;
+        }
+        start = end;
+    }
+
+    // Round the edges rather than the sizes, so neighbours stay flush
+    let right = area.x + area.width;
+    let bottom = area.y + area.height;
+    for (rect, f) in out.iter_mut().zip(frects) {
+        let x0 = (area.x + f.x.round() as u16).min(right);
+        let x1 = (area.x + (f.x + f.w).round() as u16).min(right);
+        let y0 = (area.y + (f.y / CELL_ASPECT).round() as u16).min(bottom);
+        let y1 = (area.y + ((f.y + f.h) / CELL_ASPECT).round() as u16).min(bottom);
+        *rect = Rect::new(x0, y0, x1 - x0, y1 - y0);
+    }
+    out
+}
+
+struct Tile<'a> {
+    path: &'a str,
+    size: u64,
+    color: Color,
+    selected: bool,
+}
+
+/// The selected path's references as nested rectangles, with the larger
+/// ones showing their own references inside
+pub fn render_treemap(f: &mut Frame, app: &App, area: Rect) {
+    let Some(path) = &app.current_path else {
+        return;
+    };
+    let name = |path: &str| {
+        app.graph
+            .get_path(path)
+            .map_or(path.to_string(), |p| p.short_name().to_string())
+    };
+
+    let tiles = app.treemap_tiles(path);
+    let selected = app.treemap_selected.min(tiles.len().saturating_sub(1));
+
+    let mut title = format!(
+        " Treemap of {} by {} size ",
+        name(path),
+        app.treemap_size.as_str()
+    );
+    if let Some((path, size)) = tiles.get(selected) {
+        title.push_str(&format!(
+            "| {} ({}) ",
+            name(path),
+            bytesize::ByteSize(*size)
+        ));
+    }
+    let block = Block::default()
+        .title(title)
+        .borders(Borders::ALL)
+        .border_style(app.theme.active_border());
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+
+    if tiles.is_empty() {
+        f.render_widget(Paragraph::new("No references"), inner);
+        return;
+    }
+
+    let sizes: Vec<u64> = tiles.iter().map(|(_, size)| *size).collect();
+    for (i, ((path, size), rect)) in tiles.iter().zip(squarify(&sizes, inner)).enumerate() {
+        let tile = Tile {
+            path,
+            size: *size,
+            color: app.theme.treemap[i % app.theme.treemap.len()],
+            selected: i == selected,
+        };
+        render_tile(f, app, &tile, rect, true);
+    }
+}
+
+fn render_tile(f: &mut Frame, app: &App, tile: &Tile, area: Rect, nested: bool) {
+    if area.is_empty() {
+        return;
+    }
+    let name = app
+        .graph
+        .get_path(tile.path)
+        .map_or(tile.path, |p| p.short_name());
+    let label = format!("{name} ({})", bytesize::ByteSize(tile.size));
+
+    // Too small for a border, so just a coloured patch
+    if area.width < 4 || area.height < 2 {
+        let mut style = if app.theme.monochrome {
+            Style::default()
+        } else {
+            Style::default().bg(tile.color).fg(Color::Black)
+        };
+        if tile.selected {
+            style = style.add_modifier(Modifier::REVERSED);
+        }
+        f.render_widget(Paragraph::new(label).style(style), area);
+        return;
+    }
+
+    let mut border_style = Style::default().fg(tile.color);
+    if !nested {
+        border_style = border_style.add_modifier(Modifier::DIM);
+    }
+    let mut block = Block::default()
+        .title(Line::from(Span::styled(label, border_style)))
+        .borders(Borders::ALL)
+        .border_style(border_style);
+    if tile.selected {
+        block = block
+            .border_type(BorderType::Thick)
+            .border_style(border_style.add_modifier(Modifier::BOLD | Modifier::REVERSED));
+    }
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+
+    // One level of references inside, if there is room to read them
+    if nested && inner.width >= 8 && inner.height >= 3 {
+        let children = app.treemap_tiles(tile.path);
+        let sizes: Vec<u64> = children.iter().map(|(_, size)| *size).collect();
+        for ((path, size), rect) in children.iter().zip(squarify(&sizes, inner)) {
+            let child = Tile {
+                path,
+                size: *size,
+                color: tile.color,
+                selected: false,
+            };
+            render_tile(f, app, &child, rect, false);
+        }
+    }
+}
@@ -1,58 +1,153 @@
 use ratatui::{
     Frame,
     layout::{Alignment, Constraint, Margin, Rect},
-    style::{Color, Modifier, Style},
+    style::{Modifier, Style},
     text::{Line, Span},
     widgets::{Block, Borders, Clear, Paragraph, Scrollbar, ScrollbarOrientation, ScrollbarState},
 };
 use std::collections::HashSet;
 
-use crate::store_path::StorePathGraph;
-use crate::ui::app::{App, Modal};
+use crate::package::PackageGroup;
+use std::time::Duration;
 
-pub fn render_help(f: &mut Frame, area: Rect) {
-    let help_text = vec![
+use crate::backend::{LoadPhase, LoadProgress};
+use crate::store_path::StorePathGraph;
+use crate::ui::app::{App, Highlight, Modal, PackageRow, package_rows};
+use crate::ui::keymap::Action;
+use crate::ui::theme::Theme;
+
+pub fn render_help(f: &mut Frame, app: &App, area: Rect) {
+    let keymap = &app.keymap;
+    let key = |action| keymap.primary(action);
+    let width = Action::ALL
+        .iter()
+        .map(|&a| keymap.describe(a).chars().count())
+        .max()
+        .unwrap_or(0)
+        .max("Mouse".len())
+        + 2;
+    let line = |keys: &str, text: &str| Line::from(format!("  {keys:<width$}{text}"));
+    let note = |text: String| Line::from(format!("  {:width$}({text})", ""));
+    let action = |action: Action| line(&keymap.describe(action), action.description());
+
+    let mut help_text = vec![
         Line::from("nix-tree - Interactive Nix dependency viewer"),
         Line::from(""),
         Line::from("Navigation:"),
-        Line::from("  j/↓     Move down"),
-        Line::from("  k/↑     Move up"),
-        Line::from("  h/←     Move to previous pane"),
-        Line::from("  l/→     Move to next pane"),
-        Line::from("  Enter   Select item"),
-        Line::from(""),
-        Line::from("Actions:"),
-        Line::from("  /       Search"),
-        Line::from("  w       Show why-depends (use h/l to scroll horizontally)"),
-        Line::from("  s       Change sort order"),
-        Line::from("  ?       Toggle this help"),
-        Line::from("  q/Esc   Quit"),
-        Line::from(""),
-        Line::from("Press any key to close this help"),
     ];
+    help_text.extend(
+        [
+            Action::Down,
+            Action::Up,
+            Action::Left,
+            Action::Right,
+            Action::PageDown,
+            Action::PageUp,
+            Action::Select,
+        ]
+        .map(action),
+    );
+    help_text.push(line(
+        "Mouse",
+        "Click to select, double-click to go in, scroll to move",
+    ));
+    help_text.extend([Line::from(""), Line::from("Actions:")]);
+    for a in [
+        Action::Search,
+        Action::NextMatch,
+        Action::PreviousMatch,
+        Action::WhyDepends,
+        Action::Packages,
+        Action::Duplicates,
+        Action::Export,
+        Action::Yank,
+        Action::YankName,
+        Action::Shell,
+        Action::Pager,
+        Action::Browse,
+        Action::Treemap,
+        Action::Tree,
+        Action::Sort,
+        Action::Help,
+        Action::Back,
+        Action::Quit,
+    ] {
+        help_text.push(action(a));
+        match a {
+            Action::WhyDepends => help_text.push(note(format!(
+                "{}/{} scroll sideways",
+                key(Action::Left),
+                key(Action::Right)
+            ))),
+            Action::Treemap => help_text.push(note(format!(
+                "{}/{} select, {}/{} drill in and out, {} switches sizes",
+                key(Action::Down),
+                key(Action::Up),
+                key(Action::Right),
+                key(Action::Left),
+                key(Action::Sort)
+            ))),
+            Action::Tree => help_text.push(note(format!(
+                "{}/{} expand and collapse, {} toggles, [...] was shown above",
+                key(Action::Right),
+                key(Action::Left),
+                key(Action::Select)
+            ))),
+            _ => {}
+        }
+    }
+    help_text.extend([
+        Line::from(""),
+        Line::from(format!("Press {} to close this help", key(Action::Help))),
+    ]);
 
+    let theme = &app.theme;
+    let style = Style::default()
+        .fg(theme.help_text)
+        .bg(theme.help_background);
     let block = Block::default()
         .title("Help")
         .borders(Borders::ALL)
-        .style(Style::default().fg(Color::Black).bg(Color::White));
+        .style(style);
 
     let paragraph = Paragraph::new(help_text)
         .block(block)
-        .style(Style::default().fg(Color::Black).bg(Color::White))
+        .style(style)
         .alignment(Alignment::Left);
 
-    let help_area = centered_rect(60, 70, area);
+    let help_area = centered_rect(60, 90, area);
     f.render_widget(Clear, help_area);
     f.render_widget(paragraph, help_area);
 }
 
-pub fn render_search(f: &mut Frame, area: Rect, query: &str) {
-    let search_text = vec![Line::from("Search:"), Line::from(query)];
+pub fn render_search(f: &mut Frame, app: &App, area: Rect) {
+    let theme = &app.theme;
+    let query = app.search_query.as_str();
+    let error = app.search_error.as_deref();
+    let mut search_text = vec![
+        Line::from("Search (fuzzy, e.g. ffx, or a filter like closure>100MB and not signed):"),
+        Line::from(query),
+    ];
+    if let Some(matches) = app.search_matches
+        && error.is_none()
+    {
+        let text = match matches {
+            0 => "No matches".to_string(),
+            1 => "1 match".to_string(),
+            n => format!("{n} matches"),
+        };
+        search_text.push(Line::from(""));
+        search_text.push(Line::styled(text, theme.fg(theme.dimmed)));
+    }
+    if let Some(error) = error {
+        search_text.push(Line::from(""));
+        search_text.push(Line::styled(error, theme.fg(theme.error)));
+    }
 
     let block = Block::default()
         .title("Search")
         .borders(Borders::ALL)
-        .style(Style::default().fg(Color::Yellow));
+        .style(theme.fg(theme.accent));
 
     let paragraph = Paragraph::new(search_text)
         .block(block)
@@ -63,10 +158,84 @@ pub fn render_search(f: &mut Frame, area: Rect, query: &str) {
     f.render_widget(paragraph, search_area);
 }
 
+pub fn render_loading(
+    f: &mut Frame,
+    area: Rect,
+    theme: &Theme,
+    progress: &LoadProgress,
+    elapsed: Duration,
+) {
+    let current = progress.phase();
+
+    let mut text = vec![
+        Line::from(format!(
+            "Loading store paths ({:.1}s)",
+            elapsed.as_secs_f64()
+        )),
+        Line::from(""),
+    ];
+
+    for phase in LoadPhase::ALL {
+        let line = if phase < current {
+            Line::from(vec![
+                Span::styled("  ✓ ", theme.fg(theme.message)),
+                Span::raw(phase.as_str()),
+            ])
+        } else if phase == current {
+            let mut spans = vec![
+                Span::styled("  → ", theme.fg(theme.accent)),
+                Span::styled(
+                    phase.as_str(),
+                    Style::default().add_modifier(Modifier::BOLD),
+                ),
+            ];
+            if let Some(detail) = progress.detail() {
+                spans.push(Span::styled(format!(" ({detail})"), theme.fg(theme.dimmed)));
+            }
+            Line::from(spans)
+        } else {
+            Line::from(vec![
+                Span::raw("    "),
+                Span::styled(phase.as_str(), theme.fg(theme.dimmed)),
+            ])
+        };
+        text.push(line);
+    }
+
+    text.push(Line::from(""));
+    text.push(Line::from(vec![
+        Span::styled(progress.parsed().to_string(), theme.fg(theme.info)),
+        Span::raw(" paths parsed"),
+    ]));
+    text.push(Line::from(""));
+    text.push(Line::from("Press q to cancel"));
+
+    let block = Block::default().title("nix-tree").borders(Borders::ALL);
+    let paragraph = Paragraph::new(text).block(block).alignment(Alignment::Left);
+
+    let loading_area = centered_rect(50, 50, area);
+    f.render_widget(Clear, loading_area);
+    f.render_widget(paragraph, loading_area);
+}
+
 pub fn render_status_bar(f: &mut Frame, app: &App, area: Rect) {
+    let theme = &app.theme;
     if let Some(path) = &app.current_path {
         // First line: full path
-        let path_line = Line::from(vec![Span::raw(path)]);
+        let mut path_spans = vec![Span::raw(path)];
+        if let Some(filter) = &app.filter {
+            path_spans.push(Span::styled(
+                format!(" | Filter: {}", filter.query()),
+                theme.fg(theme.accent),
+            ));
+        }
+        if let Some(message) = &app.message {
+            path_spans.push(Span::styled(
+                format!(" | {message}"),
+                theme.fg(theme.message),
+            ));
+        }
+        let path_line = Line::from(path_spans);
 
         // Second line: detailed stats
         if let Some(store_path) = app.graph.get_path(path) {
@@ -78,7 +247,8 @@ pub fn render_status_bar(f: &mut Frame, app: &App, area: Rect) {
                 .unwrap_or(bytesize::ByteSize(0));
             // Calculate added size on-demand if not already calculated
             let added_size = if let Some(s) = stats {
-                match s.added_size {
+                // The stored one is relative to the roots, the first pane
+                match s.added_size.filter(|_| app.navigation_history.is_empty()) {
                     Some(size) => bytesize::ByteSize(size),
                     None => {
                         // Calculate it now using the nix-tree algorithm
@@ -92,6 +262,7 @@ pub fn render_status_bar(f: &mut Frame, app: &App, area: Rect) {
             } else {
                 bytesize::ByteSize(0)
             };
+            let retained_size = bytesize::ByteSize(stats.map(|s| s.retained_size).unwrap_or(0));
 
             let signatures = if store_path.signatures.is_empty() {
                 "none".to_string()
@@ -117,24 +288,54 @@ pub fn render_status_bar(f: &mut Frame, app: &App, area: Rect) {
                 })
                 .unwrap_or_default();
 
-            let stats_line = Line::from(vec![
+            let mut stats_spans = vec![
                 Span::raw("NAR Size: "),
-                Span::styled(nar_size.to_string(), Style::default().fg(Color::Yellow)),
+                Span::styled(nar_size.to_string(), theme.fg(theme.nar_size)),
                 Span::raw(" | Closure Size: "),
-                Span::styled(closure_size.to_string(), Style::default().fg(Color::Green)),
+                Span::styled(closure_size.to_string(), theme.fg(theme.closure_size)),
                 Span::raw(" | Added Size: "),
-                Span::styled(added_size.to_string(), Style::default().fg(Color::Cyan)),
-            ]);
+                Span::styled(added_size.to_string(), theme.fg(theme.added_size)),
+                Span::raw(" | Retained Size: "),
+                Span::styled(retained_size.to_string(), theme.fg(theme.retained_size)),
+            ];
+
+            // Only known when reading from a binary cache
+            if let Some(file_size) = store_path.file_size {
+                let compression = store_path.compression.as_deref().unwrap_or("none");
+                stats_spans.push(Span::raw(" | Download Size: "));
+                stats_spans.push(Span::styled(
+                    format!("{} ({compression})", bytesize::ByteSize(file_size)),
+                    theme.fg(theme.details),
+                ));
+            }
 
-            let info_line = Line::from(vec![
+            let stats_line = Line::from(stats_spans);
+
+            let mut info_spans = vec![
                 Span::raw("Signatures: "),
-                Span::styled(signatures, Style::default().fg(Color::Magenta)),
-            ]);
+                Span::styled(signatures, theme.fg(theme.details)),
+            ];
+
+            if app.highlights.get(path) == Some(&Highlight::Forbidden) {
+                info_spans.push(Span::styled(
+                    " | Forbidden by lint rules",
+                    theme.fg(theme.forbidden),
+                ));
+            }
+
+            if store_path.missing {
+                info_spans.push(Span::styled(" | Not in store", theme.fg(theme.error)));
+                if let Some(deriver) = &store_path.deriver {
+                    info_spans.push(Span::raw(format!(" | Deriver: {deriver}")));
+                }
+            }
+
+            let info_line = Line::from(info_spans);
 
             let parents_line = if parents_count > 0 {
                 Line::from(vec![
                     Span::raw(format!("Immediate Parents ({parents_count}): ")),
-                    Span::styled(parents_preview, Style::default().fg(Color::Blue)),
+                    Span::styled(parents_preview, theme.fg(theme.parents)),
                 ])
             } else {
                 Line::from(vec![Span::raw("Immediate Parents: none")])
@@ -241,7 +442,7 @@ pub fn render_why_depends(
     horizontal_scroll_state: ScrollbarState,
     horizontal_scroll: usize,
 ) {
-    let modal_area = centered_rect(90, 60, area);
+    let modal_area = why_depends_area(area);
 
     // Clear with black background
     f.render_widget(Clear, modal_area);
@@ -256,12 +457,7 @@ pub fn render_why_depends(
     // Calculate visible window
     let visible_height = inner_area.height.saturating_sub(1) as usize; // Leave room for borders
 
-    // Calculate scroll offset to keep selected item visible
-    let scroll_offset = if visible_height > 0 && selected >= visible_height {
-        selected.saturating_sub(visible_height / 2)
-    } else {
-        0
-    };
+    let scroll_offset = why_depends_offset(selected, visible_height);
 
     // Build visible lines
     let visible_lines = formatted_lines
@@ -330,6 +526,95 @@ pub fn render_why_depends(
     }
 }
 
+pub fn render_packages(
+    f: &mut Frame,
+    area: Rect,
+    theme: &Theme,
+    graph: &StorePathGraph,
+    groups: &[PackageGroup],
+    expanded: &HashSet<usize>,
+    selected: usize,
+) {
+    let modal_area = packages_area(area);
+    f.render_widget(Clear, modal_area);
+
+    let block = Block::default()
+        .title(format!(
+            "Packages ({}) - Enter to expand, Enter on a path to go to it",
+            groups.len()
+        ))
+        .borders(Borders::ALL);
+
+    let inner_area = block.inner(modal_area);
+    f.render_widget(block, modal_area);
+
+    let rows = package_rows(groups, expanded);
+    let visible_height = inner_area.height as usize;
+    let scroll_offset = list_offset(selected, visible_height);
+
+    let lines: Vec<Line> = rows
+        .iter()
+        .enumerate()
+        .skip(scroll_offset)
+        .take(visible_height)
+        .map(|(i, row)| {
+            let line = match *row {
+                PackageRow::Group(g) => {
+                    let group = &groups[g];
+                    let marker = if expanded.contains(&g) {
+                        "▾ "
+                    } else {
+                        "▸ "
+                    };
+                    let versions: Vec<&str> = group
+                        .versions
+                        .iter()
+                        .map(|v| if v.is_empty() { "-" } else { v.as_str() })
+                        .collect();
+                    Line::from(vec![
+                        Span::raw(marker),
+                        Span::styled(
+                            group.pname.clone(),
+                            Style::default().add_modifier(Modifier::BOLD),
+                        ),
+                        Span::raw(format!("  {}", versions.join(", "))),
+                        Span::styled(
+                            format!("  [{}]", group.outputs.join(", ")),
+                            theme.fg(theme.info),
+                        ),
+                        Span::styled(
+                            format!(" ({})", bytesize::ByteSize(group.size)),
+                            theme.fg(theme.closure_size),
+                        ),
+                    ])
+                }
+                PackageRow::Path(g, p) => {
+                    let path = &groups[g].paths[p];
+                    let (name, size) = graph
+                        .get_path(path)
+                        .map_or((path.as_str(), 0), |sp| (sp.full_name(), sp.nar_size));
+                    Line::from(vec![
+                        Span::raw("    "),
+                        Span::raw(name.to_string()),
+                        Span::styled(
+                            format!(" ({})", bytesize::ByteSize(size)),
+                            theme.fg(theme.closure_size),
+                        ),
+                    ])
+                }
+            };
+
+            if i == selected {
+                line.style(Style::default().add_modifier(Modifier::REVERSED))
+            } else {
+                line
+            }
+        })
+        .collect();
+
+    f.render_widget(Paragraph::new(lines), inner_area);
+}
+
 pub fn render_modal(f: &mut Frame, app: &App, area: Rect) {
     if let Some(modal) = &app.modal {
         match modal {
@@ -353,10 +638,60 @@ pub fn render_modal(f: &mut Frame, app: &App, area: Rect) {
                     *horizontal_scroll,
                 );
             }
+            Modal::Packages {
+                groups,
+                expanded,
+                selected,
+            } => {
+                render_packages(f, area, &app.theme, &app.graph, groups, expanded, *selected);
+            }
         }
     }
 }
 
+/// The main view above the status bar, and the status bar
+pub fn main_layout(area: Rect) -> [Rect; 2] {
+    ratatui::layout::Layout::vertical([Constraint::Min(1), Constraint::Length(4)]).areas(area)
+}
+
+pub(crate) fn why_depends_area(area: Rect) -> Rect {
+    centered_rect(90, 60, area)
+}
+
+/// The first line shown in the why-depends modal, keeping the selected
+/// line visible
+pub(crate) fn why_depends_offset(selected: usize, visible_height: usize) -> usize {
+    if visible_height > 0 && selected >= visible_height {
+        selected.saturating_sub(visible_height / 2)
+    } else {
+        0
+    }
+}
+
+pub(crate) fn packages_area(area: Rect) -> Rect {
+    centered_rect(80, 70, area)
+}
+
+/// The first item shown in a list of `visible_height` rows, scrolled just
+/// far enough to show the selected one, as ratatui's lists and tables do
+pub(crate) fn list_offset(selected: usize, visible_height: usize) -> usize {
+    if visible_height > 0 && selected >= visible_height {
+        selected + 1 - visible_height
+    } else {
+        0
+    }
+}
+
+/// The index of the item on `row` of a list drawn in `area` and scrolled
+/// by `list_offset`, if there is one
+pub(crate) fn list_index(area: Rect, row: u16, selected: usize, len: usize) -> Option<usize> {
+    if row < area.y || row >= area.bottom() {
+        return None;
+    }
+    let index = list_offset(selected, area.height as usize) + (row - area.y) as usize;
+    (index < len).then_some(index)
+}
+
 fn centered_rect(percent_x: u16, percent_y: u16, r: Rect) -> Rect {
     let popup_layout = ratatui::layout::Layout::vertical([
         Constraint::Percentage((100 - percent_y) / 2),
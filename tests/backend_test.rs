@@ -0,0 +1,365 @@
+use anyhow::Result;
+use nix_tree::backend::{
+    BackendFuture, JsonBackend, LoadPhase, LoadProgress, StoreBackend, load_graph,
+};
+use nix_tree::store_path::StorePath;
+use std::io::Write;
+
+const ROOT: &str = "/nix/store/aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa-root";
+const DEP: &str = "/nix/store/bbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbb-dep";
+const LEAF: &str = "/nix/store/cccccccccccccccccccccccccccccccc-leaf";
+
+fn write_dump(json: &str) -> Result<tempfile::NamedTempFile> {
+    let mut file = tempfile::NamedTempFile::new()?;
+    file.write_all(json.as_bytes())?;
+    Ok(file)
+}
+
+#[tokio::test]
+async fn test_load_map_form_dump() -> Result<()> {
+    // Newer nix: keyed by path, no "path" field, null for invalid paths
+    let dump = write_dump(&format!(
+        r#"{{
+            "{ROOT}": {{"narSize": 100, "closureSize": 160, "references": ["{ROOT}", "{DEP}"], "signatures": ["cache.nixos.org-1:abc"]}},
+            "{DEP}": {{"narSize": 50, "closureSize": 60, "references": ["{LEAF}"]}},
+            "{LEAF}": {{"narSize": 10, "closureSize": 10, "references": []}},
+            "/nix/store/dddddddddddddddddddddddddddddddd-gone": null
+        }}"#
+    ))?;
+
+    let backend = JsonBackend::from_source(dump.path().to_str().unwrap()).await?;
+    let graph = load_graph(&backend, &[], &LoadProgress::default()).await?;
+
+    assert_eq!(graph.len(), 3);
+    // Self-references do not keep a path from being a root
+    assert_eq!(graph.roots, vec![ROOT.to_string()]);
+    assert!(graph.get_path(ROOT).unwrap().is_signed());
+    assert_eq!(graph.get_references(DEP).len(), 1);
+
+    let stats = nix_tree::path_stats::calculate_stats(&graph);
+    assert_eq!(stats[ROOT].closure_size, 160);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_load_list_form_dump() -> Result<()> {
+    // Older nix: a list of objects that carry their own path, no closure sizes
+    let dump = write_dump(&format!(
+        r#"[
+            {{"path": "{ROOT}", "narSize": 100, "references": ["{DEP}"]}},
+            {{"path": "{DEP}", "narSize": 50, "references": ["{LEAF}"]}},
+            {{"path": "{LEAF}", "narSize": 10, "references": []}}
+        ]"#
+    ))?;
+
+    let backend = JsonBackend::from_source(dump.path().to_str().unwrap()).await?;
+    let graph = load_graph(&backend, &[DEP.to_string()], &LoadProgress::default()).await?;
+
+    assert_eq!(graph.roots, vec![DEP.to_string()]);
+
+    let stats = nix_tree::path_stats::calculate_stats(&graph);
+    assert_eq!(stats[DEP].closure_size, 60);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_load_closure_from_dump() -> Result<()> {
+    // Only the closure of the requested root is loaded, not the whole dump
+    let other = "/nix/store/dddddddddddddddddddddddddddddddd-other";
+    let dump = write_dump(&format!(
+        r#"[
+            {{"path": "{ROOT}", "narSize": 100, "references": ["{DEP}"]}},
+            {{"path": "{DEP}", "narSize": 50, "references": ["{DEP}", "{LEAF}"]}},
+            {{"path": "{LEAF}", "narSize": 10, "references": []}},
+            {{"path": "{other}", "narSize": 20, "references": ["{LEAF}"]}}
+        ]"#
+    ))?;
+
+    let backend = JsonBackend::from_source(dump.path().to_str().unwrap()).await?;
+    let graph = load_graph(&backend, &[DEP.to_string()], &LoadProgress::default()).await?;
+
+    assert_eq!(graph.len(), 2);
+    assert!(graph.get_path(DEP).is_some());
+    assert!(graph.get_path(LEAF).is_some());
+    assert!(graph.get_path(ROOT).is_none());
+    assert!(graph.get_path(other).is_none());
+
+    // Without installables, the whole dump is loaded
+    let graph = load_graph(&backend, &[], &LoadProgress::default()).await?;
+    assert_eq!(graph.len(), 4);
+    assert_eq!(graph.roots, vec![ROOT.to_string(), other.to_string()]);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_load_dump_errors() -> Result<()> {
+    let dump = write_dump(&format!(r#"[{{"path": "{LEAF}", "narSize": 10}}]"#))?;
+    let backend = JsonBackend::from_source(dump.path().to_str().unwrap()).await?;
+
+    let err = load_graph(&backend, &[ROOT.to_string()], &LoadProgress::default())
+        .await
+        .unwrap_err();
+    assert!(
+        err.to_string().contains("not in the path info dump"),
+        "{err}"
+    );
+
+    let garbage = write_dump("not json")?;
+    assert!(
+        JsonBackend::from_source(garbage.path().to_str().unwrap())
+            .await
+            .is_err()
+    );
+
+    assert!(
+        JsonBackend::from_source("/nonexistent/dump.json")
+            .await
+            .is_err()
+    );
+
+    Ok(())
+}
+
+/// A backend that serves a fixed set of paths, as an embedder might provide
+struct StaticBackend {
+    paths: Vec<StorePath>,
+}
+
+impl StoreBackend for StaticBackend {
+    fn resolve_roots<'a>(
+        &'a self,
+        installables: &'a [String],
+        _progress: &'a LoadProgress,
+    ) -> BackendFuture<'a, Vec<String>> {
+        Box::pin(async move { Ok(installables.to_vec()) })
+    }
+
+    fn query_closure<'a>(
+        &'a self,
+        _roots: &'a [String],
+        _progress: &'a LoadProgress,
+    ) -> BackendFuture<'a, Vec<StorePath>> {
+        Box::pin(async move { Ok(self.paths.clone()) })
+    }
+}
+
+#[tokio::test]
+async fn test_app_from_custom_backend() -> Result<()> {
+    let path = |path: &str, nar_size, references: &[&str]| {
+        let (hash, name) = StorePath::parse(path).unwrap();
+        StorePath {
+            path: path.to_string(),
+            hash,
+            name,
+            nar_size,
+            closure_size: None,
+            references: references.iter().map(|r| r.to_string()).collect(),
+            signatures: vec![],
+            ..Default::default()
+        }
+    };
+    let backend = StaticBackend {
+        paths: vec![
+            path(ROOT, 100, &[DEP]),
+            path(DEP, 50, &[LEAF]),
+            path(LEAF, 10, &[]),
+        ],
+    };
+
+    let progress = LoadProgress::default();
+    let app = nix_tree::ui::App::from_backend(&backend, &[ROOT.to_string()], &progress).await?;
+
+    assert_eq!(progress.phase(), LoadPhase::ComputingStats);
+    assert_eq!(progress.parsed(), 3);
+    assert_eq!(app.current_items, vec![ROOT.to_string()]);
+    assert_eq!(app.next_items, vec![DEP.to_string()]);
+    assert_eq!(app.stats[ROOT].closure_size, 160);
+
+    Ok(())
+}
+
+/// Create a database with the same schema nix uses for its local store
+fn create_store_db(path: &std::path::Path) -> Result<()> {
+    let conn = rusqlite::Connection::open(path)?;
+    conn.execute_batch(&format!(
+        r#"
+        CREATE TABLE ValidPaths (
+            id               integer primary key autoincrement not null,
+            path             text unique not null,
+            hash             text not null,
+            registrationTime integer not null,
+            deriver          text,
+            narSize          integer,
+            ultimate         integer,
+            sigs             text,
+            ca               text
+        );
+        CREATE TABLE Refs (
+            referrer  integer not null,
+            reference integer not null,
+            primary key (referrer, reference)
+        );
+        INSERT INTO ValidPaths VALUES
+            (1, '{ROOT}', 'sha256:00', 1700000000, '/nix/store/eeeeeeeeeeeeeeeeeeeeeeeeeeeeeeee-root.drv', 100, 1, 'cache.nixos.org-1:abc other-1:def', NULL),
+            (2, '{DEP}', 'sha256:01', 1700000001, NULL, 50, NULL, NULL, NULL),
+            (3, '{LEAF}', 'sha256:02', 1700000002, NULL, 10, NULL, '', 'fixed:r:sha256:1b8m03r63zqhnjf7l5wnldhh7c134ap5vpj0850ymkq1iyzicy5s'),
+            (4, '/nix/store/ffffffffffffffffffffffffffffffff-unrelated', 'sha256:03', 1700000003, NULL, 7, NULL, NULL, NULL);
+        INSERT INTO Refs VALUES (1, 1), (1, 2), (2, 3);
+        "#
+    ))?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_sqlite_backend() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let db_path = dir.path().join("db.sqlite");
+    create_store_db(&db_path)?;
+
+    let backend = nix_tree::backend::SqliteBackend::open(&db_path).await?;
+
+    // Without installables the whole store is loaded
+    let graph = load_graph(&backend, &[], &LoadProgress::default()).await?;
+    assert_eq!(graph.len(), 4);
+    assert_eq!(graph.roots.len(), 2);
+    assert!(graph.roots.contains(&ROOT.to_string()));
+
+    // With a root only its closure is loaded
+    let graph = load_graph(&backend, &[ROOT.to_string()], &LoadProgress::default()).await?;
+    assert_eq!(graph.len(), 3);
+
+    let root = graph.get_path(ROOT).unwrap();
+    assert_eq!(root.nar_size, 100);
+    assert_eq!(root.registration_time, Some(1700000000));
+    assert_eq!(
+        root.deriver.as_deref(),
+        Some("/nix/store/eeeeeeeeeeeeeeeeeeeeeeeeeeeeeeee-root.drv")
+    );
+    assert_eq!(root.signatures.len(), 2);
+    assert_eq!(graph.get_references(ROOT).len(), 1);
+
+    let leaf = graph.get_path(LEAF).unwrap();
+    assert!(!leaf.is_signed());
+    assert!(leaf.ca.as_deref().unwrap().starts_with("fixed:r:sha256:"));
+
+    let stats = nix_tree::path_stats::calculate_stats(&graph);
+    assert_eq!(stats[ROOT].closure_size, 160);
+
+    let err = load_graph(
+        &backend,
+        &["/nix/store/gggggggggggggggggggggggggggggggg-missing".to_string()],
+        &LoadProgress::default(),
+    )
+    .await
+    .unwrap_err();
+    assert!(err.to_string().contains("not a valid path"), "{err}");
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_sqlite_backend_missing_db() {
+    let dir = tempfile::tempdir().unwrap();
+    assert!(
+        nix_tree::backend::SqliteBackend::open(dir.path().join("missing.sqlite"))
+            .await
+            .is_err()
+    );
+}
+
+fn write_narinfo(
+    dir: &std::path::Path,
+    path: &str,
+    nar_size: u64,
+    references: &[&str],
+) -> Result<()> {
+    let base = path.strip_prefix("/nix/store/").unwrap();
+    let hash = &base[..32];
+    let references = references
+        .iter()
+        .map(|r| r.strip_prefix("/nix/store/").unwrap())
+        .collect::<Vec<_>>()
+        .join(" ");
+    std::fs::write(
+        dir.join(format!("{hash}.narinfo")),
+        format!(
+            "StorePath: {path}\n\
+             URL: nar/{hash}.nar.xz\n\
+             Compression: xz\n\
+             FileHash: sha256:0000000000000000000000000000000000000000000000000000\n\
+             FileSize: {}\n\
+             NarHash: sha256:0000000000000000000000000000000000000000000000000000\n\
+             NarSize: {nar_size}\n\
+             References: {references}\n\
+             Deriver: {hash}-x.drv\n\
+             Sig: cache.example.org-1:abc\n",
+            nar_size / 4
+        ),
+    )?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_binary_cache_backend() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    std::fs::write(
+        dir.path().join("nix-cache-info"),
+        "StoreDir: /nix/store\nWantMassQuery: 1\nPriority: 30\n",
+    )?;
+    write_narinfo(dir.path(), ROOT, 100, &[ROOT, DEP])?;
+    write_narinfo(dir.path(), DEP, 48, &[LEAF])?;
+    write_narinfo(dir.path(), LEAF, 12, &[])?;
+
+    let url = format!("file://{}?compression=xz", dir.path().display());
+    let backend = nix_tree::backend::BinaryCacheBackend::from_url(&url).unwrap();
+
+    let graph = load_graph(&backend, &[], &LoadProgress::default()).await?;
+    assert_eq!(graph.roots, vec![ROOT.to_string()]);
+    assert_eq!(graph.len(), 3);
+
+    let graph = load_graph(&backend, &[DEP.to_string()], &LoadProgress::default()).await?;
+    assert_eq!(graph.len(), 2);
+    let dep = graph.get_path(DEP).unwrap();
+    assert_eq!(dep.references, vec![LEAF.to_string()]);
+    assert_eq!(dep.file_size, Some(12));
+    assert_eq!(dep.compression.as_deref(), Some("xz"));
+    assert_eq!(
+        dep.deriver.as_deref(),
+        Some("/nix/store/bbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbb-x.drv")
+    );
+    assert!(dep.is_signed());
+
+    let stats = nix_tree::path_stats::calculate_stats(&graph);
+    assert_eq!(stats[DEP].closure_size, 60);
+
+    let err = load_graph(
+        &backend,
+        &["/nix/store/gggggggggggggggggggggggggggggggg-missing".to_string()],
+        &LoadProgress::default(),
+    )
+    .await
+    .unwrap_err();
+    assert!(err.to_string().contains("not in the binary cache"), "{err}");
+
+    Ok(())
+}
+
+#[test]
+fn test_parse_narinfo_errors() {
+    use nix_tree::backend::binary_cache::NarInfo;
+
+    assert!(NarInfo::parse("NarSize: 10\n").is_err());
+    assert!(NarInfo::parse(&format!("StorePath: {ROOT}\n")).is_err());
+    assert!(NarInfo::parse(&format!("StorePath: {ROOT}\nNarSize: ten\n")).is_err());
+    assert!(NarInfo::parse(&format!("StorePath: {ROOT}\ngarbage\n")).is_err());
+
+    let info = NarInfo::parse(&format!(
+        "StorePath: {ROOT}\nNarSize: 10\nReferences: \nDeriver: unknown-deriver\n"
+    ))
+    .unwrap();
+    assert!(info.references.is_empty());
+    assert_eq!(info.deriver, None);
+}
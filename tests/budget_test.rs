@@ -0,0 +1,278 @@
+mod common;
+
+use common::{path, store_path};
+use nix_tree::budget::{Budget, Growth, Size, check, render_text};
+use nix_tree::path_stats;
+use nix_tree::report::Report;
+use nix_tree::store_path::StorePathGraph;
+use std::process::Command;
+
+/// image -> {app, python}, app -> {glibc, zlib-dev}, python -> glibc
+fn graph() -> StorePathGraph {
+    let glibc = store_path('g', "glibc-2.39", 3000, &[]);
+    let zlib_dev = store_path('z', "zlib-1.3-dev", 500, &[]);
+    let app = store_path('a', "app-1.0", 1000, &[&glibc, &zlib_dev]);
+    let python = store_path('p', "python3-3.11.9", 8000, &[&glibc]);
+    let image = store_path('i', "image", 10, &[&app, &python]);
+
+    let root = image.path.clone();
+    StorePathGraph::from_paths(vec![glibc, zlib_dev, app, python, image], vec![root])
+}
+
+fn run(budget: &Budget, baseline: Option<&Report>) -> Vec<nix_tree::budget::BudgetCheck> {
+    let graph = graph();
+    let stats = path_stats::calculate_stats(&graph);
+    let depths = path_stats::root_depths(&graph);
+    check(budget, &graph, &stats, &depths, baseline).unwrap()
+}
+
+fn write_budget(dir: &std::path::Path, toml: &str) -> std::path::PathBuf {
+    let file = dir.join("budget.toml");
+    std::fs::write(&file, toml).unwrap();
+    file
+}
+
+#[test]
+fn test_load_budget() {
+    let dir = tempfile::tempdir().unwrap();
+    let file = write_budget(
+        dir.path(),
+        r#"
+max_closure_size = "10KB"
+baseline = "baseline.json"
+max_growth = "5%"
+
+[[limit]]
+match = "python3*"
+max_closure_size = 8192
+max_total_size = "1.5k"
+"#,
+    );
+
+    let budget = Budget::load(&file).unwrap();
+    assert_eq!(budget.max_closure_size, Some(Size(10_000)));
+    assert_eq!(budget.baseline, Some(dir.path().join("baseline.json")));
+    assert_eq!(budget.max_growth, Some(Growth::Percent(5.0)));
+    assert_eq!(budget.limits[0].pattern, "python3*");
+    assert_eq!(budget.limits[0].max_closure_size, Some(Size(8192)));
+    assert_eq!(budget.limits[0].max_total_size, Some(Size(1536)));
+
+    for invalid in [
+        "max_closure_size = \"lots\"",
+        "max_closure = 1",
+        "max_growth = \"five%\"",
+        "[[limit]]\nmatch = \"(python\"",
+    ] {
+        let file = write_budget(dir.path(), invalid);
+        assert!(Budget::load(&file).is_err(), "{invalid:?} should not load");
+    }
+}
+
+#[test]
+fn test_closure_size() {
+    let budget = Budget {
+        max_closure_size: Some(Size(20_000)),
+        ..Default::default()
+    };
+    let checks = run(&budget, None);
+    assert_eq!(checks.len(), 1);
+    assert!(checks[0].passed);
+    assert_eq!(checks[0].actual, 12_510);
+    assert!(checks[0].offenders.is_empty());
+
+    let budget = Budget {
+        max_closure_size: Some(Size(10_000)),
+        ..Default::default()
+    };
+    let checks = run(&budget, None);
+    assert!(!checks[0].passed);
+    // python retains the most, and glibc is shared so retained by the image
+    let offender = &checks[0].offenders[0];
+    assert!(offender.path.ends_with("python3-3.11.9"));
+    assert_eq!(offender.size, 8000);
+    assert_eq!(offender.chains.len(), 1);
+    assert_eq!(offender.chains[0].len(), 2);
+}
+
+#[test]
+fn test_closure_size_of_roots() {
+    // Paths the roots don't reach are left out of the closure size
+    let mut paths = graph().paths().to_vec();
+    paths.push(store_path('o', "other-1.0", 50_000, &[]));
+    let graph = StorePathGraph::from_paths(paths, graph().roots.clone());
+    let stats = path_stats::calculate_stats(&graph);
+    let depths = path_stats::root_depths(&graph);
+
+    let budget = Budget {
+        max_closure_size: Some(Size(20_000)),
+        ..Default::default()
+    };
+    let checks = check(&budget, &graph, &stats, &depths, None).unwrap();
+    assert!(checks[0].passed);
+    assert_eq!(checks[0].actual, 12_510);
+}
+
+#[test]
+fn test_limits() {
+    let budget: Budget = toml::from_str(
+        r#"
+[[limit]]
+match = "depth=1"
+max_nar_size = "2KB"
+max_retained_size = "10KB"
+
+[[limit]]
+match = "*-dev"
+max_total_size = 0
+"#,
+    )
+    .unwrap();
+    let checks = run(&budget, None);
+    assert_eq!(checks.len(), 3);
+
+    assert_eq!(checks[0].rule, "NAR size of depth=1");
+    assert!(!checks[0].passed);
+    assert_eq!(checks[0].actual, 8000);
+    assert_eq!(checks[0].offenders.len(), 1);
+
+    assert_eq!(checks[1].rule, "retained size of depth=1");
+    assert!(checks[1].passed);
+
+    assert_eq!(checks[2].rule, "total size of *-dev");
+    assert!(!checks[2].passed);
+    assert_eq!(checks[2].actual, 500);
+    let chain = &checks[2].offenders[0].chains[0];
+    assert!(chain[1].ends_with("app-1.0"));
+
+    let text = render_text(&checks, &graph());
+    assert!(text.contains("FAIL NAR size of depth=1: 7.8 KiB > 2.0 KiB"));
+    assert!(text.contains("ok   retained size of depth=1"));
+    assert!(text.contains("         image → app-1.0 → zlib-1.3-dev"));
+    assert!(text.contains("2 of 3 budget checks failed"));
+}
+
+#[test]
+fn test_offender_chains() {
+    // image -> {app, tool}, app -> lib -> zlib, tool -> zlib
+    let zlib = store_path('z', "zlib-1.3", 500, &[]);
+    let lib = store_path('l', "lib-1.0", 10, &[&zlib]);
+    let app = store_path('a', "app-1.0", 10, &[&lib]);
+    let tool = store_path('t', "tool-1.0", 10, &[&zlib]);
+    let image = store_path('i', "image", 10, &[&app, &tool]);
+    let root = image.path.clone();
+    let graph = StorePathGraph::from_paths(vec![zlib, lib, app, tool, image], vec![root]);
+    let stats = path_stats::calculate_stats(&graph);
+    let depths = path_stats::root_depths(&graph);
+
+    let budget: Budget = toml::from_str("[[limit]]\nmatch = \"zlib*\"\nmax_nar_size = 0").unwrap();
+    let checks = check(&budget, &graph, &stats, &depths, None).unwrap();
+    // One chain through each referrer, shortest first
+    assert_eq!(
+        checks[0].offenders[0].chains,
+        [
+            vec![
+                path('i', "image"),
+                path('t', "tool-1.0"),
+                path('z', "zlib-1.3")
+            ],
+            vec![
+                path('i', "image"),
+                path('a', "app-1.0"),
+                path('l', "lib-1.0"),
+                path('z', "zlib-1.3"),
+            ],
+        ]
+    );
+}
+
+#[test]
+fn test_growth() {
+    let old = {
+        let glibc = store_path('g', "glibc-2.39", 3000, &[]);
+        let app = store_path('a', "app-1.0", 1000, &[&glibc]);
+        let image = store_path('i', "image", 10, &[&app]);
+        let root = image.path.clone();
+        StorePathGraph::from_paths(vec![glibc, app, image], vec![root])
+    };
+    let stats = path_stats::calculate_stats(&old);
+    let depths = path_stats::root_depths(&old);
+    let baseline = Report::new(&old, &stats, &depths, |_| true);
+
+    let budget = Budget {
+        max_growth: Some(Growth::Percent(300.0)),
+        ..Default::default()
+    };
+    let checks = run(&budget, Some(&baseline));
+    assert_eq!(checks[0].actual, 8500);
+    assert_eq!(checks[0].limit, 12_030);
+    assert!(checks[0].passed);
+
+    let budget = Budget {
+        max_growth: Some(Growth::Bytes(1000)),
+        ..Default::default()
+    };
+    let checks = run(&budget, Some(&baseline));
+    assert!(!checks[0].passed);
+    // The new paths, largest first
+    assert_eq!(checks[0].offenders.len(), 2);
+    assert!(checks[0].offenders[0].path.ends_with("python3-3.11.9"));
+    assert!(checks[0].offenders[1].path.ends_with("zlib-1.3-dev"));
+
+    let graph = graph();
+    let stats = path_stats::calculate_stats(&graph);
+    let depths = path_stats::root_depths(&graph);
+    assert!(check(&budget, &graph, &stats, &depths, None).is_err());
+}
+
+#[test]
+fn test_check_command() {
+    let dump: serde_json::Map<String, serde_json::Value> = graph()
+        .paths()
+        .iter()
+        .map(|p| {
+            (
+                p.path.clone(),
+                serde_json::json!({ "narSize": p.nar_size, "references": p.references }),
+            )
+        })
+        .collect();
+    let dir = tempfile::tempdir().unwrap();
+    let closure = dir.path().join("closure.json");
+    std::fs::write(&closure, serde_json::to_string(&dump).unwrap()).unwrap();
+
+    let check = |budget: &str| {
+        let file = write_budget(dir.path(), budget);
+        Command::new(env!("CARGO_BIN_EXE_nix-tree"))
+            .args(["check", "--budget"])
+            .arg(&file)
+            .arg("--from-json")
+            .arg(&closure)
+            .output()
+            .unwrap()
+    };
+
+    let output = check("max_closure_size = \"1MB\"");
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("All 1 budget checks passed"));
+
+    // Over budget, told apart from errors, which exit with 1
+    let output = check("max_closure_size = \"10KB\"");
+    assert_eq!(output.status.code(), Some(2));
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("FAIL closure size"));
+    assert!(stdout.contains("image → python3-3.11.9"));
+
+    // A snapshot from `report --json` works as the baseline
+    let report = Command::new(env!("CARGO_BIN_EXE_nix-tree"))
+        .args(["report", "--json", "--from-json"])
+        .arg(&closure)
+        .output()
+        .unwrap();
+    std::fs::write(dir.path().join("baseline.json"), report.stdout).unwrap();
+    let output = check("baseline = \"baseline.json\"\nmax_growth = 0");
+    assert!(output.status.success());
+
+    let output = check("baseline = \"missing.json\"\nmax_growth = 0");
+    assert_eq!(output.status.code(), Some(1));
+}
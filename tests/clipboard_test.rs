@@ -0,0 +1,95 @@
+mod common;
+
+use common::{app, keys, path};
+use crossterm::event::{KeyCode, KeyEvent};
+use nix_tree::clipboard::{Clipboard, in_tmux, osc52};
+use nix_tree::settings::Settings;
+
+#[test]
+fn test_osc52() {
+    assert_eq!(osc52("hello", false), "\x1b]52;c;aGVsbG8=\x07");
+    // Passed through tmux, with the escape doubled
+    assert_eq!(
+        osc52("hello", true),
+        "\x1bPtmux;\x1b\x1b]52;c;aGVsbG8=\x07\x1b\\"
+    );
+
+    let mut terminal = Vec::new();
+    Clipboard::default()
+        .copy("/nix/store/x", &mut terminal)
+        .unwrap();
+    assert_eq!(terminal, osc52("/nix/store/x", in_tmux()).as_bytes());
+}
+
+#[test]
+fn test_copy_command() {
+    let dir = tempfile::tempdir().unwrap();
+    let file = dir.path().join("clipboard");
+    let clipboard = Clipboard {
+        osc52: false,
+        command: Some(vec![
+            "sh".to_string(),
+            "-c".to_string(),
+            format!("cat > {}", file.display()),
+        ]),
+    };
+    let mut terminal = Vec::new();
+    clipboard.copy("firefox-125.0", &mut terminal).unwrap();
+    assert!(terminal.is_empty());
+    assert_eq!(std::fs::read_to_string(&file).unwrap(), "firefox-125.0");
+
+    let failing = Clipboard {
+        osc52: true,
+        command: Some(vec!["false".to_string()]),
+    };
+    let error = failing.copy("x", &mut terminal).unwrap_err();
+    assert!(error.to_string().starts_with("false failed"), "{error}");
+    // The escape sequence was still sent
+    assert_eq!(terminal, osc52("x", in_tmux()).as_bytes());
+
+    let settings = Settings::parse(
+        "[clipboard]\ncommand = \"xclip -selection clipboard\"",
+        false,
+    )
+    .unwrap();
+    assert!(settings.clipboard.osc52);
+    assert_eq!(
+        settings.clipboard.command.unwrap(),
+        ["xclip", "-selection", "clipboard"]
+    );
+    assert!(Settings::parse("[clipboard]\ncommand = []", false).is_err());
+}
+
+#[test]
+fn test_yank() {
+    let mut app = app();
+    keys(&mut app, "lj");
+    keys(&mut app, "y");
+    assert_eq!(app.yanked.take(), Some(path('p', "python3-3.11.9")));
+    keys(&mut app, "Y");
+    assert_eq!(app.yanked.take().as_deref(), Some("python3-3.11.9"));
+
+    // The chain selected in why-depends
+    keys(&mut app, "lw");
+    keys(&mut app, "y");
+    assert_eq!(
+        app.yanked.take().unwrap(),
+        format!(
+            "{} → {} → {}",
+            path('s', "system"),
+            path('f', "firefox-125.0"),
+            path('k', "gtk-3.24")
+        )
+    );
+    keys(&mut app, "jY");
+    assert_eq!(
+        app.yanked.take().as_deref(),
+        Some("system → python3-3.11.9 → gtk-3.24")
+    );
+    app.handle_key(KeyEvent::from(KeyCode::Esc)).unwrap();
+
+    // The tile selected in the treemap
+    keys(&mut app, "t");
+    keys(&mut app, "y");
+    assert_eq!(app.yanked.take(), Some(path('g', "glibc-2.39")));
+}
@@ -0,0 +1,62 @@
+//! Fixtures shared by the integration tests. Each test binary only uses some
+//! of them.
+#![allow(dead_code)]
+
+use crossterm::event::{KeyCode, KeyEvent};
+use nix_tree::path_stats;
+use nix_tree::store_path::{StorePath, StorePathGraph};
+use nix_tree::ui::App;
+
+/// A store path whose hash is `hash` repeated, referring to `references`
+pub fn store_path(hash: char, name: &str, nar_size: u64, references: &[&StorePath]) -> StorePath {
+    StorePath {
+        path: path(hash, name),
+        hash: hash.to_string().repeat(32),
+        name: name.to_string(),
+        nar_size,
+        references: references.iter().map(|r| r.path.clone()).collect(),
+        ..Default::default()
+    }
+}
+
+/// The path `store_path` gives `name`
+pub fn path(hash: char, name: &str) -> String {
+    format!("/nix/store/{}-{name}", hash.to_string().repeat(32))
+}
+
+/// system -> {firefox -> {gtk, glibc}, python -> gtk, bash}, gtk -> glibc
+pub fn graph() -> StorePathGraph {
+    let glibc = store_path('g', "glibc-2.39", 3000, &[]);
+    let gtk = store_path('k', "gtk-3.24", 4000, &[&glibc]);
+    let firefox = store_path('f', "firefox-125.0", 20_000, &[&gtk, &glibc]);
+    let python = store_path('p', "python3-3.11.9", 8000, &[&gtk]);
+    let bash = store_path('b', "bash-5.2", 1000, &[]);
+    let system = store_path('s', "system", 10, &[&firefox, &python, &bash]);
+
+    let root = system.path.clone();
+    StorePathGraph::from_paths(vec![glibc, gtk, firefox, python, bash, system], vec![root])
+}
+
+/// The TUI browsing `graph()`
+pub fn app() -> App {
+    let graph = graph();
+    let stats = path_stats::calculate_stats(&graph);
+    App::new(graph, stats)
+}
+
+pub fn key(app: &mut App, code: KeyCode) {
+    app.handle_key(KeyEvent::from(code)).unwrap();
+}
+
+/// Press each character of `keys` in turn
+pub fn keys(app: &mut App, keys: &str) {
+    for c in keys.chars() {
+        key(app, KeyCode::Char(c));
+    }
+}
+
+/// The short name of the current path
+pub fn current(app: &App) -> String {
+    let path = app.current_path.as_ref().unwrap();
+    app.graph.get_path(path).unwrap().short_name().to_string()
+}
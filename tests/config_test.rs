@@ -0,0 +1,164 @@
+mod common;
+
+use common::{app, current};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use nix_tree::path_stats::SortOrder;
+use nix_tree::settings::Settings;
+use nix_tree::ui::keymap::{Action, Key};
+use nix_tree::ui::pane::render_panes;
+use nix_tree::ui::theme::Theme;
+use nix_tree::ui::widgets::render_help;
+use ratatui::Terminal;
+use ratatui::backend::TestBackend;
+use ratatui::buffer::Buffer;
+use ratatui::style::{Color, Modifier};
+
+fn lines(buffer: &Buffer) -> Vec<String> {
+    (0..buffer.area.height)
+        .map(|y| {
+            (0..buffer.area.width)
+                .map(|x| buffer.cell((x, y)).unwrap().symbol())
+                .collect()
+        })
+        .collect()
+}
+
+#[test]
+fn test_parse_keys() {
+    let key = |text| Key::parse(text).unwrap();
+    assert_eq!(key("j").code, KeyCode::Char('j'));
+    assert_eq!(key("Space").code, KeyCode::Char(' '));
+    assert_eq!(key("pagedown").code, KeyCode::PageDown);
+    assert_eq!(key("F5").code, KeyCode::F(5));
+    assert_eq!(key("-").code, KeyCode::Char('-'));
+
+    let ctrl = key("Ctrl-n");
+    assert_eq!(ctrl.modifiers, KeyModifiers::CONTROL);
+    assert!(ctrl.matches(&KeyEvent::new(KeyCode::Char('n'), KeyModifiers::CONTROL)));
+    assert!(!ctrl.matches(&KeyEvent::from(KeyCode::Char('n'))));
+    assert!(key("N").matches(&KeyEvent::new(KeyCode::Char('N'), KeyModifiers::SHIFT)));
+
+    for text in ["Ctrl-Alt-x", "Space", "PageUp", "Esc", "/"] {
+        assert_eq!(key(text).to_string(), text);
+    }
+    for text in ["", "Hyper-x", "Pgdn", "F99"] {
+        assert!(Key::parse(text).is_err(), "{text:?} parsed");
+    }
+}
+
+#[test]
+fn test_parse_settings() {
+    let settings = Settings::parse(
+        r##"
+sort = "retained"
+pane_widths = [1, 2, 1]
+
+[keys]
+down = ["j", "Ctrl-n"]
+quit = "x"
+
+[theme]
+selected = "magenta"
+matched = "#ff8700"
+treemap = ["blue", "light-cyan"]
+"##,
+        false,
+    )
+    .unwrap();
+
+    assert_eq!(settings.sort_order, SortOrder::RetainedSize);
+    assert_eq!(settings.pane_widths, [1, 2, 1]);
+    assert_eq!(settings.keymap.describe(Action::Down), "j/Ctrl-n");
+    assert_eq!(settings.keymap.describe(Action::Quit), "x");
+    // Taken from export, which is left without a key
+    assert_eq!(settings.keymap.describe(Action::Export), "(none)");
+    assert_eq!(settings.keymap.describe(Action::Up), "k/Up");
+
+    assert!(!settings.theme.monochrome);
+    assert_eq!(settings.theme.selected, Color::Magenta);
+    assert_eq!(settings.theme.matched, Color::Rgb(0xff, 0x87, 0x00));
+    assert_eq!(settings.theme.treemap, [Color::Blue, Color::LightCyan]);
+    assert_eq!(settings.theme.accent, Theme::default().accent);
+
+    for (text, error) in [
+        ("sort = \"size\"", "Unknown sort order"),
+        ("pane_widths = [0, 0, 0]", "can't all be 0"),
+        ("[keys]\njump = \"j\"", "Unknown action 'jump'"),
+        ("[keys]\ndown = \"Pgdn\"", "Invalid key for down"),
+        (
+            "[keys]\ndown = \"x\"\nup = \"x\"",
+            "x is bound to both down and up",
+        ),
+        ("[theme]\nborder = \"red\"", "Unknown colour 'border'"),
+        ("[theme]\naccent = \"reddish\"", "Unknown colour 'reddish'"),
+        ("[theme]\nbase = \"solarized\"", "theme.base"),
+        ("colour = true", "unknown field"),
+    ] {
+        let message = format!("{:#}", Settings::parse(text, false).unwrap_err());
+        assert!(message.contains(error), "{text:?}: {message}");
+    }
+}
+
+#[test]
+fn test_no_color() {
+    assert_eq!(
+        Settings::parse("", true).unwrap().theme,
+        Theme::monochrome()
+    );
+    let settings = Settings::parse("[theme]\nbase = \"default\"", true).unwrap();
+    assert_eq!(settings.theme, Theme::default());
+
+    // The selection stands out without colours
+    let mut app = app();
+    app.apply_settings(&Settings::parse("", true).unwrap());
+    let mut terminal = Terminal::new(TestBackend::new(120, 10)).unwrap();
+    terminal.draw(|f| render_panes(f, &app, f.area())).unwrap();
+    let buffer = terminal.backend().buffer();
+    for cell in &buffer.content {
+        assert_eq!((cell.fg, cell.bg), (Color::Reset, Color::Reset));
+    }
+    let y = lines(buffer)
+        .iter()
+        .position(|l| l.contains("system"))
+        .unwrap();
+    let cell = buffer.cell((50, y as u16)).unwrap();
+    assert!(cell.modifier.contains(Modifier::REVERSED));
+}
+
+#[test]
+fn test_apply_settings() {
+    let mut app = app();
+    let settings = Settings::parse(
+        "sort = \"name\"\n[keys]\ndown = \"Ctrl-n\"\nquit = \"x\"",
+        false,
+    )
+    .unwrap();
+    app.apply_settings(&settings);
+    assert_eq!(app.sort_order, SortOrder::Alphabetical);
+
+    app.handle_key(KeyEvent::from(KeyCode::Char('l'))).unwrap();
+    assert_eq!(current(&app), "bash-5.2");
+    app.handle_key(KeyEvent::from(KeyCode::Char('j'))).unwrap();
+    assert_eq!(current(&app), "bash-5.2");
+    app.handle_key(KeyEvent::new(KeyCode::Char('n'), KeyModifiers::CONTROL))
+        .unwrap();
+    assert_eq!(current(&app), "firefox-125.0");
+
+    // q isn't bound any more
+    assert!(!app.handle_key(KeyEvent::from(KeyCode::Char('q'))).unwrap());
+    assert!(app.handle_key(KeyEvent::from(KeyCode::Char('x'))).unwrap());
+
+    // The help lists the keys in use
+    let mut terminal = Terminal::new(TestBackend::new(120, 50)).unwrap();
+    terminal.draw(|f| render_help(f, &app, f.area())).unwrap();
+    let help = lines(terminal.backend().buffer());
+    assert!(
+        help.iter()
+            .any(|l| l.contains("Ctrl-n") && l.contains("Move down"))
+    );
+    assert!(help.iter().any(|l| l.contains("x ") && l.contains("Quit")));
+    assert!(
+        help.iter()
+            .any(|l| l.contains("(none)") && l.contains("Export"))
+    );
+}
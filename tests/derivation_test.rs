@@ -0,0 +1,178 @@
+use anyhow::Result;
+use nix_tree::backend::{DrvFilesBackend, LoadProgress, load_graph};
+use nix_tree::derivation::Derivation;
+
+const HELLO_DRV: &str = r#"Derive([("out","/nix/store/zzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzz-hello-2.12.1","","")],[("/nix/store/aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa-bash-5.2.drv",["out"]),("/nix/store/bbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbb-hello-2.12.1.tar.gz.drv",["out"]),("/nix/store/cccccccccccccccccccccccccccccccc-stdenv-linux.drv",["dev","out"])],["/nix/store/dddddddddddddddddddddddddddddddd-default-builder.sh"],"x86_64-linux","/nix/store/eeeeeeeeeeeeeeeeeeeeeeeeeeeeeeee-bash-5.2/bin/bash",["-e","/nix/store/dddddddddddddddddddddddddddddddd-default-builder.sh"],[("name","hello-2.12.1"),("out","/nix/store/zzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzz-hello-2.12.1"),("script","echo \"hi\"\n\tdone\\")])"#;
+
+const TARBALL_DRV: &str = r#"Derive([("out","/nix/store/ffffffffffffffffffffffffffffffff-hello-2.12.1.tar.gz","sha256","8d99142afd92576f30b0cd7cb42a8dc6809998bc5d607d88761f512e26c7db20")],[],[],"builtin","builtin:fetchurl",[],[("name","hello-2.12.1.tar.gz"),("outputHash","sha256-jZkUKv2SV28wsM18tCqNxoCZmLxdYH2IdhH1Ei7H2yA="),("outputHashMode","flat")])"#;
+
+#[test]
+fn test_parse_input_addressed_derivation() -> Result<()> {
+    let drv = Derivation::parse(HELLO_DRV)?;
+
+    assert_eq!(drv.system, "x86_64-linux");
+    assert!(drv.builder.ends_with("/bin/bash"));
+    assert_eq!(drv.args.len(), 2);
+
+    let out = &drv.outputs["out"];
+    assert_eq!(
+        out.path.as_deref(),
+        Some("/nix/store/zzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzz-hello-2.12.1")
+    );
+    assert!(!out.is_content_addressed());
+
+    assert_eq!(drv.input_drvs.len(), 3);
+    let stdenv = &drv.input_drvs["/nix/store/cccccccccccccccccccccccccccccccc-stdenv-linux.drv"];
+    assert!(stdenv.contains("dev") && stdenv.contains("out"));
+    assert_eq!(drv.input_srcs.len(), 1);
+
+    // Escapes inside strings are decoded
+    assert_eq!(drv.env["script"], "echo \"hi\"\n\tdone\\");
+    assert!(!drv.has_structured_attrs());
+
+    Ok(())
+}
+
+#[test]
+fn test_parse_content_addressed_outputs() -> Result<()> {
+    let fixed = Derivation::parse(TARBALL_DRV)?;
+    let out = &fixed.outputs["out"];
+    assert!(out.is_fixed_output());
+    assert!(!out.is_recursive());
+    assert_eq!(out.hash_algo.as_deref(), Some("sha256"));
+
+    let floating = Derivation::parse(
+        r#"Derive([("out","","r:sha256","")],[],[],"x86_64-linux","/bin/sh",[],[("__contentAddressed","1"),("name","ca")])"#,
+    )?;
+    let out = &floating.outputs["out"];
+    assert!(out.is_content_addressed());
+    assert!(!out.is_fixed_output());
+    assert!(out.is_recursive());
+    assert_eq!(out.path, None);
+
+    Ok(())
+}
+
+#[test]
+fn test_parse_structured_attrs() -> Result<()> {
+    let drv = Derivation::parse(
+        r#"Derive([("out","/nix/store/gggggggggggggggggggggggggggggggg-sa","","")],[],[],"x86_64-linux","/bin/sh",[],[("__json","{\"name\":\"sa\",\"list\":[1,2]}"),("out","/nix/store/gggggggggggggggggggggggggggggggg-sa")])"#,
+    )?;
+
+    assert!(drv.has_structured_attrs());
+    let attrs = drv
+        .structured_attrs()?
+        .expect("Should have structured attrs");
+    assert_eq!(attrs["name"], "sa");
+    assert_eq!(attrs["list"][1], 2);
+
+    Ok(())
+}
+
+#[test]
+fn test_parse_dynamic_derivation_inputs() -> Result<()> {
+    let drv = Derivation::parse(
+        r#"DrvWithVersion("xp-dyn-drv",[("out","","r:sha256","")],[("/nix/store/hhhhhhhhhhhhhhhhhhhhhhhhhhhhhhhh-dyn.drv",(["out"],[("out",(["bin"],[]))]))],[],"x86_64-linux","/bin/sh",[],[])"#,
+    )?;
+
+    let outputs = &drv.input_drvs["/nix/store/hhhhhhhhhhhhhhhhhhhhhhhhhhhhhhhh-dyn.drv"];
+    assert!(outputs.contains("out"));
+
+    Ok(())
+}
+
+#[test]
+fn test_malformed_derivations() {
+    let err = Derivation::parse("Derive([(\"out\"").unwrap_err();
+    assert!(err.to_string().contains("expected ','"), "{err}");
+
+    let err = Derivation::parse("Derivation([])").unwrap_err();
+    assert!(err.to_string().contains("expected 'Derive('"), "{err}");
+
+    let err = Derivation::parse(&format!("{TARBALL_DRV}garbage")).unwrap_err();
+    assert!(err.to_string().contains("trailing data"), "{err}");
+
+    let err = Derivation::parse(r#"Derive([],[],["relative"],"","",[],[])"#).unwrap_err();
+    assert!(err.to_string().contains("expected a store path"), "{err}");
+
+    let err = Derivation::parse(r#"Derive([("out","/nix/store/x"#).unwrap_err();
+    assert!(err.to_string().contains("unterminated string"), "{err}");
+}
+
+/// A directory of copied `.drv` files, returning the root's store path
+fn drv_directory(dir: &std::path::Path) -> Result<String> {
+    std::fs::write(
+        dir.join("bbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbb-hello-2.12.1.tar.gz.drv"),
+        TARBALL_DRV,
+    )?;
+    std::fs::write(
+        dir.join("iiiiiiiiiiiiiiiiiiiiiiiiiiiiiiii-hello.drv"),
+        r#"Derive([("out","/nix/store/zzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzz-hello","","")],[("/nix/store/bbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbb-hello-2.12.1.tar.gz.drv",["out"])],["/nix/store/dddddddddddddddddddddddddddddddd-default-builder.sh"],"x86_64-linux","/bin/sh",[],[])"#,
+    )?;
+    std::fs::write(
+        dir.join("dddddddddddddddddddddddddddddddd-default-builder.sh"),
+        "genericBuild\n",
+    )?;
+    Ok("/nix/store/iiiiiiiiiiiiiiiiiiiiiiiiiiiiiiii-hello.drv".to_string())
+}
+
+#[test]
+fn test_load_graph_from_drv_directory() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let root = drv_directory(dir.path())?;
+    let graph = nix_tree::derivation::load_graph(std::slice::from_ref(&root), dir.path())?;
+
+    assert_eq!(graph.len(), 3);
+    assert_eq!(graph.roots, vec![root.clone()]);
+    assert_eq!(graph.get_references(&root).len(), 2);
+
+    let builder = graph
+        .get_path("/nix/store/dddddddddddddddddddddddddddddddd-default-builder.sh")
+        .expect("Should include input sources");
+    assert_eq!(builder.nar_size, 13);
+
+    let stats = nix_tree::path_stats::calculate_stats(&graph);
+    assert!(stats[&root].closure_size > 13);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_drv_files_backend() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let root = drv_directory(dir.path())?;
+    let file = dir
+        .path()
+        .join("iiiiiiiiiiiiiiiiiiiiiiiiiiiiiiii-hello.drv");
+    let installables = vec![file.to_string_lossy().to_string()];
+
+    let backend = DrvFilesBackend::from_installables(&installables).unwrap();
+    let graph = load_graph(&backend, &installables, &LoadProgress::default()).await?;
+    assert_eq!(graph.roots, vec![root]);
+    assert_eq!(graph.len(), 3);
+
+    // Anything else is left to nix
+    let builder = dir
+        .path()
+        .join("dddddddddddddddddddddddddddddddd-default-builder.sh");
+    for installables in [
+        vec![builder.to_string_lossy().to_string()],
+        vec!["nixpkgs#hello".to_string()],
+        vec![],
+    ] {
+        assert!(DrvFilesBackend::from_installables(&installables).is_none());
+    }
+
+    // `-d` reads them without nix
+    let output = std::process::Command::new(env!("CARGO_BIN_EXE_nix-tree"))
+        .args(["-d", "--filter", "name=*builder*"])
+        .arg(&file)
+        .output()?;
+    assert!(output.status.success(), "{output:?}");
+    assert_eq!(
+        String::from_utf8(output.stdout)?,
+        "/nix/store/dddddddddddddddddddddddddddddddd-default-builder.sh\n"
+    );
+
+    Ok(())
+}
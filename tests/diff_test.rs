@@ -0,0 +1,104 @@
+mod common;
+
+use common::store_path;
+use nix_tree::diff::{ChangeKind, ClosureDiff};
+use nix_tree::store_path::{StorePath, StorePathGraph};
+use nix_tree::ui::App;
+use nix_tree::ui::app::Highlight;
+
+fn graph(paths: Vec<StorePath>) -> StorePathGraph {
+    let root = paths[0].path.clone();
+    StorePathGraph::from_paths(paths, vec![root])
+}
+
+#[test]
+fn test_closure_diff() {
+    let openssl = store_path('b', "openssl-3.0.1", 1000, &[]);
+    let python = store_path('c', "python3-3.12.1", 5000, &[]);
+    let perl = store_path('d', "perl-5.38", 2000, &[]);
+    let system = store_path('a', "system-1", 100, &[&openssl, &python, &perl]);
+    let old = graph(vec![system, openssl, python, perl]);
+
+    let openssl = store_path('f', "openssl-3.0.2", 1500, &[]);
+    let python = store_path('g', "python3-3.11.9", 4000, &[]);
+    let zstd = store_path('h', "zstd-1.5", 300, &[]);
+    let system = store_path('e', "system-1", 100, &[&openssl, &python, &zstd]);
+    let new = graph(vec![system, openssl, python, zstd]);
+
+    let diff = ClosureDiff::compute(&old, &new);
+    let kinds: Vec<(&str, ChangeKind)> = diff
+        .changes
+        .iter()
+        .map(|c| (c.name.as_str(), c.kind))
+        .collect();
+    assert_eq!(
+        kinds,
+        vec![
+            ("openssl", ChangeKind::Upgraded),
+            ("python3", ChangeKind::Downgraded),
+            ("zstd", ChangeKind::Added),
+            ("perl", ChangeKind::Removed),
+        ]
+    );
+    assert_eq!(diff.changes[0].size_delta(), 500);
+    assert_eq!(diff.changes[3].size_delta(), -2000);
+    assert_eq!(diff.old_closure_size, 8100);
+    assert_eq!(diff.new_closure_size, 5900);
+
+    let text = diff.render_text();
+    assert!(text.contains("Upgraded (1):\n  openssl  3.0.1 → 3.0.2    +500 B\n"));
+    assert!(text.contains("Removed (1):\n  perl     5.38             -2.0 KiB\n"));
+    assert!(text.contains("Closure size: 7.9 KiB → 5.8 KiB (-2.1 KiB)"));
+
+    let json = serde_json::to_value(&diff).unwrap();
+    assert_eq!(json["changes"][0]["kind"], "upgraded");
+    assert_eq!(json["changes"][0]["new_versions"][0], "3.0.2");
+
+    // The TUI shows both closures, marking the paths only in one of them
+    let app = App::from_diff(&old, &new);
+    assert_eq!(app.graph.roots.len(), 2);
+    assert_eq!(app.graph.len(), 8);
+    assert_eq!(app.highlights.get(&new.roots[0]), Some(&Highlight::Added));
+    assert_eq!(
+        app.highlights.get(&old.paths()[3].path),
+        Some(&Highlight::Removed)
+    );
+}
+
+#[test]
+fn test_diff_command() {
+    // Both closures come from the selected backend, here a single dump
+    let old_zlib = store_path('b', "zlib-1.3", 100, &[]);
+    let old = store_path('a', "system-1", 10, &[&old_zlib]);
+    let new_zlib = store_path('d', "zlib-1.3.1", 120, &[]);
+    let new = store_path('c', "system-1", 10, &[&new_zlib]);
+    let dump: serde_json::Map<String, serde_json::Value> = [&old_zlib, &old, &new_zlib, &new]
+        .into_iter()
+        .map(|p| {
+            (
+                p.path.clone(),
+                serde_json::json!({ "narSize": p.nar_size, "references": p.references }),
+            )
+        })
+        .collect();
+    let dir = tempfile::tempdir().unwrap();
+    let closure = dir.path().join("closure.json");
+    std::fs::write(&closure, serde_json::to_string(&dump).unwrap()).unwrap();
+
+    let output = std::process::Command::new(env!("CARGO_BIN_EXE_nix-tree"))
+        .args(["diff", "--from-json"])
+        .arg(&closure)
+        .args([&old.path, &new.path])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(
+        stdout.contains("Upgraded (1):\n  zlib  1.3 → 1.3.1  +20 B\n"),
+        "{stdout}"
+    );
+    assert!(
+        stdout.contains("Closure size: 110 B → 130 B (+20 B)"),
+        "{stdout}"
+    );
+}
@@ -0,0 +1,118 @@
+mod common;
+
+use common::store_path;
+use crossterm::event::{KeyCode, KeyEvent};
+use nix_tree::duplicates::{find_duplicates, render_text};
+use nix_tree::path_stats;
+use nix_tree::store_path::StorePathGraph;
+use nix_tree::ui::App;
+use std::process::Command;
+
+/// system -> curl, git -> openssl-3.3.1 and system -> python -> openssl-3.0.13,
+/// which alone needs zlib-1.2
+fn graph() -> StorePathGraph {
+    let zlib = store_path('z', "zlib-1.2", 50, &[]);
+    let old = store_path('o', "openssl-3.0.13", 1000, &[&zlib]);
+    let new = store_path('n', "openssl-3.3.1", 1200, &[]);
+    let new_dev = store_path('d', "openssl-3.3.1-dev", 300, &[&new]);
+    let curl = store_path('c', "curl-8.7.1", 400, &[&new, &new_dev]);
+    let git = store_path('g', "git-2.44.0", 900, &[&new]);
+    let python = store_path('p', "python3-3.11.9", 5000, &[&old]);
+    let system = store_path('s', "system", 10, &[&curl, &git, &python]);
+
+    let root = system.path.clone();
+    StorePathGraph::from_paths(
+        vec![zlib, old, new, new_dev, curl, git, python, system],
+        vec![root],
+    )
+}
+
+#[test]
+fn test_find_duplicates() {
+    let graph = graph();
+    let duplicates = find_duplicates(&graph);
+
+    assert_eq!(duplicates.len(), 1);
+    let openssl = &duplicates[0];
+    assert_eq!(openssl.pname, "openssl");
+    assert_eq!(openssl.versions[0].version, "3.3.1");
+    assert_eq!(openssl.versions[0].paths.len(), 2);
+    assert_eq!(openssl.versions[0].referrers, 2);
+    assert_eq!(openssl.versions[0].extra_size, 0);
+
+    let odd = &openssl.versions[1];
+    assert_eq!(odd.version, "3.0.13");
+    assert_eq!(odd.extra_size, 1050);
+    assert_eq!(openssl.extra_size(), 1050);
+    assert_eq!(odd.chains.len(), 1);
+    assert_eq!(odd.chains[0].len(), 3);
+    assert!(odd.chains[0][1].ends_with("python3-3.11.9"));
+
+    let text = render_text(&duplicates, &graph);
+    assert!(text.contains("openssl (2 versions, +1.0 KiB)"));
+    assert!(text.contains("  3.3.1: 2 referrers (kept)"));
+    assert!(text.contains("    system → python3-3.11.9 → openssl-3.0.13"));
+}
+
+#[test]
+fn test_unversioned_paths_are_not_duplicates() {
+    // A source tarball or a wrapper shares the pname but isn't a version
+    let hello = store_path('h', "hello-2.12", 100, &[]);
+    let wrapper = store_path('w', "hello", 10, &[&hello]);
+    let system = store_path('s', "system", 10, &[&wrapper]);
+    let root = system.path.clone();
+    let wrapped = StorePathGraph::from_paths(vec![hello, wrapper, system], vec![root]);
+    assert!(find_duplicates(&wrapped).is_empty());
+
+    // Nor do they show up next to versions that are duplicated
+    let mut paths = graph().paths().to_vec();
+    paths.push(store_path('u', "openssl", 10, &[]));
+    let graph = StorePathGraph::from_paths(paths, graph().roots.clone());
+    let duplicates = find_duplicates(&graph);
+    assert_eq!(duplicates.len(), 1);
+    assert_eq!(duplicates[0].versions.len(), 2);
+    assert!(duplicates[0].versions.iter().all(|v| !v.version.is_empty()));
+}
+
+#[test]
+fn test_duplicates_filter() {
+    let graph = graph();
+    let stats = path_stats::calculate_stats(&graph);
+    let mut app = App::new(graph, stats);
+    let before = app.current_items.clone();
+
+    app.handle_key(KeyEvent::from(KeyCode::Char('d'))).unwrap();
+    assert_eq!(app.current_items.len(), 3);
+    assert!(app.current_items.iter().all(|p| p.contains("openssl")));
+
+    app.handle_key(KeyEvent::from(KeyCode::Left)).unwrap();
+    assert_eq!(app.current_items, before);
+}
+
+#[test]
+fn test_duplicates_command() {
+    let dump: serde_json::Map<String, serde_json::Value> = graph()
+        .paths()
+        .iter()
+        .map(|p| {
+            (
+                p.path.clone(),
+                serde_json::json!({ "narSize": p.nar_size, "references": p.references }),
+            )
+        })
+        .collect();
+    let dir = tempfile::tempdir().unwrap();
+    let file = dir.path().join("closure.json");
+    std::fs::write(&file, serde_json::to_string(&dump).unwrap()).unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_nix-tree"))
+        .args(["duplicates", "--json", "--from-json"])
+        .arg(&file)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(report[0]["pname"], "openssl");
+    assert_eq!(report[0]["versions"][1]["extra_size"], 1050);
+}
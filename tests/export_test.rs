@@ -0,0 +1,167 @@
+mod common;
+
+use common::store_path;
+use crossterm::event::{KeyCode, KeyEvent};
+use nix_tree::export::{DotOptions, Prune, render_dot};
+use nix_tree::path_stats;
+use nix_tree::store_path::StorePathGraph;
+use nix_tree::ui::App;
+use std::process::Command;
+
+/// system -> {app, lib, glibc}, app -> {lib, glibc}, lib -> glibc
+fn graph() -> StorePathGraph {
+    let glibc = store_path('g', "glibc-2.39", 30_000_000, &[]);
+    let lib = store_path('l', "libfoo-1.0", 1000, &[&glibc]);
+    let app = store_path('a', "app \"quoted\"", 5000, &[&lib, &glibc]);
+    let system = store_path('s', "system", 10, &[&app, &lib, &glibc]);
+
+    let root = system.path.clone();
+    StorePathGraph::from_paths(vec![glibc, lib, app, system], vec![root])
+}
+
+fn export(graph: &StorePathGraph, options: &DotOptions) -> String {
+    let stats = path_stats::calculate_stats(graph);
+    render_dot(graph, &stats, &graph.root_ids(), options)
+}
+
+fn edge_count(dot: &str) -> usize {
+    dot.lines().filter(|l| l.contains(" -> ")).count()
+}
+
+#[test]
+fn test_dot_nodes() {
+    let graph = graph();
+    let dot = export(&graph, &DotOptions::default());
+
+    assert!(dot.starts_with("digraph \"nix-tree\" {\n"));
+    assert!(dot.ends_with("}\n"));
+    assert_eq!(dot.lines().filter(|l| l.contains("[label=")).count(), 4);
+    assert_eq!(edge_count(&dot), 6);
+
+    let glibc = dot.lines().find(|l| l.contains("glibc-2.39\\n")).unwrap();
+    assert!(glibc.contains("glibc-2.39\\n28.6 MiB / 28.6 MiB"));
+    // The largest path is the reddest
+    assert!(glibc.contains("fillcolor=\"0.000 0.750 1.000\""));
+
+    let app = dot.lines().find(|l| l.contains("quoted")).unwrap();
+    assert!(app.contains("label=\"app \\\"quoted\\\"\\n"));
+
+    let system = dot.lines().find(|l| l.contains("-system\"")).unwrap();
+    assert!(system.contains("penwidth=2"));
+}
+
+#[test]
+fn test_dot_pruning() {
+    let graph = graph();
+
+    let transitive = export(
+        &graph,
+        &DotOptions {
+            prune: Prune::Transitive,
+            ..Default::default()
+        },
+    );
+    // Only system -> app -> lib -> glibc is left
+    assert_eq!(edge_count(&transitive), 3);
+
+    let tree = export(
+        &graph,
+        &DotOptions {
+            prune: Prune::Tree,
+            ..Default::default()
+        },
+    );
+    // Everything hangs off the system directly
+    assert_eq!(edge_count(&tree), 3);
+    let system = graph.id(&graph.roots[0]).unwrap();
+    assert!(
+        tree.lines()
+            .filter(|l| l.contains(" -> "))
+            .all(|l| l.starts_with(&format!("  n{system} -> ")))
+    );
+}
+
+#[test]
+fn test_dot_depth() {
+    let graph = graph();
+    let mut options = DotOptions {
+        max_depth: Some(0),
+        ..Default::default()
+    };
+
+    let dot = export(&graph, &options);
+    assert_eq!(dot.lines().filter(|l| l.contains("[label=")).count(), 1);
+    assert_eq!(edge_count(&dot), 0);
+    assert!(dot.contains("style=\"rounded,filled,dashed\""));
+
+    options.max_depth = Some(1);
+    let dot = export(&graph, &options);
+    assert_eq!(dot.lines().filter(|l| l.contains("[label=")).count(), 4);
+    // References between paths at the limit are still drawn, and glibc has
+    // none to cut off
+    assert_eq!(edge_count(&dot), 6);
+    assert_eq!(dot.matches("dashed").count(), 2);
+}
+
+#[test]
+fn test_export_selected_closure() {
+    let graph = graph();
+    let stats = path_stats::calculate_stats(&graph);
+    let mut app = App::new(graph, stats);
+    let dir = tempfile::tempdir().unwrap();
+    std::env::set_current_dir(dir.path()).unwrap();
+
+    // Go to libfoo, the second largest dependency of the system
+    app.handle_key(KeyEvent::from(KeyCode::Right)).unwrap();
+    let lib = app
+        .current_items
+        .iter()
+        .position(|p| p.ends_with("libfoo-1.0"))
+        .unwrap();
+    app.current_state.select(Some(lib));
+    app.handle_key(KeyEvent::from(KeyCode::Enter)).unwrap();
+
+    app.handle_key(KeyEvent::from(KeyCode::Char('x'))).unwrap();
+    assert_eq!(app.message.as_deref(), Some("Wrote libfoo-1.0.dot"));
+    let dot = std::fs::read_to_string(dir.path().join("libfoo-1.0.dot")).unwrap();
+    assert_eq!(dot.lines().filter(|l| l.contains("[label=")).count(), 2);
+    assert_eq!(edge_count(&dot), 1);
+
+    // The message goes away on the next key
+    app.handle_key(KeyEvent::from(KeyCode::Char('j'))).unwrap();
+    assert!(app.message.is_none());
+}
+
+#[test]
+fn test_export_command() {
+    let dump: serde_json::Map<String, serde_json::Value> = graph()
+        .paths()
+        .iter()
+        .map(|p| {
+            (
+                p.path.clone(),
+                serde_json::json!({ "narSize": p.nar_size, "references": p.references }),
+            )
+        })
+        .collect();
+    let dir = tempfile::tempdir().unwrap();
+    let file = dir.path().join("closure.json");
+    std::fs::write(&file, serde_json::to_string(&dump).unwrap()).unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_nix-tree"))
+        .args(["--export", "dot", "--prune=transitive", "--from-json"])
+        .arg(&file)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let dot = String::from_utf8(output.stdout).unwrap();
+    assert!(dot.starts_with("digraph"));
+    assert_eq!(edge_count(&dot), 3);
+
+    let output = Command::new(env!("CARGO_BIN_EXE_nix-tree"))
+        .args(["--export", "svg", "--from-json"])
+        .arg(&file)
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+}
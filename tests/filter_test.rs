@@ -0,0 +1,274 @@
+mod common;
+
+use common::store_path;
+use crossterm::event::{KeyCode, KeyEvent};
+use nix_tree::filter::{Filter, FilterContext, parse_size};
+use nix_tree::path_stats;
+use nix_tree::store_path::{StorePath, StorePathGraph};
+use nix_tree::ui::App;
+use std::process::Command;
+
+/// system -> firefox -> {glibc, zlib-dev -> zlib}, system -> python -> glibc
+fn graph() -> StorePathGraph {
+    let glibc = StorePath {
+        signatures: vec!["cache.nixos.org-1:abc".to_string()],
+        ..store_path('g', "glibc-2.39", 30_000_000, &[])
+    };
+    let zlib = store_path('z', "zlib-1.3", 100_000, &[]);
+    let zlib_dev = store_path('d', "zlib-1.3-dev", 50_000, &[&zlib]);
+    let firefox = store_path('f', "firefox-125.0", 200_000_000, &[&glibc, &zlib_dev]);
+    let python = store_path('p', "python3-3.11.9", 80_000_000, &[&glibc]);
+    let system = store_path('s', "system", 1000, &[&firefox, &python]);
+
+    let root = system.path.clone();
+    StorePathGraph::from_paths(
+        vec![glibc, zlib, zlib_dev, firefox, python, system],
+        vec![root],
+    )
+}
+
+/// Short names of the paths matching `query`, sorted
+fn matching(graph: &StorePathGraph, query: &str) -> Vec<String> {
+    let stats = path_stats::calculate_stats(graph);
+    let depths = path_stats::root_depths(graph);
+    let ctx = FilterContext {
+        graph,
+        stats: &stats,
+        depths: &depths,
+    };
+    let filter = Filter::parse(query).unwrap();
+
+    let mut names: Vec<String> = graph
+        .paths()
+        .iter()
+        .filter(|p| filter.matches(&ctx, &p.path))
+        .map(|p| p.name.clone())
+        .collect();
+    names.sort();
+    names
+}
+
+#[test]
+fn test_name_predicates() {
+    let graph = graph();
+
+    assert_eq!(matching(&graph, "FIRE"), ["firefox-125.0"]);
+    assert_eq!(matching(&graph, "*-dev"), ["zlib-1.3-dev"]);
+    assert_eq!(
+        matching(&graph, "name=zlib-*"),
+        ["zlib-1.3", "zlib-1.3-dev"]
+    );
+    assert_eq!(matching(&graph, "name!=*-*").len(), 1);
+    assert_eq!(
+        matching(&graph, r"name~'^python3\.?\d*-'"),
+        ["python3-3.11.9"]
+    );
+
+    // Names made unique with part of the hash match as they are in the store
+    let old = store_path('a', "zlib-1.3", 100, &[]);
+    let new = store_path('b', "zlib-1.3", 100, &[]);
+    let root = store_path('s', "system", 10, &[&old, &new]);
+    let roots = vec![root.path.clone()];
+    let graph = StorePathGraph::from_paths(vec![old, new, root], roots);
+    for query in ["zlib", "name=zlib-*", "name~^zlib-1"] {
+        assert_eq!(matching(&graph, query).len(), 2, "{query}");
+    }
+    assert!(matching(&graph, "aaaa").is_empty());
+}
+
+#[test]
+fn test_numeric_predicates() {
+    let graph = graph();
+
+    assert_eq!(
+        matching(&graph, "nar>=80MB"),
+        ["firefox-125.0", "python3-3.11.9"]
+    );
+    assert_eq!(
+        matching(&graph, "closure<150K"),
+        ["zlib-1.3", "zlib-1.3-dev"]
+    );
+    // firefox retains zlib, but glibc is shared with python
+    assert_eq!(
+        matching(&graph, "retained>200MB"),
+        ["firefox-125.0", "system"]
+    );
+    assert_eq!(
+        matching(&graph, "retained>=80MB and retained<100MB"),
+        ["python3-3.11.9"]
+    );
+    assert_eq!(matching(&graph, "refs=0"), ["glibc-2.39", "zlib-1.3"]);
+    assert_eq!(matching(&graph, "referrers>1"), ["glibc-2.39"]);
+    assert_eq!(matching(&graph, "depth=2"), ["glibc-2.39", "zlib-1.3-dev"]);
+    assert_eq!(matching(&graph, "signed"), ["glibc-2.39"]);
+    assert_eq!(matching(&graph, "unsigned and depth<=1").len(), 3);
+}
+
+#[test]
+fn test_added_size() {
+    // Two roots sharing glibc, which neither adds on its own
+    let glibc = store_path('g', "glibc-2.39", 30_000_000, &[]);
+    let firefox = store_path('f', "firefox-125.0", 200_000_000, &[&glibc]);
+    let python = store_path('p', "python3-3.11.9", 80_000_000, &[&glibc]);
+    let roots = vec![firefox.path.clone(), python.path.clone()];
+    let graph = StorePathGraph::from_paths(vec![glibc, firefox, python], roots);
+
+    let stats = path_stats::calculate_stats(&graph);
+    let ids = graph.root_ids();
+    assert_eq!(
+        path_stats::added_sizes(&graph, &ids),
+        [200_000_000, 80_000_000]
+    );
+    assert_eq!(stats[&graph.roots[0]].added_size, Some(200_000_000));
+
+    assert_eq!(matching(&graph, "added>100MB"), ["firefox-125.0"]);
+    assert_eq!(matching(&graph, "added<100MB"), ["python3-3.11.9"]);
+    // Only the roots have one
+    assert_eq!(matching(&graph, "added>=0").len(), 2);
+}
+
+#[test]
+fn test_boolean_operators() {
+    let graph = graph();
+
+    // and binds tighter than or
+    assert_eq!(
+        matching(&graph, "zlib and dev or python"),
+        ["python3-3.11.9", "zlib-1.3-dev"]
+    );
+    assert_eq!(matching(&graph, "zlib (dev or python)"), ["zlib-1.3-dev"]);
+    assert_eq!(matching(&graph, "!zlib not depth>1").len(), 3);
+    assert_eq!(
+        matching(&graph, "not (zlib or glibc) and nar<1GB"),
+        ["firefox-125.0", "python3-3.11.9", "system"]
+    );
+    assert_eq!(matching(&graph, "\"firefox-125\" AND signed").len(), 0);
+}
+
+#[test]
+fn test_parse_errors() {
+    for query in [
+        "",
+        "(zlib",
+        "zlib)",
+        "and zlib",
+        "zlib or",
+        "'zlib",
+        "size>lots",
+        "colour=red",
+        "name<a",
+        "nar~1",
+        "name~(",
+    ] {
+        assert!(Filter::parse(query).is_err(), "{query:?} should not parse");
+    }
+}
+
+#[test]
+fn test_parse_size() {
+    assert_eq!(parse_size("512").unwrap(), 512);
+    assert_eq!(parse_size("10KB").unwrap(), 10_000);
+    assert_eq!(parse_size("10k").unwrap(), 10_240);
+    assert_eq!(parse_size("1.5MiB").unwrap(), 1_572_864);
+    assert_eq!(parse_size("2G").unwrap(), 2 << 30);
+    assert_eq!(parse_size("1tb").unwrap(), 1_000_000_000_000);
+    assert!(parse_size("5 parsecs").is_err());
+}
+
+fn search(app: &mut App, query: &str) {
+    app.handle_key(KeyEvent::from(KeyCode::Char('/'))).unwrap();
+    while !app.search_query.is_empty() {
+        app.handle_key(KeyEvent::from(KeyCode::Backspace)).unwrap();
+    }
+    for c in query.chars() {
+        app.handle_key(KeyEvent::from(KeyCode::Char(c))).unwrap();
+    }
+    app.handle_key(KeyEvent::from(KeyCode::Enter)).unwrap();
+}
+
+#[test]
+fn test_search_filters_panes() {
+    let graph = graph();
+    let stats = path_stats::calculate_stats(&graph);
+    let mut app = App::new(graph, stats);
+
+    search(&mut app, "depth>=1 and nar>10MB");
+    assert!(!app.searching);
+    assert_eq!(app.current_items.len(), 3);
+    // Sorted by closure size, so firefox comes first
+    assert!(app.current_items[0].ends_with("firefox-125.0"));
+    // zlib-dev is filtered out of firefox's dependencies, and the system
+    // out of its referrers
+    assert_eq!(app.next_items.len(), 1);
+    assert!(app.next_items[0].ends_with("glibc-2.39"));
+    assert!(app.previous_items.is_empty());
+
+    // The search box starts from the active filter
+    app.handle_key(KeyEvent::from(KeyCode::Char('/'))).unwrap();
+    assert_eq!(app.search_query, "depth>=1 and nar>10MB");
+    app.handle_key(KeyEvent::from(KeyCode::Esc)).unwrap();
+
+    // An empty search clears the filter
+    search(&mut app, "");
+    assert!(app.filter.is_none());
+    assert_eq!(app.next_items.len(), 2);
+    assert_eq!(app.previous_items.len(), 1);
+}
+
+#[test]
+fn test_search_errors() {
+    let graph = graph();
+    let stats = path_stats::calculate_stats(&graph);
+    let mut app = App::new(graph, stats);
+    let before = app.current_items.clone();
+
+    search(&mut app, "closure>");
+    assert!(app.searching);
+    assert!(app.search_error.is_some());
+    assert_eq!(app.current_items, before);
+
+    // Editing the query clears the error
+    app.handle_key(KeyEvent::from(KeyCode::Backspace)).unwrap();
+    assert!(app.search_error.is_none());
+    app.handle_key(KeyEvent::from(KeyCode::Esc)).unwrap();
+
+    search(&mut app, "nothing-is-called-this");
+    assert_eq!(app.search_error.as_deref(), Some("No paths match"));
+    assert!(app.filter.is_none());
+}
+
+#[test]
+fn test_filter_command() {
+    let dump: serde_json::Map<String, serde_json::Value> = graph()
+        .paths()
+        .iter()
+        .map(|p| {
+            (
+                p.path.clone(),
+                serde_json::json!({ "narSize": p.nar_size, "references": p.references }),
+            )
+        })
+        .collect();
+    let dir = tempfile::tempdir().unwrap();
+    let file = dir.path().join("closure.json");
+    std::fs::write(&file, serde_json::to_string(&dump).unwrap()).unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_nix-tree"))
+        .args(["--filter", "zlib or python", "--from-json"])
+        .arg(&file)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let lines: Vec<&str> = stdout.lines().collect();
+    assert_eq!(lines.len(), 3);
+    assert!(lines[0].ends_with("python3-3.11.9"));
+    assert!(lines.iter().all(|l| l.starts_with("/nix/store/")));
+
+    let output = Command::new(env!("CARGO_BIN_EXE_nix-tree"))
+        .args(["--filter=closure>>1", "--from-json"])
+        .arg(&file)
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+}
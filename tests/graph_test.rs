@@ -0,0 +1,164 @@
+use nix_tree::path_stats;
+use nix_tree::store_path::{StorePath, StorePathGraph};
+use std::time::{Duration, Instant};
+
+fn store_path(n: usize, references: &[usize]) -> StorePath {
+    let hash = format!("{n:032}");
+    StorePath {
+        path: format!("/nix/store/{hash}-p{n}"),
+        hash,
+        name: format!("p{n}"),
+        nar_size: 10,
+        references: references
+            .iter()
+            .map(|r| format!("/nix/store/{r:032}-p{r}"))
+            .collect(),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn test_adjacency() {
+    // References to paths added later are linked once they appear, and
+    // self-references and duplicates are dropped
+    let graph = StorePathGraph::from_paths(
+        vec![
+            store_path(0, &[0, 1, 2, 1]),
+            store_path(1, &[2]),
+            store_path(2, &[2]),
+        ],
+        vec![store_path(0, &[]).path],
+    );
+
+    let p = |n: usize| store_path(n, &[]).path;
+    let root = graph.id(&p(0)).unwrap();
+    let leaf = graph.id(&p(2)).unwrap();
+
+    assert_eq!(graph.len(), 3);
+    assert_eq!(graph.root_ids(), vec![root]);
+    assert_eq!(graph.reference_ids(root).len(), 2);
+    assert!(graph.reference_ids(leaf).is_empty());
+    assert_eq!(graph.referrer_ids(leaf).len(), 2);
+    assert!(graph.referrer_ids(root).is_empty());
+    assert_eq!(graph.get_referrers(&p(1))[0].path, p(0));
+    assert!(graph.get_path("/nix/store/missing").is_none());
+
+    let stats = path_stats::calculate_stats(&graph);
+    assert_eq!(stats[&p(0)].closure_size, 30);
+    assert_eq!(stats[&p(1)].closure_size, 20);
+    assert_eq!(stats[&p(2)].immediate_parents.len(), 2);
+}
+
+#[test]
+fn test_large_graph_stats() {
+    // 100k paths in chains of 100, each referring to the two paths below it
+    // and a shared path at the bottom
+    let paths: Vec<StorePath> = (0..100_000)
+        .map(|n| match n % 100 {
+            0 => store_path(n, &[]),
+            1 => store_path(n, &[n - 1, 0]),
+            _ => store_path(n, &[n - 1, n - 2, 0]),
+        })
+        .collect();
+
+    let start = Instant::now();
+    let graph = StorePathGraph::from_paths(paths, vec![store_path(99, &[]).path]);
+    let stats = path_stats::calculate_stats(&graph);
+    let elapsed = start.elapsed();
+
+    assert_eq!(stats.len(), 100_000);
+    assert_eq!(stats[&graph.roots[0]].closure_size, 1000);
+    assert_eq!(stats[&graph.roots[0]].retained_size, 990);
+    assert_eq!(
+        stats[&store_path(0, &[]).path].immediate_parents.len(),
+        99_000
+    );
+    assert!(elapsed < Duration::from_secs(30), "took {elapsed:?}");
+}
+
+#[test]
+fn test_large_graph_closure_sizes() {
+    // One chain of 50k paths without closure sizes from nix, each referring
+    // to the one below it and the bottom one, so walking every path's
+    // closure would take quadratic time
+    let paths: Vec<StorePath> = (0..50_000)
+        .map(|n| match n {
+            0 => store_path(n, &[]),
+            _ => store_path(n, &[n - 1, 0]),
+        })
+        .collect();
+    assert!(paths.iter().all(|p| p.closure_size.is_none()));
+
+    let start = Instant::now();
+    let graph = StorePathGraph::from_paths(paths, vec![store_path(49_999, &[]).path]);
+    let stats = path_stats::calculate_stats(&graph);
+    let elapsed = start.elapsed();
+
+    for n in [0, 1, 2, 25_000, 49_999] {
+        assert_eq!(
+            stats[&store_path(n, &[]).path].closure_size,
+            10 * (n as u64 + 1)
+        );
+    }
+    assert!(elapsed < Duration::from_secs(10), "took {elapsed:?}");
+}
+
+#[test]
+fn test_retained_size() {
+    // 0 -> 1, 2; 1 -> 3; 2 -> 3; 3 -> 4; 5 -> 4
+    // with 0 and 5 as roots, 4 is shared between them and 3 between 1 and 2
+    let graph = StorePathGraph::from_paths(
+        vec![
+            store_path(0, &[1, 2]),
+            store_path(1, &[3]),
+            store_path(2, &[3]),
+            store_path(3, &[4]),
+            store_path(4, &[]),
+            store_path(5, &[4]),
+        ],
+        vec![store_path(0, &[]).path, store_path(5, &[]).path],
+    );
+    let id = |n: usize| graph.id(&store_path(n, &[]).path).unwrap();
+
+    let idom = path_stats::immediate_dominators(&graph);
+    assert_eq!(idom[id(0)], None);
+    assert_eq!(idom[id(1)], Some(id(0)));
+    assert_eq!(idom[id(3)], Some(id(0)));
+    assert_eq!(idom[id(4)], None);
+
+    let stats = path_stats::calculate_stats(&graph);
+    let retained = |n: usize| stats[&store_path(n, &[]).path].retained_size;
+    assert_eq!(retained(0), 40);
+    assert_eq!(retained(1), 10);
+    assert_eq!(retained(3), 10);
+    assert_eq!(retained(4), 10);
+    assert_eq!(retained(5), 10);
+}
+
+#[test]
+fn test_retained_size_cycle() {
+    // 0 is the only root; 1 <-> 2 is a cycle nothing else refers to, with 2
+    // keeping 3 alive
+    let graph = StorePathGraph::from_paths(
+        vec![
+            store_path(0, &[3]),
+            store_path(1, &[2]),
+            store_path(2, &[1, 3, 4]),
+            store_path(3, &[]),
+            store_path(4, &[]),
+        ],
+        vec![store_path(0, &[]).path],
+    );
+
+    let stats = path_stats::calculate_stats(&graph);
+    let retained = |n: usize| stats[&store_path(n, &[]).path].retained_size;
+    assert_eq!(retained(0), 10);
+    assert_eq!(retained(1), 30);
+    assert_eq!(retained(2), 20);
+    assert_eq!(retained(3), 10);
+
+    let closure = |n: usize| stats[&store_path(n, &[]).path].closure_size;
+    assert_eq!(closure(0), 20);
+    assert_eq!(closure(1), 40);
+    assert_eq!(closure(2), 40);
+}
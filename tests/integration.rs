@@ -29,7 +29,7 @@ async fn test_parse_hello_derivation() -> Result<()> {
     let paths = vec![drv_path];
     let graph = nix_tree::nix::query_path_info(&paths, true, None, &[], None).await?;
 
-    assert!(!graph.paths.is_empty());
+    assert!(!graph.is_empty());
 
     let hello_drv = graph
         .get_path(&paths[0])
@@ -44,3 +44,44 @@ async fn test_parse_hello_derivation() -> Result<()> {
 
     Ok(())
 }
+
+#[tokio::test]
+async fn test_derivation_graph() -> Result<()> {
+    // Needs nix and a nixpkgs channel, which not every machine running the
+    // tests has
+    let Ok(output) = Command::new("nix-instantiate")
+        .arg("<nixpkgs>")
+        .arg("-A")
+        .arg("hello")
+        .output()
+    else {
+        eprintln!("Skipping: nix-instantiate is not available");
+        return Ok(());
+    };
+
+    if !output.status.success() {
+        eprintln!(
+            "Skipping: nix-instantiate failed: {}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+        return Ok(());
+    }
+
+    let drv_path = String::from_utf8(output.stdout)?.trim().to_string();
+
+    let paths = vec![drv_path];
+    let graph = nix_tree::nix::query_derivation_info(&paths, None, &[], None).await?;
+
+    assert_eq!(graph.roots, paths);
+
+    // Every reference of the root is either an input derivation or an input source
+    let hello_drv = graph
+        .get_path(&paths[0])
+        .expect("Should find hello derivation");
+    assert!(hello_drv.references.iter().any(|r| r.ends_with(".drv")));
+
+    let stats = nix_tree::path_stats::calculate_stats(&graph);
+    assert!(stats.contains_key(&paths[0]));
+
+    Ok(())
+}
@@ -0,0 +1,154 @@
+mod common;
+
+use common::{app, keys, path};
+use crossterm::event::{KeyCode, KeyEvent};
+use nix_tree::launch::{Launcher, Program, shell_dir, write_listing};
+use nix_tree::settings::Settings;
+use std::fs;
+use std::path::Path;
+
+fn listing(dir: &Path) -> String {
+    let mut out = Vec::new();
+    write_listing(dir, &mut out).unwrap();
+    String::from_utf8(out).unwrap()
+}
+
+/// A command running `script` with `sh`, which gets the path as `$0`
+fn sh(script: String) -> Option<Vec<String>> {
+    Some(vec!["sh".to_string(), "-c".to_string(), script])
+}
+
+/// A package with a binary, a symlink and nested directories
+fn package(dir: &Path) {
+    fs::create_dir_all(dir.join("bin")).unwrap();
+    fs::create_dir_all(dir.join("share/doc")).unwrap();
+    fs::write(dir.join("bin/hello"), "#!/bin/sh\n").unwrap();
+    fs::write(dir.join("share/doc/README"), "Hello\n").unwrap();
+    std::os::unix::fs::symlink("bin/hello", dir.join("hi")).unwrap();
+}
+
+#[test]
+fn test_launch_keys() {
+    let mut app = app();
+    keys(&mut app, "lj!");
+    assert_eq!(
+        app.launch.take(),
+        Some((Program::Shell, path('p', "python3-3.11.9")))
+    );
+
+    // The tile selected in the treemap
+    keys(&mut app, "to");
+    assert_eq!(
+        app.launch.take(),
+        Some((Program::Pager, path('k', "gtk-3.24")))
+    );
+    app.handle_key(KeyEvent::from(KeyCode::Esc)).unwrap();
+
+    // A path of a package, but not the package itself
+    keys(&mut app, "pb");
+    assert_eq!(app.launch, None);
+    app.handle_key(KeyEvent::from(KeyCode::Enter)).unwrap();
+    keys(&mut app, "jb");
+    assert!(matches!(app.launch.take(), Some((Program::Browser, _))));
+}
+
+#[test]
+fn test_run() {
+    let store = tempfile::tempdir().unwrap();
+    let out = tempfile::tempdir().unwrap();
+    let hello = store.path().join("hello");
+    package(&hello);
+    let output = out.path().join("output");
+    let read = || fs::read_to_string(&output).unwrap();
+
+    assert_eq!(
+        listing(&hello),
+        "bin/\nbin/hello\nhi -> bin/hello\nshare/\nshare/doc/\nshare/doc/README\n"
+    );
+
+    let launcher = Launcher {
+        shell: sh(format!("pwd -P > {}", output.display())),
+        // The path, if any, follows $0
+        pager: Some(vec![
+            "sh".to_string(),
+            "-c".to_string(),
+            format!("cat \"$@\" > {}", output.display()),
+            "sh".to_string(),
+        ]),
+        browser: sh(format!("echo \"$0\" > {}", output.display())),
+    };
+    launcher.run(Program::Shell, &hello).unwrap();
+    assert_eq!(
+        read().trim_end(),
+        hello.canonicalize().unwrap().to_str().unwrap()
+    );
+    // The package a store path links into, or just the store for a file
+    let link = store.path().join("hello-bin");
+    std::os::unix::fs::symlink(hello.join("bin/hello"), &link).unwrap();
+    launcher.run(Program::Shell, &link).unwrap();
+    assert_eq!(
+        read().trim_end(),
+        hello.canonicalize().unwrap().to_str().unwrap()
+    );
+    let file = store.path().join("hello.patch");
+    fs::write(&file, "").unwrap();
+    assert_eq!(
+        shell_dir(&file).unwrap(),
+        store.path().canonicalize().unwrap()
+    );
+
+    // Ctrl-C is for the program, not us
+    let interrupting = Launcher {
+        shell: sh("kill -INT $PPID".to_string()),
+        ..Default::default()
+    };
+    interrupting.run(Program::Shell, &hello).unwrap();
+
+    // A directory is listed on the pager's input
+    launcher.run(Program::Pager, &hello).unwrap();
+    assert_eq!(read(), listing(&hello));
+    launcher
+        .run(Program::Pager, &hello.join("share/doc/README"))
+        .unwrap();
+    assert_eq!(read(), "Hello\n");
+
+    launcher.run(Program::Browser, &hello).unwrap();
+    assert_eq!(read().trim_end(), hello.to_str().unwrap());
+
+    let error = |launcher: &Launcher, program, path: &Path| {
+        format!("{:#}", launcher.run(program, path).unwrap_err())
+    };
+    let failing = Launcher {
+        browser: Some(vec!["false".to_string()]),
+        ..Default::default()
+    };
+    assert!(error(&failing, Program::Browser, &hello).starts_with("false failed"));
+    let message = error(&Launcher::default(), Program::Browser, &hello);
+    assert!(message.contains("open.browser"), "{message}");
+    let message = error(&launcher, Program::Shell, &store.path().join("gone"));
+    assert!(message.contains("isn't in the local store"), "{message}");
+}
+
+#[test]
+fn test_parse_open() {
+    let settings = Settings::parse(
+        "[open]\nshell = \"bash\"\npager = \"less -R\"\nbrowser = [\"yazi\"]",
+        false,
+    )
+    .unwrap();
+    let launcher = settings.launcher;
+    assert_eq!(launcher.command_line(Program::Shell).unwrap(), ["bash"]);
+    assert_eq!(
+        launcher.command_line(Program::Pager).unwrap(),
+        ["less", "-R"]
+    );
+    assert_eq!(launcher.command_line(Program::Browser).unwrap(), ["yazi"]);
+
+    for (text, error) in [
+        ("[open]\npager = \"\"", "open.pager is empty"),
+        ("[open]\neditor = \"vi\"", "unknown field"),
+    ] {
+        let message = format!("{:#}", Settings::parse(text, false).unwrap_err());
+        assert!(message.contains(error), "{text:?}: {message}");
+    }
+}
@@ -0,0 +1,244 @@
+mod common;
+
+use common::store_path;
+use nix_tree::lint::{Rule, Rules, check, render_text};
+use nix_tree::path_stats;
+use nix_tree::store_path::StorePathGraph;
+use nix_tree::ui::{App, app::Highlight};
+use std::process::Command;
+
+/// image -> nginx -> {perl, zlib-dev -> zlib}, image -> app -> gcc-lib, with
+/// python only reachable through perl
+fn graph() -> StorePathGraph {
+    let gcc_lib = store_path('g', "gcc-13.2.0-lib", 1000, &[]);
+    let libgcc = store_path('l', "libgcc-13.2.0", 100, &[]);
+    let zlib = store_path('z', "zlib-1.3", 100, &[]);
+    let zlib_dev = store_path('d', "zlib-1.3-dev", 50, &[&zlib]);
+    let python = store_path('y', "python3-3.11.9", 8000, &[]);
+    let perl = store_path('p', "perl-5.38.2", 3000, &[&python]);
+    let nginx = store_path('n', "nginx-1.25.4", 500, &[&perl, &zlib_dev]);
+    let app = store_path('a', "app-1.0", 200, &[&gcc_lib, &libgcc]);
+    let image = store_path('i', "image", 10, &[&nginx, &app]);
+
+    let root = image.path.clone();
+    StorePathGraph::from_paths(
+        vec![
+            gcc_lib, libgcc, zlib, zlib_dev, python, perl, nginx, app, image,
+        ],
+        vec![root],
+    )
+}
+
+fn rules(toml: &str) -> Rules {
+    toml::from_str(toml).unwrap()
+}
+
+fn short_names(graph: &StorePathGraph, chain: &[String]) -> Vec<String> {
+    chain
+        .iter()
+        .map(|p| graph.get_path(p).unwrap().full_name().to_string())
+        .collect()
+}
+
+#[test]
+fn test_forbidden_dependencies() {
+    let graph = graph();
+    let violations = check(
+        &rules(
+            r#"
+[[rule]]
+forbid = ["gcc", "perl", "*-dev"]
+reason = "no build tools"
+"#,
+        ),
+        &graph,
+    );
+
+    // libgcc is a different package, and zlib isn't a -dev output. Equally
+    // long chains are ordered by path.
+    let found: Vec<(&str, &str)> = violations
+        .iter()
+        .map(|v| {
+            let name = graph.get_path(&v.path).unwrap().full_name();
+            (name, v.pattern.as_str())
+        })
+        .collect();
+    assert_eq!(
+        found,
+        [
+            ("zlib-1.3-dev", "*-dev"),
+            ("gcc-13.2.0-lib", "gcc"),
+            ("perl-5.38.2", "perl")
+        ]
+    );
+    assert_eq!(
+        short_names(&graph, &violations[0].chain),
+        ["image", "nginx-1.25.4", "zlib-1.3-dev"]
+    );
+    assert_eq!(violations[0].reason.as_deref(), Some("no build tools"));
+
+    let text = render_text(&violations, &graph);
+    assert!(text.starts_with("the closure must not contain gcc, perl, *-dev (no build tools):\n"));
+    assert!(text.contains("  perl-5.38.2 matches perl\n    image → nginx-1.25.4 → perl-5.38.2\n"));
+    assert!(text.ends_with("\n3 forbidden dependencies\n"));
+}
+
+#[test]
+fn test_closure_of() {
+    let graph = graph();
+    let violations = check(
+        &rules(
+            r#"
+[[rule]]
+closure_of = ["perl"]
+forbid = ["python3", "gcc"]
+"#,
+        ),
+        &graph,
+    );
+
+    // gcc isn't in perl's closure, and the chain leads up to perl from the
+    // root
+    assert_eq!(violations.len(), 1);
+    assert_eq!(
+        short_names(&graph, &violations[0].chain),
+        ["image", "nginx-1.25.4", "perl-5.38.2", "python3-3.11.9"]
+    );
+    assert_eq!(
+        violations[0].rule,
+        "the closure of perl must not contain python3, gcc"
+    );
+
+    let none = check(
+        &Rules {
+            rules: vec![Rule {
+                closure_of: vec!["app-*".to_string()],
+                forbid: vec!["perl".to_string()],
+                reason: None,
+            }],
+        },
+        &graph,
+    );
+    assert!(none.is_empty());
+    assert_eq!(render_text(&none, &graph), "No forbidden dependencies\n");
+
+    // The paths a rule is about don't break it themselves, only what they
+    // pull in does
+    let violations = check(
+        &rules(
+            r#"
+[[rule]]
+closure_of = ["perl"]
+forbid = ["perl*", "python3"]
+"#,
+        ),
+        &graph,
+    );
+    assert_eq!(violations.len(), 1);
+    assert!(violations[0].path.ends_with("python3-3.11.9"));
+}
+
+#[test]
+fn test_rules_reading_the_same() {
+    // Two rules with the same description are still listed apart
+    let graph = graph();
+    let violations = check(
+        &rules(
+            r#"
+[[rule]]
+forbid = ["perl"]
+reason = "too big"
+
+[[rule]]
+forbid = ["perl"]
+reason = "no interpreters"
+"#,
+        ),
+        &graph,
+    );
+    assert_eq!(violations.len(), 2);
+    assert_eq!(violations[0].rule_index, 0);
+    assert_eq!(violations[1].rule_index, 1);
+
+    let text = render_text(&violations, &graph);
+    assert!(text.contains(
+        "the closure must not contain perl (too big):
+"
+    ));
+    assert!(text.contains("\n\nthe closure must not contain perl (no interpreters):\n"));
+}
+
+#[test]
+fn test_load_rules() {
+    let dir = tempfile::tempdir().unwrap();
+    let file = dir.path().join("lint.toml");
+
+    std::fs::write(&file, "[[rule]]\nforbid = [\"gcc\"]\n").unwrap();
+    assert_eq!(Rules::load(&file).unwrap().rules.len(), 1);
+
+    for invalid in [
+        "[[rule]]\nforbid = []",
+        "[[rule]]\nclosure_of = [\"x\"]",
+        "[[rule]]\nforbid = [\"gcc\"]\nallow = [\"gcc\"]",
+    ] {
+        std::fs::write(&file, invalid).unwrap();
+        assert!(Rules::load(&file).is_err(), "{invalid:?} should not load");
+    }
+}
+
+#[test]
+fn test_highlight_violations() {
+    let graph = graph();
+    let violations = check(&rules("[[rule]]\nforbid = [\"perl\"]"), &graph);
+    let stats = path_stats::calculate_stats(&graph);
+    let mut app = App::new(graph, stats);
+    app.highlight_violations(&violations);
+
+    assert_eq!(app.highlights.len(), 1);
+    let (path, highlight) = app.highlights.iter().next().unwrap();
+    assert!(path.ends_with("perl-5.38.2"));
+    assert_eq!(*highlight, Highlight::Forbidden);
+}
+
+#[test]
+fn test_lint_command() {
+    let dump: serde_json::Map<String, serde_json::Value> = graph()
+        .paths()
+        .iter()
+        .map(|p| {
+            (
+                p.path.clone(),
+                serde_json::json!({ "narSize": p.nar_size, "references": p.references }),
+            )
+        })
+        .collect();
+    let dir = tempfile::tempdir().unwrap();
+    let closure = dir.path().join("closure.json");
+    std::fs::write(&closure, serde_json::to_string(&dump).unwrap()).unwrap();
+
+    let lint = |rules: &str| {
+        let file = dir.path().join("lint.toml");
+        std::fs::write(&file, rules).unwrap();
+        Command::new(env!("CARGO_BIN_EXE_nix-tree"))
+            .args(["lint", "--json", "--rules"])
+            .arg(&file)
+            .arg("--from-json")
+            .arg(&closure)
+            .output()
+            .unwrap()
+    };
+
+    // Violations, told apart from errors, which exit with 1
+    let output = lint("[[rule]]\nforbid = [\"*-dev\"]");
+    assert_eq!(output.status.code(), Some(2));
+    let violations: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(violations.as_array().unwrap().len(), 1);
+    assert_eq!(violations[0]["pattern"], "*-dev");
+    assert_eq!(violations[0]["chain"].as_array().unwrap().len(), 3);
+
+    let output = lint("[[rule]]\nforbid = [\"rust\"]");
+    assert!(output.status.success());
+
+    let output = lint("[[rule]]\nforbidden = [\"rust\"]");
+    assert_eq!(output.status.code(), Some(1));
+}
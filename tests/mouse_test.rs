@@ -0,0 +1,136 @@
+mod common;
+
+use common::{app, current};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers, MouseButton, MouseEvent, MouseEventKind};
+use nix_tree::ui::App;
+use nix_tree::ui::app::{Modal, View};
+use nix_tree::ui::treemap::squarify;
+use ratatui::layout::Rect;
+
+/// The terminal the events happen in: the panes are 36, 48 and 36 columns
+/// wide, with their first items on row 1
+const AREA: Rect = Rect::new(0, 0, 120, 30);
+
+fn mouse(app: &mut App, kind: MouseEventKind, column: u16, row: u16) {
+    let event = MouseEvent {
+        kind,
+        column,
+        row,
+        modifiers: KeyModifiers::NONE,
+    };
+    assert!(!app.handle_mouse(event, AREA).unwrap());
+}
+
+fn click(app: &mut App, column: u16, row: u16) {
+    mouse(app, MouseEventKind::Down(MouseButton::Left), column, row);
+}
+
+fn double_click(app: &mut App, column: u16, row: u16) {
+    click(app, column, row);
+    click(app, column, row);
+}
+
+#[test]
+fn test_click_panes() {
+    let mut app = app();
+
+    // A dependency is selected by moving into its pane
+    click(&mut app, 100, 2);
+    assert_eq!(current(&app), "python3-3.11.9");
+    assert_eq!(app.navigation_history.len(), 1);
+
+    // Clicking in the current pane selects, and double-clicking descends
+    click(&mut app, 50, 1);
+    assert_eq!(current(&app), "firefox-125.0");
+    double_click(&mut app, 50, 1);
+    assert_eq!(current(&app), "gtk-3.24");
+    assert_eq!(app.navigation_history.len(), 2);
+
+    // Below the last item
+    click(&mut app, 50, 10);
+    assert_eq!(current(&app), "gtk-3.24");
+
+    // A referrer other than the one we came from is reached from the roots
+    click(&mut app, 10, 2);
+    assert_eq!(current(&app), "python3-3.11.9");
+    assert_eq!(app.navigation_history.len(), 1);
+
+    // The one we came from, or the empty part of the pane, go back
+    click(&mut app, 10, 1);
+    assert_eq!(current(&app), "system");
+    assert!(app.navigation_history.is_empty());
+    click(&mut app, 100, 1);
+    click(&mut app, 10, 20);
+    assert_eq!(current(&app), "system");
+
+    // The wheel moves through the current pane
+    click(&mut app, 100, 1);
+    mouse(&mut app, MouseEventKind::ScrollDown, 50, 5);
+    mouse(&mut app, MouseEventKind::ScrollDown, 10, 5);
+    assert_eq!(current(&app), "bash-5.2");
+    mouse(&mut app, MouseEventKind::ScrollUp, 50, 5);
+    assert_eq!(current(&app), "python3-3.11.9");
+}
+
+#[test]
+fn test_click_why_depends() {
+    let mut app = app();
+    for c in "lll".chars() {
+        app.handle_key(KeyEvent::from(KeyCode::Char(c))).unwrap();
+    }
+    app.handle_key(KeyEvent::from(KeyCode::Char('w'))).unwrap();
+    let Some(Modal::WhyDepends { paths, .. }) = &app.modal else {
+        panic!("no why-depends modal");
+    };
+    let second = paths[1].clone();
+
+    // The modal takes the middle 108 columns and 18 rows, starting at row 6
+    mouse(&mut app, MouseEventKind::ScrollDown, 50, 10);
+    mouse(&mut app, MouseEventKind::ScrollUp, 50, 10);
+    click(&mut app, 20, 8);
+    let Some(Modal::WhyDepends { selected, .. }) = &app.modal else {
+        panic!("no why-depends modal");
+    };
+    assert_eq!(*selected, 1);
+
+    // Clicking it again makes a double-click
+    click(&mut app, 20, 8);
+    assert!(app.modal.is_none());
+    assert_eq!(app.current_path.as_ref(), second.last());
+    assert_eq!(app.navigation_history.len(), second.len() - 1);
+
+    // Clicking outside closes it
+    app.handle_key(KeyEvent::from(KeyCode::Char('w'))).unwrap();
+    assert!(app.modal.is_some());
+    click(&mut app, 0, 0);
+    assert!(app.modal.is_none());
+}
+
+#[test]
+fn test_click_tree_and_treemap() {
+    let mut app = app();
+    app.handle_key(KeyEvent::from(KeyCode::Char('v'))).unwrap();
+    assert_eq!(app.view, View::Tree);
+
+    // Rows start below the border and the header
+    double_click(&mut app, 30, 2);
+    assert_eq!(app.tree_rows().len(), 4);
+    click(&mut app, 30, 4);
+    assert_eq!(current(&app), "python3-3.11.9");
+    mouse(&mut app, MouseEventKind::ScrollDown, 30, 4);
+    assert_eq!(current(&app), "bash-5.2");
+
+    app.handle_key(KeyEvent::from(KeyCode::Char('h'))).unwrap();
+    app.handle_key(KeyEvent::from(KeyCode::Char('t'))).unwrap();
+    assert_eq!(app.view, View::Treemap);
+    let path = app.current_path.clone().unwrap();
+    let sizes: Vec<u64> = app.treemap_tiles(&path).iter().map(|t| t.1).collect();
+    let python = squarify(&sizes, Rect::new(1, 1, 118, 24))[1];
+    let (x, y) = (python.x + python.width / 2, python.y + python.height / 2);
+
+    click(&mut app, x, y);
+    assert_eq!(app.treemap_selected, 1);
+    click(&mut app, x, y);
+    assert_eq!(current(&app), "python3-3.11.9");
+    assert_eq!(app.view, View::Treemap);
+}
@@ -0,0 +1,85 @@
+//! Drives the nix CLI backend against stub `nix` and `nix-store` scripts.
+//! Kept in its own test binary because it changes `PATH`.
+
+use anyhow::Result;
+use nix_tree::backend::{LoadProgress, NixCliBackend, load_graph};
+use nix_tree::cli::Config;
+use std::os::unix::fs::PermissionsExt;
+use std::path::Path;
+
+const ROOT: &str = "/nix/store/aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa-root";
+const DEP: &str = "/nix/store/bbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbb-dep";
+const ROOT_DRV: &str = "/nix/store/dddddddddddddddddddddddddddddddd-root.drv";
+
+fn write_script(dir: &Path, name: &str, body: &str) -> Result<()> {
+    let path = dir.join(name);
+    std::fs::write(&path, format!("#!/bin/sh\n{body}"))?;
+    std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755))?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_missing_paths() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let log = dir.path().join("nix-store.log");
+
+    // Neither root is valid locally; the substituter only knows DEP
+    write_script(
+        dir.path(),
+        "nix",
+        &format!(
+            r#"case "$*" in
+  *"config show substituters"*) echo "https://cache.example.org" ;;
+  *"--store https://cache.example.org"*) echo '{{"{DEP}": {{"narSize": 50, "references": []}}}}' ;;
+  *"derivation show"*) echo '{{"{ROOT_DRV}": {{"outputs": {{"out": {{"path": "{ROOT}"}}}}}}}}' ;;
+  *"path-info"*) echo '{{"{ROOT}": null, "{DEP}": null}}' ;;
+esac
+"#
+        ),
+    )?;
+    write_script(
+        dir.path(),
+        "nix-store",
+        &format!("echo \"$*\" >> {}\n", log.display()),
+    )?;
+
+    let path = std::env::var("PATH").unwrap_or_default();
+    // SAFETY: this is the only test in this binary
+    unsafe { std::env::set_var("PATH", format!("{}:{path}", dir.path().display())) };
+
+    let installables = vec![ROOT.to_string(), DEP.to_string()];
+
+    // With --no-realise nothing is built and missing paths become placeholders
+    let backend = NixCliBackend::from_config(&Config {
+        no_realise: true,
+        ..Default::default()
+    });
+    let graph = load_graph(&backend, &installables, &LoadProgress::default()).await?;
+
+    assert!(!log.exists(), "nix-store must not run with --no-realise");
+    assert_eq!(graph.len(), 2);
+
+    let dep = graph.get_path(DEP).unwrap();
+    assert!(dep.missing);
+    assert_eq!(dep.nar_size, 50);
+
+    let root = graph.get_path(ROOT).unwrap();
+    assert!(root.missing);
+    assert_eq!(root.deriver.as_deref(), Some(ROOT_DRV));
+
+    // Otherwise each missing path is realised, honouring --store and --option
+    let backend = NixCliBackend::from_config(&Config {
+        store: Some("local".to_string()),
+        nix_options: vec![("substitute".to_string(), "false".to_string())],
+        ..Default::default()
+    });
+    load_graph(&backend, &installables, &LoadProgress::default()).await?;
+
+    let realised = std::fs::read_to_string(&log)?;
+    assert_eq!(realised.lines().count(), 2);
+    assert!(realised.contains(&format!(
+        "--option substitute false --store local --realise {ROOT}"
+    )));
+
+    Ok(())
+}
@@ -0,0 +1,160 @@
+mod common;
+
+use common::store_path;
+use crossterm::event::{KeyCode, KeyEvent};
+use nix_tree::package::{PackageName, compare_versions, group_packages, parse_drv_name};
+use nix_tree::path_stats;
+use nix_tree::store_path::StorePathGraph;
+use nix_tree::ui::App;
+use nix_tree::ui::app::{Modal, PackageRow, package_rows};
+use ratatui::Terminal;
+use ratatui::backend::TestBackend;
+use std::cmp::Ordering;
+
+fn name(pname: &str, version: &str, output: Option<&str>) -> PackageName {
+    PackageName {
+        pname: pname.to_string(),
+        version: version.to_string(),
+        output: output.map(str::to_string),
+    }
+}
+
+#[test]
+fn test_parse_drv_name() {
+    assert_eq!(parse_drv_name("hello-2.12.1"), ("hello", "2.12.1"));
+    assert_eq!(
+        parse_drv_name("nix-prefetch-git-1.0"),
+        ("nix-prefetch-git", "1.0")
+    );
+    assert_eq!(
+        parse_drv_name("openssl-3.0.13-dev"),
+        ("openssl", "3.0.13-dev")
+    );
+    assert_eq!(parse_drv_name("source"), ("source", ""));
+    assert_eq!(parse_drv_name("etc-"), ("etc-", ""));
+}
+
+#[test]
+fn test_compare_versions() {
+    // The cases from the Nix manual's description of builtins.compareVersions
+    let less = [
+        ("1.0", "2.3"),
+        ("2.1", "2.3"),
+        ("2.3", "2.5"),
+        ("2.3", "3.1"),
+        ("2.3", "2.3.1"),
+        ("2.3a", "2.3.1"),
+        ("2.3pre1", "2.3"),
+        ("2.3pre3", "2.3pre12"),
+        ("2.3a", "2.3c"),
+        ("2.3pre1", "2.3c"),
+        ("2.3pre1", "2.3q"),
+    ];
+    for (a, b) in less {
+        assert_eq!(compare_versions(a, b), Ordering::Less, "{a} < {b}");
+        assert_eq!(compare_versions(b, a), Ordering::Greater, "{b} > {a}");
+    }
+    assert_eq!(compare_versions("2.3", "2.3"), Ordering::Equal);
+    assert_eq!(compare_versions("2.3", "2-3"), Ordering::Equal);
+}
+
+#[test]
+fn test_package_name() {
+    assert_eq!(
+        PackageName::parse("openssl-3.0.13-dev"),
+        name("openssl", "3.0.13", Some("dev"))
+    );
+    assert_eq!(
+        PackageName::parse("gcc-13.2.0-lib"),
+        name("gcc", "13.2.0", Some("lib"))
+    );
+    assert_eq!(
+        PackageName::parse("util-linux-minimal-2.39.4-bin"),
+        name("util-linux-minimal", "2.39.4", Some("bin"))
+    );
+    assert_eq!(
+        PackageName::parse("hello-2.12.1"),
+        name("hello", "2.12.1", None)
+    );
+    assert_eq!(
+        PackageName::parse("hello-2.12.1.drv"),
+        name("hello", "2.12.1", None)
+    );
+    // Without a version the suffix is part of the name
+    assert_eq!(PackageName::parse("foo-bin"), name("foo-bin", "", None));
+    assert_eq!(PackageName::parse("source"), name("source", "", None));
+    assert_eq!(PackageName::parse("perl-5.38.2-man").output(), "man");
+    assert_eq!(PackageName::parse("perl-5.38.2").output(), "out");
+}
+
+#[test]
+fn test_group_packages() {
+    let openssl_dev = store_path('a', "openssl-3.0.13-dev", 300, &[]);
+    let openssl = store_path('b', "openssl-3.0.13", 1000, &[]);
+    let openssl_new = store_path('c', "openssl-3.3.1", 1200, &[]);
+    let hello = store_path(
+        'd',
+        "hello-2.12.1",
+        100,
+        &[&openssl, &openssl_new, &openssl_dev],
+    );
+
+    let root = hello.path.clone();
+    let graph =
+        StorePathGraph::from_paths(vec![openssl_dev, openssl, openssl_new, hello], vec![root]);
+
+    let groups = group_packages(&graph);
+    assert_eq!(groups.len(), 2);
+
+    let openssl = &groups[0];
+    assert_eq!(openssl.pname, "openssl");
+    assert_eq!(openssl.versions, vec!["3.0.13", "3.3.1"]);
+    assert_eq!(openssl.outputs, vec!["dev", "out"]);
+    assert_eq!(openssl.size, 2500);
+    assert_eq!(openssl.paths.len(), 3);
+    assert!(openssl.paths[2].ends_with("openssl-3.3.1"));
+
+    assert_eq!(groups[1].pname, "hello");
+
+    // The grouped view expands a package in place and can jump to its paths
+    let stats = path_stats::calculate_stats(&graph);
+    let mut app = App::new(graph, stats);
+    app.handle_key(KeyEvent::from(KeyCode::Char('p'))).unwrap();
+    app.handle_key(KeyEvent::from(KeyCode::Enter)).unwrap();
+    match &app.modal {
+        Some(Modal::Packages {
+            groups, expanded, ..
+        }) => {
+            let rows = package_rows(groups, expanded);
+            assert_eq!(rows.len(), 5);
+            assert_eq!(rows[1], PackageRow::Path(0, 0));
+        }
+        _ => panic!("packages modal should be open"),
+    }
+
+    let mut terminal = Terminal::new(TestBackend::new(80, 20)).unwrap();
+    terminal
+        .draw(|f| nix_tree::ui::widgets::render_modal(f, &app, f.area()))
+        .unwrap();
+    let buffer = terminal.backend().buffer();
+    let text: String = (0..buffer.area.height)
+        .map(|y| {
+            (0..buffer.area.width)
+                .map(|x| buffer.cell((x, y)).unwrap().symbol())
+                .collect::<String>()
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    assert!(text.contains("▾ openssl  3.0.13, 3.3.1  [dev, out] (2.4 KiB)"));
+    assert!(text.contains("    openssl-3.0.13-dev (300 B)"));
+
+    for key in [KeyCode::Down, KeyCode::Down, KeyCode::Down, KeyCode::Enter] {
+        app.handle_key(KeyEvent::from(key)).unwrap();
+    }
+    assert!(app.modal.is_none());
+    assert!(
+        app.current_path
+            .as_deref()
+            .is_some_and(|p| p.ends_with("openssl-3.3.1"))
+    );
+}
@@ -0,0 +1,109 @@
+mod common;
+
+use common::store_path;
+use nix_tree::path_stats;
+use nix_tree::report::Report;
+use nix_tree::store_path::{StorePath, StorePathGraph};
+use std::process::Command;
+
+/// system -> {firefox, python} -> glibc, firefox -> zlib
+fn graph() -> StorePathGraph {
+    let glibc = StorePath {
+        signatures: vec!["cache.nixos.org-1:abc".to_string()],
+        ..store_path('g', "glibc-2.39", 3000, &[])
+    };
+    let zlib = store_path('z', "zlib-1.3", 100, &[]);
+    let firefox = store_path('f', "firefox-125.0", 20_000, &[&glibc, &zlib]);
+    let python = store_path('p', "python3-3.11.9", 8000, &[&glibc]);
+    let system = store_path('s', "system", 10, &[&firefox, &python]);
+
+    let root = system.path.clone();
+    StorePathGraph::from_paths(vec![glibc, zlib, firefox, python, system], vec![root])
+}
+
+#[test]
+fn test_report() {
+    let graph = graph();
+    let stats = path_stats::calculate_stats(&graph);
+    let depths = path_stats::root_depths(&graph);
+    let report = Report::new(&graph, &stats, &depths, |_| true);
+
+    assert_eq!(report.roots, graph.roots);
+    assert_eq!(report.closure_size, 31_110);
+    let names: Vec<&str> = report.paths.iter().map(|p| p.name.as_str()).collect();
+    assert_eq!(
+        names,
+        [
+            "system",
+            "firefox-125.0",
+            "python3-3.11.9",
+            "glibc-2.39",
+            "zlib-1.3"
+        ]
+    );
+
+    let firefox = &report.paths[1];
+    assert_eq!(firefox.nar_size, 20_000);
+    assert_eq!(firefox.closure_size, 23_100);
+    assert_eq!(firefox.retained_size, 20_100);
+    assert_eq!(firefox.references.len(), 2);
+    assert!(firefox.references[0].ends_with("glibc-2.39"));
+    assert_eq!(firefox.referrers, [graph.roots[0].clone()]);
+    assert_eq!(firefox.depth, Some(1));
+
+    let glibc = &report.paths[3];
+    assert_eq!(glibc.signatures, ["cache.nixos.org-1:abc"]);
+    assert_eq!(glibc.referrers.len(), 2);
+    assert_eq!(glibc.depth, Some(2));
+
+    let text = report.render_text();
+    assert!(text.starts_with("NAME "));
+    assert!(text.contains("5 paths, 30.4 KiB in total"));
+}
+
+#[test]
+fn test_report_command() {
+    let dump: serde_json::Map<String, serde_json::Value> = graph()
+        .paths()
+        .iter()
+        .map(|p| {
+            (
+                p.path.clone(),
+                serde_json::json!({
+                    "narSize": p.nar_size,
+                    "references": p.references,
+                    "signatures": p.signatures,
+                }),
+            )
+        })
+        .collect();
+    let dir = tempfile::tempdir().unwrap();
+    let file = dir.path().join("closure.json");
+    std::fs::write(&file, serde_json::to_string(&dump).unwrap()).unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_nix-tree"))
+        .args(["report", "--json", "--from-json"])
+        .arg(&file)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    let report: Report = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(report.roots.len(), 1);
+    assert!(report.roots[0].ends_with("-system"));
+    assert_eq!(report.paths.len(), 5);
+    assert_eq!(report.paths[0].closure_size, 31_110);
+    assert_eq!(report.paths[3].signatures, ["cache.nixos.org-1:abc"]);
+
+    let output = Command::new(env!("CARGO_BIN_EXE_nix-tree"))
+        .args(["report", "--json", "--filter", "depth=1", "--from-json"])
+        .arg(&file)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let report: Report = serde_json::from_slice(&output.stdout).unwrap();
+    let names: Vec<&str> = report.paths.iter().map(|p| p.name.as_str()).collect();
+    assert_eq!(names, ["firefox-125.0", "python3-3.11.9"]);
+    // The total still covers the whole closure
+    assert_eq!(report.closure_size, 31_110);
+}
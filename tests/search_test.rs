@@ -0,0 +1,164 @@
+mod common;
+
+use common::{app, current, key};
+use crossterm::event::KeyCode;
+use nix_tree::fuzzy::{fuzzy_match, is_fuzzy_query};
+use nix_tree::ui::App;
+use nix_tree::ui::pane::render_panes;
+use ratatui::Terminal;
+use ratatui::backend::TestBackend;
+use ratatui::style::Modifier;
+
+/// Type `query`, opening the search box first if it isn't
+fn type_query(app: &mut App, query: &str) {
+    if !app.searching {
+        key(app, KeyCode::Char('/'));
+    }
+    for c in query.chars() {
+        key(app, KeyCode::Char(c));
+    }
+}
+
+#[test]
+fn test_fuzzy_match() {
+    let m = fuzzy_match("glc", "glibc-2.39").unwrap();
+    assert_eq!(m.positions, [0, 1, 4]);
+    assert!(fuzzy_match("GLC", "glibc-2.39").is_none());
+    assert!(fuzzy_match("cgl", "glibc-2.39").is_none());
+    assert_eq!(fuzzy_match("BC", "libBC").unwrap().positions, [3, 4]);
+
+    // The start of a word beats an earlier match in the middle of one
+    assert_eq!(fuzzy_match("py", "happy-python").unwrap().positions, [6, 7]);
+
+    // Consecutive and word-start matches score higher
+    let score = |query, text| fuzzy_match(query, text).unwrap().score;
+    assert!(score("fire", "firefox") > score("fire", "fi-r-e"));
+    assert!(score("gtk", "gtk-3.24") > score("gtk", "gnome-toolkit"));
+    assert!(score("gtk", "gnome-toolkit") > score("gtk", "agitoks"));
+}
+
+#[test]
+fn test_fuzzy_queries() {
+    assert!(is_fuzzy_query("firefox"));
+    assert!(is_fuzzy_query(" ffx "));
+    for query in [
+        "",
+        "name=*-dev",
+        "*-dev",
+        "closure>1MB",
+        "a b",
+        "unsigned",
+        "(x)",
+    ] {
+        assert!(!is_fuzzy_query(query), "{query:?} is a filter");
+    }
+}
+
+#[test]
+fn test_search_as_you_type() {
+    let mut app = app();
+    key(&mut app, KeyCode::Char('l'));
+    key(&mut app, KeyCode::Char('j'));
+    let before = app.current_items.clone();
+    let history = app.navigation_history.clone();
+
+    // The pane keeps its items, and the best match is selected
+    type_query(&mut app, "b");
+    assert_eq!(app.current_items, before);
+    assert_eq!(app.search_matches, Some(1));
+    assert_eq!(current(&app), "bash-5.2");
+
+    // The arrows move between the matches
+    key(&mut app, KeyCode::Backspace);
+    type_query(&mut app, "h");
+    assert_eq!(app.search_matches, Some(2));
+    assert_eq!(current(&app), "python3-3.11.9");
+    key(&mut app, KeyCode::Down);
+    assert_eq!(current(&app), "bash-5.2");
+    key(&mut app, KeyCode::Down);
+    assert_eq!(current(&app), "python3-3.11.9");
+    key(&mut app, KeyCode::Up);
+    assert_eq!(current(&app), "bash-5.2");
+
+    // Paths outside the pane don't match
+    key(&mut app, KeyCode::Backspace);
+    type_query(&mut app, "glibc");
+    assert_eq!(app.search_matches, Some(0));
+    assert_eq!(current(&app), "python3-3.11.9");
+
+    // Esc restores the pane, selection included
+    key(&mut app, KeyCode::Char('b'));
+    key(&mut app, KeyCode::Esc);
+    assert!(!app.searching);
+    assert_eq!(app.current_items, before);
+    assert_eq!(current(&app), "python3-3.11.9");
+    assert_eq!(app.navigation_history, history);
+    assert_eq!(app.last_search, None);
+
+    // Enter keeps the selected match in the same pane
+    type_query(&mut app, "bash");
+    key(&mut app, KeyCode::Enter);
+    assert!(!app.searching);
+    assert_eq!(app.current_items, before);
+    assert_eq!(current(&app), "bash-5.2");
+    assert_eq!(app.last_search.as_deref(), Some("bash"));
+    assert!(app.filter.is_none());
+    assert_eq!(app.navigation_history, history);
+    key(&mut app, KeyCode::Char('h'));
+    assert_eq!(current(&app), "system");
+}
+
+#[test]
+fn test_jump_between_matches() {
+    let mut app = app();
+    key(&mut app, KeyCode::Char('l'));
+    type_query(&mut app, "h");
+    key(&mut app, KeyCode::Enter);
+    assert_eq!(app.current_items.len(), 3);
+    assert_eq!(current(&app), "python3-3.11.9");
+
+    key(&mut app, KeyCode::Char('n'));
+    assert_eq!(current(&app), "bash-5.2");
+    key(&mut app, KeyCode::Char('n'));
+    assert_eq!(current(&app), "python3-3.11.9");
+    key(&mut app, KeyCode::Char('N'));
+    assert_eq!(current(&app), "bash-5.2");
+
+    app.last_search = Some("zzz".to_string());
+    key(&mut app, KeyCode::Char('n'));
+    assert_eq!(current(&app), "bash-5.2");
+    assert_eq!(
+        app.message.as_deref(),
+        Some("No matches for zzz in this pane")
+    );
+}
+
+#[test]
+fn test_match_highlighting() {
+    let mut app = app();
+    type_query(&mut app, "pyt3");
+
+    let mut terminal = Terminal::new(TestBackend::new(120, 10)).unwrap();
+    terminal.draw(|f| render_panes(f, &app, f.area())).unwrap();
+    let buffer = terminal.backend().buffer();
+
+    let (x, y) = (0..buffer.area.height)
+        .find_map(|y| {
+            let row: String = (0..buffer.area.width)
+                .map(|x| buffer.cell((x, y)).unwrap().symbol())
+                .collect();
+            let x = row.find("python3")?;
+            Some((row[..x].chars().count() as u16, y))
+        })
+        .unwrap();
+    let underlined: Vec<bool> = (x..x + 7)
+        .map(|x| {
+            buffer
+                .cell((x, y))
+                .unwrap()
+                .modifier
+                .contains(Modifier::UNDERLINED)
+        })
+        .collect();
+    assert_eq!(underlined, [true, true, true, false, false, false, true]);
+}
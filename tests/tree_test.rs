@@ -0,0 +1,155 @@
+mod common;
+
+use common::{app, current, key};
+use crossterm::event::KeyCode;
+use nix_tree::ui::App;
+use nix_tree::ui::app::View;
+use nix_tree::ui::tree::render_tree;
+use ratatui::Terminal;
+use ratatui::backend::TestBackend;
+
+fn names(app: &App, chain: &[String]) -> Vec<String> {
+    chain
+        .iter()
+        .map(|p| app.graph.get_path(p).unwrap().full_name().to_string())
+        .collect()
+}
+
+fn selected(app: &App) -> Vec<String> {
+    names(app, &app.tree_selected)
+}
+
+#[test]
+fn test_expand_and_collapse() {
+    let mut app = app();
+    key(&mut app, KeyCode::Char('v'));
+    assert_eq!(app.view, View::Tree);
+    assert_eq!(selected(&app), ["system"]);
+    assert_eq!(app.tree_rows().len(), 1);
+
+    key(&mut app, KeyCode::Char('l'));
+    key(&mut app, KeyCode::Char('j'));
+    key(&mut app, KeyCode::Enter);
+    // Expanded already, so go to the first reference
+    key(&mut app, KeyCode::Char('l'));
+    assert_eq!(selected(&app), ["system", "firefox-125.0", "gtk-3.24"]);
+    assert_eq!(current(&app), "gtk-3.24");
+
+    // gtk's glibc is shown right below it, under firefox
+    let rows: Vec<String> = app
+        .tree_rows()
+        .iter()
+        .map(|r| names(&app, &r.chain).join("/"))
+        .collect();
+    assert_eq!(
+        rows,
+        [
+            "system",
+            "system/firefox-125.0",
+            "system/firefox-125.0/gtk-3.24",
+            "system/firefox-125.0/glibc-2.39",
+            "system/python3-3.11.9",
+            "system/bash-5.2",
+        ]
+    );
+
+    // Collapsing, then going to the parent
+    key(&mut app, KeyCode::Char('k'));
+    key(&mut app, KeyCode::Char('h'));
+    assert_eq!(app.tree_rows().len(), 4);
+    key(&mut app, KeyCode::Char('h'));
+    assert_eq!(selected(&app), ["system"]);
+    key(&mut app, KeyCode::Char('h'));
+    assert_eq!(app.tree_rows().len(), 1);
+}
+
+#[test]
+fn test_repeated_paths() {
+    let mut app = app();
+    key(&mut app, KeyCode::Char('v'));
+    key(&mut app, KeyCode::Char('l'));
+    key(&mut app, KeyCode::Char('j'));
+    key(&mut app, KeyCode::Char('l'));
+    key(&mut app, KeyCode::Char('j'));
+    key(&mut app, KeyCode::Char('j'));
+    key(&mut app, KeyCode::Char('j'));
+    assert_eq!(selected(&app), ["system", "python3-3.11.9"]);
+    key(&mut app, KeyCode::Char('l'));
+    key(&mut app, KeyCode::Char('l'));
+
+    // python's gtk was shown under firefox already
+    let rows = app.tree_rows();
+    let row = rows.iter().find(|r| r.chain == app.tree_selected).unwrap();
+    assert_eq!(selected(&app), ["system", "python3-3.11.9", "gtk-3.24"]);
+    assert!(row.repeated && row.has_children && !row.expanded);
+
+    // Expanding it goes to where it was first shown
+    key(&mut app, KeyCode::Char('l'));
+    assert_eq!(selected(&app), ["system", "firefox-125.0", "gtk-3.24"]);
+    let rows = app.tree_rows();
+    assert!(rows[2].expanded);
+    assert!(!rows[3].repeated);
+    assert_eq!(names(&app, &rows[3].chain)[3], "glibc-2.39");
+    assert!(rows[4].repeated);
+}
+
+#[test]
+fn test_switching_views() {
+    let mut app = app();
+
+    // The tree opens at the path selected in the panes
+    key(&mut app, KeyCode::Char('l'));
+    key(&mut app, KeyCode::Char('j'));
+    key(&mut app, KeyCode::Char('l'));
+    assert_eq!(current(&app), "gtk-3.24");
+    key(&mut app, KeyCode::Char('v'));
+    assert_eq!(selected(&app), ["system", "python3-3.11.9", "gtk-3.24"]);
+
+    // and the panes pick up the path selected in the tree
+    key(&mut app, KeyCode::Char('h'));
+    key(&mut app, KeyCode::Char('k'));
+    assert_eq!(selected(&app), ["system", "firefox-125.0"]);
+    key(&mut app, KeyCode::Esc);
+    assert_eq!(app.view, View::Panes);
+    assert_eq!(current(&app), "firefox-125.0");
+    assert_eq!(app.navigation_history.len(), 1);
+    assert_eq!(app.next_items.len(), 2);
+
+    // Searching from the tree selects the first match in it
+    key(&mut app, KeyCode::Char('v'));
+    key(&mut app, KeyCode::Char('/'));
+    for c in "glibc".chars() {
+        key(&mut app, KeyCode::Char(c));
+    }
+    key(&mut app, KeyCode::Enter);
+    assert_eq!(app.view, View::Tree);
+    assert_eq!(current(&app), "glibc-2.39");
+    assert_eq!(selected(&app).last().unwrap(), "glibc-2.39");
+}
+
+#[test]
+fn test_render_tree() {
+    let mut app = app();
+    for c in "vljljjjl".chars() {
+        key(&mut app, KeyCode::Char(c));
+    }
+
+    let mut terminal = Terminal::new(TestBackend::new(80, 12)).unwrap();
+    terminal.draw(|f| render_tree(f, &app, f.area())).unwrap();
+    let buffer = terminal.backend().buffer();
+    let lines: Vec<String> = (0..buffer.area.height)
+        .map(|y| {
+            (0..buffer.area.width)
+                .map(|x| buffer.cell((x, y)).unwrap().symbol())
+                .collect::<String>()
+        })
+        .collect();
+
+    assert!(lines[1].contains("Path") && lines[1].trim_end().ends_with("Retained│"));
+    assert!(lines[2].contains("▾   system"));
+    assert!(lines[4].contains("│  ├─ ▸   gtk-3.24"));
+    assert!(lines[6].contains("├─ ▾   python3-3.11.9"));
+    assert!(lines[7].contains("│  └─     gtk-3.24 [...]"));
+    assert!(lines[8].contains("└─     bash-5.2"));
+    assert!(lines[8].contains("1000 B"));
+}
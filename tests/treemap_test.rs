@@ -0,0 +1,190 @@
+mod common;
+
+use common::store_path;
+use crossterm::event::{KeyCode, KeyEvent};
+use nix_tree::path_stats::{self, added_sizes};
+use nix_tree::store_path::StorePathGraph;
+use nix_tree::ui::App;
+use nix_tree::ui::app::{TreemapSize, View};
+use nix_tree::ui::treemap::{render_treemap, squarify};
+use ratatui::Terminal;
+use ratatui::backend::TestBackend;
+use ratatui::layout::Rect;
+
+/// system -> {firefox, python, bash}, firefox -> {glibc, gtk}, python -> glibc
+fn graph() -> StorePathGraph {
+    let glibc = store_path('g', "glibc-2.39", 3000, &[]);
+    let gtk = store_path('k', "gtk-3.24", 4000, &[]);
+    let firefox = store_path('f', "firefox-125.0", 20_000, &[&glibc, &gtk]);
+    let python = store_path('p', "python3-3.11.9", 8000, &[&glibc]);
+    let bash = store_path('b', "bash-5.2", 1000, &[]);
+    let system = store_path('s', "system", 10, &[&firefox, &python, &bash]);
+
+    let root = system.path.clone();
+    StorePathGraph::from_paths(vec![glibc, gtk, firefox, python, bash, system], vec![root])
+}
+
+fn key(app: &mut App, code: KeyCode) {
+    app.handle_key(KeyEvent::from(code)).unwrap();
+}
+
+fn name(app: &App, path: &str) -> String {
+    app.graph.get_path(path).unwrap().full_name().to_string()
+}
+
+#[test]
+fn test_squarify() {
+    let area = Rect::new(2, 1, 60, 20);
+    let sizes = [600, 600, 400, 300, 200, 200, 100, 0];
+    let rects = squarify(&sizes, area);
+    assert_eq!(rects.len(), sizes.len());
+
+    // The tiles cover the area exactly, without overlapping
+    let covered: u32 = rects.iter().map(|r| u32::from(r.area())).sum();
+    assert_eq!(covered, u32::from(area.area()));
+    for (i, a) in rects.iter().enumerate() {
+        assert_eq!(a.intersection(area), *a);
+        for b in &rects[i + 1..] {
+            assert!(!a.intersects(*b), "{a:?} overlaps {b:?}");
+        }
+    }
+
+    // Areas follow the sizes, give or take rounding to whole cells
+    let total: u64 = sizes.iter().sum();
+    for (size, rect) in sizes.iter().zip(&rects) {
+        let expected = *size as f64 / total as f64 * f64::from(area.area());
+        assert!((f64::from(rect.area()) - expected).abs() <= 0.2 * expected + 20.0);
+    }
+    assert!(rects[7].is_empty());
+
+    // Nothing to lay out
+    assert!(squarify(&[0, 0], area).iter().all(|r| r.is_empty()));
+    assert!(squarify(&[1], Rect::new(0, 0, 0, 5))[0].is_empty());
+}
+
+#[test]
+fn test_added_sizes() {
+    let graph = graph();
+    let ids: Vec<usize> = ["firefox-125.0", "python3-3.11.9", "bash-5.2"]
+        .iter()
+        .map(|name| graph.paths().iter().position(|p| p.name == *name).unwrap())
+        .collect();
+
+    // glibc is shared by firefox and python, so neither adds it
+    assert_eq!(added_sizes(&graph, &ids), [24_000, 8000, 1000]);
+    assert_eq!(added_sizes(&graph, &ids[..1]), [27_000]);
+}
+
+#[test]
+fn test_treemap_navigation() {
+    let graph = graph();
+    let stats = path_stats::calculate_stats(&graph);
+    let mut app = App::new(graph, stats);
+
+    key(&mut app, KeyCode::Char('t'));
+    assert_eq!(app.view, View::Treemap);
+    let system = app.current_path.clone().unwrap();
+    let tiles = app.treemap_tiles(&system);
+    let names: Vec<String> = tiles.iter().map(|(p, _)| name(&app, p)).collect();
+    assert_eq!(names, ["firefox-125.0", "python3-3.11.9", "bash-5.2"]);
+    assert_eq!(tiles[0].1, 24_000);
+
+    // Select python and drill in, as moving right in the panes would
+    key(&mut app, KeyCode::Char('j'));
+    key(&mut app, KeyCode::Enter);
+    assert_eq!(app.view, View::Treemap);
+    assert_eq!(
+        name(&app, app.current_path.as_ref().unwrap()),
+        "python3-3.11.9"
+    );
+    assert_eq!(app.navigation_history.len(), 1);
+    assert_eq!(app.current_items.len(), 3);
+
+    // Nothing below glibc to drill into
+    key(&mut app, KeyCode::Char('l'));
+    key(&mut app, KeyCode::Char('l'));
+    assert_eq!(name(&app, app.current_path.as_ref().unwrap()), "glibc-2.39");
+    assert_eq!(app.navigation_history.len(), 2);
+
+    // Drilling out selects the tile we came from
+    key(&mut app, KeyCode::Char('h'));
+    key(&mut app, KeyCode::Char('h'));
+    assert_eq!(app.current_path.as_ref(), Some(&system));
+    assert_eq!(app.treemap_selected, 1);
+    assert!(app.navigation_history.is_empty());
+
+    // Added sizes leave out what the references share
+    key(&mut app, KeyCode::Char('s'));
+    assert_eq!(app.treemap_size, TreemapSize::Added);
+    let tiles = app.treemap_tiles(&system);
+    assert_eq!(tiles[0].1, 24_000);
+    assert_eq!(tiles[1].1, 8000);
+
+    // The panes pick up where the treemap left off
+    key(&mut app, KeyCode::Enter);
+    key(&mut app, KeyCode::Char('t'));
+    assert_eq!(app.view, View::Panes);
+    assert_eq!(
+        name(&app, app.current_path.as_ref().unwrap()),
+        "firefox-125.0"
+    );
+    key(&mut app, KeyCode::Left);
+    assert_eq!(app.current_path.as_ref(), Some(&system));
+}
+
+#[test]
+fn test_treemap_tiles_follow_filter() {
+    let graph = graph();
+    let stats = path_stats::calculate_stats(&graph);
+    let mut app = App::new(graph, stats);
+    key(&mut app, KeyCode::Char('t'));
+    let system = app.current_path.clone().unwrap();
+    assert_eq!(app.treemap_tiles(&system).len(), 3);
+
+    // The tiles drawn before are limited to the new filter
+    key(&mut app, KeyCode::Char('/'));
+    for c in "nar>5K".chars() {
+        key(&mut app, KeyCode::Char(c));
+    }
+    key(&mut app, KeyCode::Enter);
+    let names: Vec<String> = app
+        .treemap_tiles(&system)
+        .iter()
+        .map(|(p, _)| name(&app, p))
+        .collect();
+    assert_eq!(names, ["firefox-125.0", "python3-3.11.9"]);
+
+    // And to no filter once it's cleared
+    key(&mut app, KeyCode::Char('/'));
+    while !app.search_query.is_empty() {
+        key(&mut app, KeyCode::Backspace);
+    }
+    key(&mut app, KeyCode::Enter);
+    assert_eq!(app.treemap_tiles(&system).len(), 3);
+}
+
+#[test]
+fn test_render_treemap() {
+    let graph = graph();
+    let stats = path_stats::calculate_stats(&graph);
+    let app = App::new(graph, stats);
+
+    let mut terminal = Terminal::new(TestBackend::new(100, 30)).unwrap();
+    terminal
+        .draw(|f| render_treemap(f, &app, f.area()))
+        .unwrap();
+    let buffer = terminal.backend().buffer();
+    let text: String = (0..buffer.area.height)
+        .map(|y| {
+            (0..buffer.area.width)
+                .map(|x| buffer.cell((x, y)).unwrap().symbol())
+                .collect::<String>()
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    assert!(text.contains("Treemap of system by retained size | firefox-125.0 (23.4 KiB)"));
+    assert!(text.contains("python3-3.11.9 (7.8 KiB)"));
+    // firefox is big enough to show its own references
+    assert!(text.contains("gtk-3.24 (3.9 KiB)"));
+}
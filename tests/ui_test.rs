@@ -20,7 +20,7 @@ async fn test_initial_dependencies_loading() {
         .await
         .unwrap();
 
-    println!("Graph loaded with {} paths", graph.paths.len());
+    println!("Graph loaded with {} paths", graph.len());
     println!("Root paths: {:?}", graph.roots);
 
     // Check if the root path has references
@@ -83,6 +83,7 @@ fn test_ranger_navigation() {
             "/nix/store/ccc-dep2".to_string(),
         ],
         signatures: vec![],
+        ..Default::default()
     };
 
     let dep1 = nix_tree::store_path::StorePath {
@@ -96,6 +97,7 @@ fn test_ranger_navigation() {
             "/nix/store/eee-dep1-2".to_string(),
         ],
         signatures: vec![],
+        ..Default::default()
     };
 
     let dep2 = nix_tree::store_path::StorePath {
@@ -106,6 +108,7 @@ fn test_ranger_navigation() {
         closure_size: Some(300),
         references: vec![],
         signatures: vec![],
+        ..Default::default()
     };
 
     let dep1_1 = nix_tree::store_path::StorePath {
@@ -116,6 +119,7 @@ fn test_ranger_navigation() {
         closure_size: Some(100),
         references: vec![],
         signatures: vec![],
+        ..Default::default()
     };
 
     let dep1_2 = nix_tree::store_path::StorePath {
@@ -126,6 +130,7 @@ fn test_ranger_navigation() {
         closure_size: Some(100),
         references: vec![],
         signatures: vec![],
+        ..Default::default()
     };
 
     graph.add_path(root);
@@ -201,6 +206,7 @@ fn test_update_panes_logic() {
             "/nix/store/shared".to_string(),
         ],
         signatures: vec![],
+        ..Default::default()
     };
 
     let dep1 = nix_tree::store_path::StorePath {
@@ -214,6 +220,7 @@ fn test_update_panes_logic() {
             "/nix/store/dep1-only".to_string(),
         ],
         signatures: vec![],
+        ..Default::default()
     };
 
     let dep2 = nix_tree::store_path::StorePath {
@@ -227,6 +234,7 @@ fn test_update_panes_logic() {
             "/nix/store/dep2-only".to_string(),
         ],
         signatures: vec![],
+        ..Default::default()
     };
 
     let shared = nix_tree::store_path::StorePath {
@@ -237,6 +245,7 @@ fn test_update_panes_logic() {
         closure_size: Some(200),
         references: vec![],
         signatures: vec![],
+        ..Default::default()
     };
 
     let dep1_only = nix_tree::store_path::StorePath {
@@ -247,6 +256,7 @@ fn test_update_panes_logic() {
         closure_size: Some(100),
         references: vec![],
         signatures: vec![],
+        ..Default::default()
     };
 
     let dep2_only = nix_tree::store_path::StorePath {
@@ -257,6 +267,7 @@ fn test_update_panes_logic() {
         closure_size: Some(150),
         references: vec![],
         signatures: vec![],
+        ..Default::default()
     };
 
     graph.add_path(root);